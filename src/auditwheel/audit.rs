@@ -1,15 +1,18 @@
 use super::musllinux::{find_musl_libc, get_musl_version};
-use super::policy::{Policy, MANYLINUX_POLICIES, MUSLLINUX_POLICIES};
+use super::policy::{Policies, Policy};
 use crate::auditwheel::{find_external_libs, PlatformTag};
 use crate::compile::BuildArtifact;
-use crate::target::Target;
+use crate::target::{Arch, Target};
 use anyhow::{bail, Context, Result};
 use fs_err::File;
-use goblin::elf::{sym::STT_FUNC, Elf};
+use glob::Pattern;
+use goblin::elf::{section_header::SHN_UNDEF, Elf};
 use lddtree::Library;
 use once_cell::sync::Lazy;
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
 use std::io;
 use std::io::Read;
 use std::path::{Path, PathBuf};
@@ -61,12 +64,39 @@ pub enum AuditWheelError {
     DependencyAnalysisError(#[source] lddtree::Error),
 }
 
+/// What to do when a library isn't compliant with the manylinux or musllinux policy
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum AuditWheelMode {
+    /// Copy the external shared libraries into the wheel
+    #[default]
+    Repair,
+    /// Fail if external shared libraries would need to be copied into the wheel
+    Check,
+    /// Print a warning and build the wheel with the native `linux` tag instead of failing
+    Warn,
+    /// Don't check for compliance
+    Skip,
+}
+
+impl fmt::Display for AuditWheelMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditWheelMode::Repair => f.write_str("repair"),
+            AuditWheelMode::Check => f.write_str("check"),
+            AuditWheelMode::Warn => f.write_str("warn"),
+            AuditWheelMode::Skip => f.write_str("skip"),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct VersionedLibrary {
     /// library name
     pub name: String,
     /// versions needed
-    versions: HashSet<String>,
+    pub(crate) versions: HashSet<String>,
 }
 
 /// Find required dynamic linked libraries with version information
@@ -95,47 +125,129 @@ pub fn find_versioned_libraries(elf: &Elf) -> Vec<VersionedLibrary> {
     symbols
 }
 
-/// Find incompliant symbols from symbol versions
-#[allow(clippy::result_large_err)]
-fn find_incompliant_symbols(
-    elf: &Elf,
-    symbol_versions: &[String],
-) -> Result<Vec<String>, AuditWheelError> {
-    let mut symbols = Vec::new();
-    let strtab = &elf.strtab;
-    for sym in &elf.syms {
-        if sym.st_type() == STT_FUNC {
-            let name = strtab.get_at(sym.st_name).unwrap_or("BAD NAME");
-            for symbol_version in symbol_versions {
-                if name.ends_with(&format!("@{symbol_version}")) {
-                    symbols.push(name.to_string());
+/// The undefined dynamic symbols of an elf file by library and symbol version, e.g. `exp` and
+/// `log` for `GLIBC_2.29` from `libm.so.6`
+pub fn find_versioned_symbols(elf: &Elf) -> HashMap<String, HashMap<String, Vec<String>>> {
+    let mut versions = HashMap::new();
+    if let Some(verneed) = &elf.verneed {
+        for need_file in verneed.iter() {
+            let Some(library) = elf.dynstrtab.get_at(need_file.vn_file) else {
+                continue;
+            };
+            for need_ver in need_file.iter() {
+                if let Some(version) = elf.dynstrtab.get_at(need_ver.vna_name) {
+                    versions.insert(need_ver.vna_other, (library, version));
                 }
             }
         }
     }
-    Ok(symbols)
+    let mut symbols: HashMap<String, HashMap<String, Vec<String>>> = HashMap::new();
+    let Some(versym) = &elf.versym else {
+        return symbols;
+    };
+    for (idx, sym) in elf.dynsyms.iter().enumerate() {
+        if sym.st_shndx != SHN_UNDEF as usize {
+            continue;
+        }
+        let Some(version) = versym.get_at(idx) else {
+            continue;
+        };
+        let (Some((library, version)), Some(name)) = (
+            versions.get(&version.version()),
+            elf.dynstrtab.get_at(sym.st_name),
+        ) else {
+            continue;
+        };
+        let names = symbols
+            .entry(library.to_string())
+            .or_default()
+            .entry(version.to_string())
+            .or_default();
+        names.push(name.to_string());
+        names.sort();
+        names.dedup();
+    }
+    symbols
 }
 
+/// Orders symbol versions such as `GLIBC_2.2.5` and `GLIBC_2.17` by their numeric parts
+pub(super) fn version_key(version: &str) -> (String, Vec<u32>) {
+    let (name, number) = version.split_once('_').unwrap_or((version, ""));
+    let numbers = number
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect();
+    (name.to_string(), numbers)
+}
+
+/// A symbol version that is newer than a policy allows
+#[derive(Debug, Clone, Serialize)]
+pub struct TooNewVersion {
+    /// The library the version is needed from, e.g. `libm.so.6`
+    pub library: String,
+    /// The symbol version, e.g. `GLIBC_2.29`
+    pub version: String,
+    /// The symbols that need this version, e.g. `exp` and `log`
+    pub symbols: Vec<String>,
+}
+
+impl fmt::Display for TooNewVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.symbols.is_empty() {
+            write!(f, "{} {}", self.library, self.version)
+        } else {
+            write!(
+                f,
+                "{} {}: {}",
+                self.library,
+                self.version,
+                self.symbols.join(", ")
+            )
+        }
+    }
+}
+
+/// Everything that keeps an elf file from satisfying a policy
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PolicyViolations {
+    /// The symbol versions that are too new, sorted by library and version
+    pub too_new_versions: Vec<TooNewVersion>,
+    /// The black-listed symbols that are used, by library
+    pub blacklisted_symbols: BTreeMap<String, Vec<String>>,
+    /// The linked libraries that aren't allowed by the policy
+    pub forbidden_libraries: Vec<String>,
+}
+
+impl PolicyViolations {
+    /// Whether the policy is satisfied
+    pub fn is_empty(&self) -> bool {
+        self.too_new_versions.is_empty()
+            && self.blacklisted_symbols.is_empty()
+            && self.forbidden_libraries.is_empty()
+    }
+}
+
+/// Collects all violations of a policy instead of stopping at the first kind like
+/// [policy_is_satisfied]
 #[allow(clippy::result_large_err)]
-fn policy_is_satisfied(
+pub(super) fn find_policy_violations(
     policy: &Policy,
     elf: &Elf,
     arch: &str,
     deps: &[String],
     versioned_libraries: &[VersionedLibrary],
     allow_linking_libpython: bool,
-) -> Result<(), AuditWheelError> {
+) -> Result<PolicyViolations, AuditWheelError> {
     let arch_versions = &policy.symbol_versions.get(arch).ok_or_else(|| {
         AuditWheelError::UnsupportedArchitecture(policy.clone(), arch.to_string())
     })?;
-    let mut offending_libs = HashSet::new();
-    let mut offending_versioned_syms = HashSet::new();
-    let mut offending_blacklist_syms = HashMap::new();
+    let mut offending_libs = BTreeSet::new();
+    let mut violations = PolicyViolations::default();
     let undef_symbols: HashSet<String> = elf
         .dynsyms
         .iter()
         .filter_map(|sym| {
-            if sym.st_shndx == goblin::elf::section_header::SHN_UNDEF as usize {
+            if sym.st_shndx == SHN_UNDEF as usize {
                 elf.dynstrtab.get_at(sym.st_name).map(ToString::to_string)
             } else {
                 None
@@ -158,55 +270,63 @@ fn policy_is_satisfied(
             let mut intersection: Vec<_> = sym_list.intersection(&undef_symbols).cloned().collect();
             if !intersection.is_empty() {
                 intersection.sort();
-                offending_blacklist_syms.insert(dep, intersection);
+                violations
+                    .blacklisted_symbols
+                    .insert(dep.clone(), intersection);
             }
         }
     }
+    let versioned_symbols = find_versioned_symbols(elf);
     for library in versioned_libraries {
         if !policy.lib_whitelist.contains(&library.name) {
             offending_libs.insert(library.name.clone());
             continue;
         }
-        let mut versions: HashMap<String, HashSet<String>> = HashMap::new();
-        for v in &library.versions {
-            let (name, version) = v.split_once('_').unwrap();
-            versions
-                .entry(name.to_string())
-                .or_default()
-                .insert(version.to_string());
-        }
-        for (name, versions_needed) in versions.iter() {
-            let versions_allowed = &arch_versions[name];
-            if !versions_needed.is_subset(versions_allowed) {
-                let offending_versions: Vec<&str> = versions_needed
-                    .difference(versions_allowed)
-                    .map(|v| v.as_ref())
-                    .collect();
-                let offending_symbol_versions: Vec<String> = offending_versions
-                    .iter()
-                    .map(|v| format!("{name}_{v}"))
-                    .collect();
-                let offending_symbols = find_incompliant_symbols(elf, &offending_symbol_versions)?;
-                let offender = if offending_symbols.is_empty() {
-                    format!(
-                        "{} offending versions: {}",
-                        library.name,
-                        offending_symbol_versions.join(", ")
-                    )
-                } else {
-                    format!(
-                        "{} offending symbols: {}",
-                        library.name,
-                        offending_symbols.join(", ")
-                    )
-                };
-                offending_versioned_syms.insert(offender);
+        for version in &library.versions {
+            let (name, number) = version.split_once('_').unwrap();
+            if arch_versions[name].contains(number) {
+                continue;
             }
+            let symbols = versioned_symbols
+                .get(&library.name)
+                .and_then(|versions| versions.get(version))
+                .cloned()
+                .unwrap_or_default();
+            violations.too_new_versions.push(TooNewVersion {
+                library: library.name.clone(),
+                version: version.clone(),
+                symbols,
+            });
         }
     }
+    violations.too_new_versions.sort_by(|a, b| {
+        (&a.library, version_key(&a.version)).cmp(&(&b.library, version_key(&b.version)))
+    });
+    violations.forbidden_libraries = offending_libs.into_iter().collect();
+    Ok(violations)
+}
+
+#[allow(clippy::result_large_err)]
+pub(super) fn policy_is_satisfied(
+    policy: &Policy,
+    elf: &Elf,
+    arch: &str,
+    deps: &[String],
+    versioned_libraries: &[VersionedLibrary],
+    allow_linking_libpython: bool,
+) -> Result<(), AuditWheelError> {
+    let violations = find_policy_violations(
+        policy,
+        elf,
+        arch,
+        deps,
+        versioned_libraries,
+        allow_linking_libpython,
+    )?;
     // Check for black-listed symbols
-    if !offending_blacklist_syms.is_empty() {
-        let offenders = offending_blacklist_syms
+    if !violations.blacklisted_symbols.is_empty() {
+        let offenders = violations
+            .blacklisted_symbols
             .into_iter()
             .map(|(lib, syms)| format!("{}: {}", lib, syms.join(", ")))
             .collect();
@@ -216,15 +336,18 @@ fn policy_is_satisfied(
         ));
     }
     // Check for too-recent versioned symbols
-    if !offending_versioned_syms.is_empty() {
+    if !violations.too_new_versions.is_empty() {
         return Err(AuditWheelError::VersionedSymbolTooNewError(
             policy.clone(),
-            offending_versioned_syms.into_iter().collect(),
+            violations
+                .too_new_versions
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
         ));
     }
     // Check for libpython and forbidden libraries
-    let offenders: Vec<String> = offending_libs.into_iter().collect();
-    match offenders.as_slice() {
+    match violations.forbidden_libraries.as_slice() {
         [] => Ok(()),
         [lib] if IS_LIBPYTHON.is_match(lib) => {
             Err(AuditWheelError::LinksLibPythonError(lib.clone()))
@@ -236,10 +359,11 @@ fn policy_is_satisfied(
     }
 }
 
-fn get_default_platform_policies() -> Vec<Policy> {
+fn get_default_platform_policies(policies: &Policies) -> Vec<Policy> {
     if let Ok(Some(musl_libc)) = find_musl_libc() {
         if let Ok(Some((major, minor))) = get_musl_version(musl_libc) {
-            return MUSLLINUX_POLICIES
+            return policies
+                .musllinux()
                 .iter()
                 .filter(|policy| {
                     policy.name == "linux" || policy.name == format!("musllinux_{major}_{minor}")
@@ -248,7 +372,7 @@ fn get_default_platform_policies() -> Vec<Policy> {
                 .collect();
         }
     }
-    MANYLINUX_POLICIES.clone()
+    policies.manylinux().to_vec()
 }
 
 /// An reimplementation of auditwheel, which checks elf files for
@@ -261,6 +385,8 @@ fn get_default_platform_policies() -> Vec<Policy> {
 /// If a specific manylinux/musllinux version is given, compliance is checked and a warning printed if
 /// a higher version would be possible.
 ///
+/// The policies are looked up in `policies`, which may include those from policy files.
+///
 /// Does nothing for `platform_tag` set to `Off`/`Linux` or non-linux platforms.
 #[allow(clippy::result_large_err)]
 pub fn auditwheel_rs(
@@ -268,37 +394,59 @@ pub fn auditwheel_rs(
     target: &Target,
     platform_tag: Option<PlatformTag>,
     allow_linking_libpython: bool,
+    policies: &Policies,
 ) -> Result<(Policy, bool), AuditWheelError> {
     if !target.is_linux() || platform_tag == Some(PlatformTag::Linux) {
         return Ok((Policy::default(), false));
     }
     let path = &artifact.path;
-    let arch = target.target_arch().to_string();
     let mut file = File::open(path).map_err(AuditWheelError::IoError)?;
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer)
         .map_err(AuditWheelError::IoError)?;
     let elf = Elf::parse(&buffer).map_err(AuditWheelError::GoblinError)?;
+    audit_elf(
+        &elf,
+        target.target_arch(),
+        platform_tag,
+        allow_linking_libpython,
+        policies,
+    )
+}
+
+/// Checks a parsed elf file for manylinux/musllinux compliance, see [auditwheel_rs]
+///
+/// `platform_tag` must not be `Linux`.
+#[allow(clippy::result_large_err)]
+pub fn audit_elf(
+    elf: &Elf,
+    target_arch: Arch,
+    platform_tag: Option<PlatformTag>,
+    allow_linking_libpython: bool,
+    policies: &Policies,
+) -> Result<(Policy, bool), AuditWheelError> {
+    let arch = target_arch.to_string();
     // This returns essentially the same as ldd
     let deps: Vec<String> = elf.libraries.iter().map(ToString::to_string).collect();
-    let versioned_libraries = find_versioned_libraries(&elf);
+    let versioned_libraries = find_versioned_libraries(elf);
 
     // Find the highest possible policy, if any
     let platform_policies = match platform_tag {
-        Some(PlatformTag::Manylinux { .. }) => MANYLINUX_POLICIES.clone(),
-        Some(PlatformTag::Musllinux { x, y }) => MUSLLINUX_POLICIES
-            .clone()
-            .into_iter()
+        Some(PlatformTag::Manylinux { .. }) => policies.manylinux().to_vec(),
+        Some(PlatformTag::Musllinux { x, y }) => policies
+            .musllinux()
+            .iter()
             .filter(|policy| policy.name == "linux" || policy.name == format!("musllinux_{x}_{y}"))
+            .cloned()
             .map(|mut policy| {
-                policy.fixup_musl_libc_so_name(target.target_arch());
+                policy.fixup_musl_libc_so_name(target_arch);
                 policy
             })
             .collect(),
         None => {
-            let mut policies = get_default_platform_policies();
+            let mut policies = get_default_platform_policies(policies);
             for policy in &mut policies {
-                policy.fixup_musl_libc_so_name(target.target_arch());
+                policy.fixup_musl_libc_so_name(target_arch);
             }
             policies
         }
@@ -309,7 +457,7 @@ pub fn auditwheel_rs(
     for policy in platform_policies.iter() {
         let result = policy_is_satisfied(
             policy,
-            &elf,
+            elf,
             &arch,
             &deps,
             &versioned_libraries,
@@ -338,8 +486,10 @@ pub fn auditwheel_rs(
 
     let policy = if let Some(platform_tag) = platform_tag {
         let tag = platform_tag.to_string();
-        let mut policy = Policy::from_name(&tag).ok_or(AuditWheelError::UndefinedPolicy(tag))?;
-        policy.fixup_musl_libc_so_name(target.target_arch());
+        let mut policy = policies
+            .from_name(&tag)
+            .ok_or(AuditWheelError::UndefinedPolicy(tag))?;
+        policy.fixup_musl_libc_so_name(target_arch);
 
         if let Some(highest_policy) = highest_policy {
             // Don't recommend manylinux1 because rust doesn't support it anymore
@@ -353,7 +503,7 @@ pub fn auditwheel_rs(
 
         match policy_is_satisfied(
             &policy,
-            &elf,
+            elf,
             &arch,
             &deps,
             &versioned_libraries,
@@ -440,26 +590,35 @@ pub fn get_sysroot_path(target: &Target) -> Result<PathBuf> {
 
 /// For the given compilation result, return the manylinux platform and the external libs
 /// we need to add to repair it
+///
+/// Libraries matching one of the `exclude` patterns are expected to be provided by the system,
+/// so they are neither copied into the wheel nor are their dependencies.
 pub fn get_policy_and_libs(
     artifact: &BuildArtifact,
     platform_tag: Option<PlatformTag>,
     target: &Target,
     allow_linking_libpython: bool,
+    exclude: &[Pattern],
+    policies: &Policies,
 ) -> Result<(Policy, Vec<Library>)> {
-    let (policy, should_repair) =
-        auditwheel_rs(artifact, target, platform_tag, allow_linking_libpython).with_context(
-            || {
-                if let Some(platform_tag) = platform_tag {
-                    format!("Error ensuring {platform_tag} compliance")
-                } else {
-                    "Error checking for manylinux/musllinux compliance".to_string()
-                }
-            },
-        )?;
+    let (policy, should_repair) = auditwheel_rs(
+        artifact,
+        target,
+        platform_tag,
+        allow_linking_libpython,
+        policies,
+    )
+    .with_context(|| {
+        if let Some(platform_tag) = platform_tag {
+            format!("Error ensuring {platform_tag} compliance")
+        } else {
+            "Error checking for manylinux/musllinux compliance".to_string()
+        }
+    })?;
     let external_libs = if should_repair {
         let sysroot = get_sysroot_path(target).unwrap_or_else(|_| PathBuf::from("/"));
         let ld_paths = artifact.linked_paths.iter().map(PathBuf::from).collect();
-        let external_libs = find_external_libs(&artifact.path, &policy, sysroot, ld_paths)
+        let external_libs = find_external_libs(&artifact.path, &policy, sysroot, ld_paths, exclude)
             .with_context(|| {
                 if let Some(platform_tag) = platform_tag {
                     format!("Error repairing wheel for {platform_tag} compliance")
@@ -508,6 +667,25 @@ mod test {
     use pretty_assertions::assert_eq;
     use std::path::Path;
 
+    #[test]
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    fn test_versioned_symbols() {
+        use super::{find_versioned_libraries, find_versioned_symbols};
+        use goblin::elf::Elf;
+
+        let bytes = fs_err::read(std::env::current_exe().unwrap()).unwrap();
+        let elf = Elf::parse(&bytes).unwrap();
+        let symbols = find_versioned_symbols(&elf);
+        let libc = &symbols["libc.so.6"];
+        assert!(libc.values().flatten().any(|symbol| symbol == "malloc"));
+        // Every version that is needed is needed by a symbol
+        for library in find_versioned_libraries(&elf) {
+            for version in &library.versions {
+                assert!(symbols[&library.name].contains_key(version), "{version}");
+            }
+        }
+    }
+
     #[test]
     fn test_relpath() {
         let cases = [
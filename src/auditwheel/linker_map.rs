@@ -0,0 +1,139 @@
+//! Reads the cross reference table of a linker map to find out which crates or object files
+//! use a symbol
+use anyhow::{Context, Result};
+use fs_err as fs;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+/// The symbol references from the cross reference table of a linker map, as written by GNU ld
+/// and lld with `-Map=<file> --cref`
+#[derive(Debug, Clone, Default)]
+pub struct LinkerMap {
+    /// The files that reference a symbol, by symbol name without the version
+    references: HashMap<String, Vec<String>>,
+}
+
+impl LinkerMap {
+    /// Reads and parses a linker map
+    pub fn read(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        Self::parse(&contents)
+            .with_context(|| format!("{} has no cross reference table", path.display()))
+    }
+
+    /// Parses the cross reference table, in which each symbol is followed by the file that
+    /// defines it and then the files that reference it, one per line
+    pub fn parse(contents: &str) -> Option<Self> {
+        let mut lines = contents
+            .lines()
+            .skip_while(|line| line.trim() != "Cross Reference Table");
+        lines.next()?;
+        let mut references: HashMap<String, Vec<String>> = HashMap::new();
+        let mut current: Option<(String, bool)> = None;
+        for line in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if line.starts_with(char::is_whitespace) {
+                let Some((symbol, seen_definition)) = &mut current else {
+                    continue;
+                };
+                // The first file is the one that defines the symbol
+                if !*seen_definition {
+                    *seen_definition = true;
+                    continue;
+                }
+                references
+                    .entry(symbol.clone())
+                    .or_default()
+                    .push(line.trim().to_string());
+            } else {
+                let mut parts = line.split_whitespace();
+                let Some(symbol) = parts.next() else {
+                    continue;
+                };
+                if symbol == "Symbol" && current.is_none() {
+                    // The header line
+                    continue;
+                }
+                let symbol = symbol.split('@').next().unwrap_or(symbol).to_string();
+                current = Some((symbol, parts.next().is_some()));
+            }
+        }
+        Some(Self { references })
+    }
+
+    /// The crates or object files that reference a symbol, e.g. `std` or `libfoo.a(foo.o)`
+    pub fn origins(&self, symbol: &str) -> Vec<String> {
+        let Some(files) = self.references.get(symbol) else {
+            return Vec::new();
+        };
+        let origins: BTreeSet<String> = files.iter().map(|file| origin_name(file)).collect();
+        origins.into_iter().collect()
+    }
+}
+
+/// Turns the path of an object file into the name of its crate where possible
+///
+/// Rust objects are named `<crate>.<hash>.rcgu.o` or `<crate>-<hash>.<crate>.<hash>.rcgu.o`
+/// inside of `lib<crate>-<hash>.rlib`. Objects from other archives are named
+/// `libfoo.a(foo.o)`.
+fn origin_name(file: &str) -> String {
+    let (archive, member) = match file.strip_suffix(')').and_then(|file| file.split_once('(')) {
+        Some((archive, member)) => (Some(archive), member),
+        None => (None, file),
+    };
+    let archive = archive.map(|archive| archive.rsplit('/').next().unwrap_or(archive));
+    let member = member.rsplit('/').next().unwrap_or(member);
+    if !member.ends_with(".rcgu.o") {
+        return match archive {
+            Some(archive) => format!("{archive}({member})"),
+            None => member.to_string(),
+        };
+    }
+    if let Some(crate_name) = archive
+        .and_then(|archive| archive.strip_prefix("lib"))
+        .and_then(|archive| archive.strip_suffix(".rlib"))
+        .and_then(|archive| archive.rsplit_once('-'))
+        .map(|(crate_name, _hash)| crate_name)
+    {
+        return crate_name.to_string();
+    }
+    member
+        .split(['.', '-'])
+        .next()
+        .unwrap_or(member)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_referencing_crates() {
+        let map = "\
+Archive member included to satisfy reference by file (symbol)
+
+Cross Reference Table
+
+Symbol                                            File
+exp@@GLIBC_2.29                                   /lib/x86_64-linux-gnu/libm.so.6
+                                                  /target/release/deps/libstatrs-0123456789abcdef.rlib(statrs-0123456789abcdef.statrs.1a2b3c-cgu.0.rcgu.o)
+                                                  /target/release/deps/my_project.4f5e6d.rcgu.o
+                                                  /usr/lib/libcmath.a(cmath.o)
+getenv                                            /lib/x86_64-linux-gnu/libc.so.6
+                                                  /rustlib/lib/libstd-0123456789abcdef.rlib(std-0123456789abcdef.std.1a2b3c-cgu.0.rcgu.o)
+local_symbol                                      /target/release/deps/my_project.4f5e6d.rcgu.o
+";
+        let map = LinkerMap::parse(map).unwrap();
+        assert_eq!(
+            map.origins("exp"),
+            vec!["libcmath.a(cmath.o)", "my_project", "statrs"]
+        );
+        assert_eq!(map.origins("getenv"), vec!["std"]);
+        assert!(map.origins("local_symbol").is_empty());
+        assert!(map.origins("log").is_empty());
+        assert!(LinkerMap::parse("Memory Configuration\n").is_none());
+    }
+}
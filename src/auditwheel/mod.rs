@@ -1,11 +1,17 @@
 mod audit;
+mod linker_map;
 mod musllinux;
 pub mod patchelf;
 mod platform_tag;
 mod policy;
 mod repair;
+mod report;
 
 pub use audit::*;
+pub use linker_map::LinkerMap;
 pub use platform_tag::PlatformTag;
-pub use policy::Policy;
+pub use policy::{Policies, Policy, POLICY_FILE_ENV};
 pub use repair::find_external_libs;
+pub use report::{
+    audit_wheels, AuditFormat, ClaimedTag, LibraryAudit, PolicyRejection, WheelAudit,
+};
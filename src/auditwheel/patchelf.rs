@@ -1,30 +1,29 @@
+//! Edits the dynamic section of ELF files, like the `patchelf` tool does
+//!
+//! Changes that need new strings or more dynamic entries than there is room for are written to
+//! a new `PT_LOAD` segment at the end of the file, which holds the program header table, the
+//! extended `.dynstr` and, if it had to grow, `.dynamic`. Editing the same file again replaces
+//! that segment instead of adding another one.
 use anyhow::{bail, Context, Result};
+use goblin::container::{Container, Ctx};
+use goblin::elf::dynamic::{
+    Dyn, DT_NEEDED, DT_NULL, DT_RPATH, DT_RUNPATH, DT_SONAME, DT_STRSZ, DT_STRTAB,
+};
+use goblin::elf::program_header::{PF_R, PF_W, PT_DYNAMIC, PT_LOAD, PT_PHDR};
+use goblin::elf::section_header::SHT_DYNAMIC;
+use goblin::elf::{Elf, ProgramHeader, SectionHeader};
+use scroll::{Pread, Pwrite};
 use std::ffi::OsStr;
 use std::path::Path;
-use std::process::Command;
 
-static MISSING_PATCHELF_ERROR: &str = "Failed to execute 'patchelf', did you install it? Hint: Try `pip install maturin[patchelf]` (or just `pip install patchelf`)";
+/// Free dynamic entries that are added when `.dynamic` is moved, so that later edits can be
+/// made in place
+const SPARE_DYNAMIC_ENTRIES: usize = 4;
 
 /// Verify patchelf version
+///
+/// The ELF files are edited by maturin itself, so this always succeeds.
 pub fn verify_patchelf() -> Result<()> {
-    let output = Command::new("patchelf")
-        .arg("--version")
-        .output()
-        .context(MISSING_PATCHELF_ERROR)?;
-    let version = String::from_utf8(output.stdout)
-        .context("Failed to parse patchelf version")?
-        .trim()
-        .to_string();
-    let version = version.strip_prefix("patchelf").unwrap_or(&version).trim();
-    let semver = version
-        .parse::<semver::Version>()
-        .context("Failed to parse patchelf version")?;
-    if semver < semver::Version::new(0, 14, 0) {
-        bail!(
-            "patchelf {} found. auditwheel repair requires patchelf >= 0.14.",
-            version
-        );
-    }
     Ok(())
 }
 
@@ -33,65 +32,54 @@ pub fn replace_needed<O: AsRef<OsStr>, N: AsRef<OsStr>>(
     file: impl AsRef<Path>,
     old_new_pairs: &[(O, N)],
 ) -> Result<()> {
-    let mut cmd = Command::new("patchelf");
-    for (old, new) in old_new_pairs {
-        cmd.arg("--replace-needed").arg(old).arg(new);
-    }
-    cmd.arg(file.as_ref());
-    let output = cmd.output().context(MISSING_PATCHELF_ERROR)?;
-    if !output.status.success() {
-        bail!(
-            "patchelf --replace-needed failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
-    }
-    Ok(())
+    let old_new_pairs = old_new_pairs
+        .iter()
+        .map(|(old, new)| Ok((to_str(old.as_ref())?, to_str(new.as_ref())?)))
+        .collect::<Result<Vec<_>>>()?;
+    edit_dynamic(file.as_ref(), |dynamic| {
+        for idx in 0..dynamic.entries.len() {
+            if dynamic.entries[idx].d_tag != DT_NEEDED {
+                continue;
+            }
+            let name = dynamic.string(dynamic.entries[idx].d_val)?.to_string();
+            if let Some((_, new)) = old_new_pairs.iter().find(|(old, _)| *old == name) {
+                dynamic.entries[idx].d_val = dynamic.add_string(new);
+            }
+        }
+        Ok(())
+    })
+    .context("Failed to replace DT_NEEDED")
 }
 
 /// Change `SONAME` of a dynamic library
 pub fn set_soname<S: AsRef<OsStr>>(file: impl AsRef<Path>, soname: &S) -> Result<()> {
-    let mut cmd = Command::new("patchelf");
-    cmd.arg("--set-soname").arg(soname).arg(file.as_ref());
-    let output = cmd.output().context(MISSING_PATCHELF_ERROR)?;
-    if !output.status.success() {
-        bail!(
-            "patchelf --set-soname failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
-    }
-    Ok(())
+    let soname = to_str(soname.as_ref())?;
+    edit_dynamic(file.as_ref(), |dynamic| {
+        dynamic.set(DT_SONAME, soname);
+        Ok(())
+    })
+    .context("Failed to set the soname")
 }
 
 /// Remove a `RPATH` from executables and libraries
 pub fn remove_rpath(file: impl AsRef<Path>) -> Result<()> {
-    let mut cmd = Command::new("patchelf");
-    cmd.arg("--remove-rpath").arg(file.as_ref());
-    let output = cmd.output().context(MISSING_PATCHELF_ERROR)?;
-    if !output.status.success() {
-        bail!(
-            "patchelf --remove-rpath failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
-    }
-    Ok(())
+    edit_dynamic(file.as_ref(), |dynamic| {
+        dynamic.remove(DT_RPATH);
+        dynamic.remove(DT_RUNPATH);
+        Ok(())
+    })
+    .context("Failed to remove the rpath")
 }
 
 /// Change the `RPATH` of executables and libraries
 pub fn set_rpath<S: AsRef<OsStr>>(file: impl AsRef<Path>, rpath: &S) -> Result<()> {
+    let rpath = to_str(rpath.as_ref())?;
     remove_rpath(&file)?;
-    let mut cmd = Command::new("patchelf");
-    cmd.arg("--force-rpath")
-        .arg("--set-rpath")
-        .arg(rpath)
-        .arg(file.as_ref());
-    let output = cmd.output().context(MISSING_PATCHELF_ERROR)?;
-    if !output.status.success() {
-        bail!(
-            "patchelf --set-rpath failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
-    }
-    Ok(())
+    edit_dynamic(file.as_ref(), |dynamic| {
+        dynamic.set(DT_RPATH, rpath);
+        Ok(())
+    })
+    .context("Failed to set the rpath")
 }
 
 /// Get the `RPATH` of executables and libraries
@@ -111,3 +99,439 @@ pub fn get_rpath(file: impl AsRef<Path>) -> Result<Vec<String>> {
         Err(e) => bail!("Failed to parse ELF file at '{}': {}", file.display(), e),
     }
 }
+
+fn to_str(value: &OsStr) -> Result<&str> {
+    value
+        .to_str()
+        .with_context(|| format!("{value:?} is not valid UTF-8"))
+}
+
+/// The entries of the dynamic section up to `DT_NULL` and the dynamic string table
+struct DynamicSection {
+    entries: Vec<Dyn>,
+    strtab: Vec<u8>,
+}
+
+impl DynamicSection {
+    fn string(&self, offset: u64) -> Result<&str> {
+        let bytes = usize::try_from(offset)
+            .ok()
+            .and_then(|offset| self.strtab.get(offset..))
+            .context("String offset is out of bounds")?;
+        let end = bytes
+            .iter()
+            .position(|&byte| byte == 0)
+            .context("String is not terminated")?;
+        std::str::from_utf8(&bytes[..end]).context("String is not valid UTF-8")
+    }
+
+    /// Returns the offset of the string, which is appended unless it's already in the table
+    fn add_string(&mut self, value: &str) -> u64 {
+        let mut needle = value.as_bytes().to_vec();
+        needle.push(0);
+        // Strings may share their tail with a longer one, so any match can be reused
+        if let Some(offset) = self
+            .strtab
+            .windows(needle.len())
+            .position(|window| window == needle)
+        {
+            return offset as u64;
+        }
+        let offset = self.strtab.len();
+        self.strtab.extend(needle);
+        offset as u64
+    }
+
+    fn remove(&mut self, tag: u64) {
+        self.entries.retain(|entry| entry.d_tag != tag);
+    }
+
+    /// Points the first entry with the tag to the string, adding an entry if there's none
+    fn set(&mut self, tag: u64, value: &str) {
+        let d_val = self.add_string(value);
+        match self.entries.iter_mut().find(|entry| entry.d_tag == tag) {
+            Some(entry) => entry.d_val = d_val,
+            None => self.entries.push(Dyn { d_tag: tag, d_val }),
+        }
+    }
+}
+
+/// Reads an ELF file, applies `edit` to its dynamic section and writes it back
+fn edit_dynamic(path: &Path, edit: impl FnOnce(&mut DynamicSection) -> Result<()>) -> Result<()> {
+    let bytes = fs_err::read(path)?;
+    let new_bytes = rewrite_dynamic(&bytes, edit)
+        .with_context(|| format!("Failed to edit {}", path.display()))?;
+    if let Some(new_bytes) = new_bytes {
+        fs_err::write(path, new_bytes)?;
+    }
+    Ok(())
+}
+
+/// Returns the edited file, or `None` if nothing changed
+fn rewrite_dynamic(
+    bytes: &[u8],
+    edit: impl FnOnce(&mut DynamicSection) -> Result<()>,
+) -> Result<Option<Vec<u8>>> {
+    let elf = Elf::parse(bytes).context("Failed to parse the ELF file")?;
+    let ctx = Ctx::new(
+        if elf.is_64 {
+            Container::Big
+        } else {
+            Container::Little
+        },
+        if elf.little_endian {
+            scroll::LE
+        } else {
+            scroll::BE
+        },
+    );
+    let dyn_size = if elf.is_64 { 16 } else { 8 };
+
+    let dynamic_phdr = elf
+        .program_headers
+        .iter()
+        .find(|phdr| phdr.p_type == PT_DYNAMIC)
+        .context("The file has no dynamic section")?
+        .clone();
+    let capacity = dynamic_phdr.p_filesz as usize / dyn_size;
+    let mut entries = Vec::new();
+    for idx in 0..capacity {
+        let entry: Dyn = bytes.pread_with(dynamic_phdr.p_offset as usize + idx * dyn_size, ctx)?;
+        if entry.d_tag == DT_NULL {
+            break;
+        }
+        entries.push(entry);
+    }
+    let find = |tag| {
+        entries
+            .iter()
+            .find(|entry| entry.d_tag == tag)
+            .map(|entry| entry.d_val)
+    };
+    let strtab_addr = find(DT_STRTAB).context("The file has no DT_STRTAB")?;
+    let strtab_size = find(DT_STRSZ).context("The file has no DT_STRSZ")? as usize;
+    let strtab_offset = vaddr_to_offset(&elf.program_headers, strtab_addr)
+        .context("DT_STRTAB is outside of the loaded segments")?;
+    let strtab = bytes
+        .get(strtab_offset..strtab_offset + strtab_size)
+        .context("DT_STRTAB is out of bounds")?
+        .to_vec();
+
+    let mut dynamic = DynamicSection {
+        entries: entries.clone(),
+        strtab: strtab.clone(),
+    };
+    edit(&mut dynamic)?;
+    if dynamic.strtab == strtab && dynamic.entries == entries {
+        return Ok(None);
+    }
+
+    // The segment that an earlier edit added, which contains the program headers and ends
+    // the file, can be replaced
+    let previous = elf.program_headers.iter().position(|phdr| {
+        phdr.p_type == PT_LOAD
+            && phdr.p_offset > 0
+            && phdr.p_offset <= elf.header.e_phoff
+            && elf.header.e_phoff < phdr.p_offset + phdr.p_filesz
+            && (phdr.p_offset + phdr.p_filesz) as usize == bytes.len()
+    });
+    let in_previous = |offset: u64| {
+        previous.is_some_and(|idx| {
+            let phdr = &elf.program_headers[idx];
+            phdr.p_offset <= offset && offset < phdr.p_offset + phdr.p_filesz
+        })
+    };
+    let move_dynamic = dynamic.entries.len() + 1 > capacity || in_previous(dynamic_phdr.p_offset);
+    if dynamic.strtab == strtab && !move_dynamic {
+        let mut new_bytes = bytes.to_vec();
+        write_dynamic(
+            &mut new_bytes,
+            dynamic_phdr.p_offset as usize,
+            &dynamic.entries,
+            capacity,
+            ctx,
+        )?;
+        return Ok(Some(new_bytes));
+    }
+
+    let mut phdrs = elf.program_headers.clone();
+    let mut end = bytes.len() as u64;
+    if let Some(previous) = previous {
+        end = phdrs[previous].p_offset;
+        phdrs.remove(previous);
+    }
+    let loads: Vec<&ProgramHeader> = phdrs.iter().filter(|phdr| phdr.p_type == PT_LOAD).collect();
+    let first_load = loads.first().context("The file has no loaded segments")?;
+    let page_size = loads
+        .iter()
+        .map(|phdr| phdr.p_align)
+        .max()
+        .unwrap_or_default()
+        .max(0x1000);
+    // Keep the difference between addresses and offsets of the first segment, so that old
+    // kernels which compute `AT_PHDR` from `e_phoff` find the program headers
+    let bias = first_load.p_vaddr.wrapping_sub(first_load.p_offset);
+    let max_vaddr = loads
+        .iter()
+        .map(|phdr| phdr.p_vaddr + phdr.p_memsz)
+        .max()
+        .unwrap_or_default();
+    let offset = align_up(end.max(max_vaddr.wrapping_sub(bias)), page_size);
+    let vaddr = offset.wrapping_add(bias);
+
+    // The layout of the new segment
+    let phdr_size = ProgramHeader::size(ctx) as u64;
+    let phdrs_len = (phdrs.len() as u64 + 1) * phdr_size;
+    let strtab_start = phdrs_len;
+    let dynamic_start = align_up(strtab_start + dynamic.strtab.len() as u64, dyn_size as u64);
+    let dynamic_capacity = if move_dynamic {
+        dynamic.entries.len() + 1 + SPARE_DYNAMIC_ENTRIES
+    } else {
+        capacity
+    };
+    let segment_len = if move_dynamic {
+        dynamic_start + (dynamic_capacity * dyn_size) as u64
+    } else {
+        strtab_start + dynamic.strtab.len() as u64
+    };
+
+    for entry in &mut dynamic.entries {
+        match entry.d_tag {
+            DT_STRTAB => entry.d_val = vaddr + strtab_start,
+            DT_STRSZ => entry.d_val = dynamic.strtab.len() as u64,
+            _ => {}
+        }
+    }
+    for phdr in &mut phdrs {
+        if phdr.p_type == PT_PHDR {
+            phdr.p_offset = offset;
+            phdr.p_vaddr = vaddr;
+            phdr.p_paddr = vaddr;
+            phdr.p_filesz = phdrs_len;
+            phdr.p_memsz = phdrs_len;
+        } else if phdr.p_type == PT_DYNAMIC && move_dynamic {
+            phdr.p_offset = offset + dynamic_start;
+            phdr.p_vaddr = vaddr + dynamic_start;
+            phdr.p_paddr = vaddr + dynamic_start;
+            phdr.p_filesz = (dynamic_capacity * dyn_size) as u64;
+            phdr.p_memsz = phdr.p_filesz;
+        }
+    }
+    // Loaded segments have to be sorted by address
+    let last_load = phdrs
+        .iter()
+        .rposition(|phdr| phdr.p_type == PT_LOAD)
+        .unwrap_or_default();
+    phdrs.insert(
+        last_load + 1,
+        ProgramHeader {
+            p_type: PT_LOAD,
+            p_flags: PF_R | PF_W,
+            p_offset: offset,
+            p_vaddr: vaddr,
+            p_paddr: vaddr,
+            p_filesz: segment_len,
+            p_memsz: segment_len,
+            p_align: page_size,
+        },
+    );
+
+    let mut new_bytes = bytes[..end as usize].to_vec();
+    new_bytes.resize((offset + segment_len) as usize, 0);
+    for (idx, phdr) in phdrs.iter().enumerate() {
+        new_bytes.pwrite_with(
+            phdr.clone(),
+            offset as usize + idx * phdr_size as usize,
+            ctx,
+        )?;
+    }
+    let strtab_offset = (offset + strtab_start) as usize;
+    new_bytes[strtab_offset..strtab_offset + dynamic.strtab.len()].copy_from_slice(&dynamic.strtab);
+    if move_dynamic {
+        write_dynamic(
+            &mut new_bytes,
+            (offset + dynamic_start) as usize,
+            &dynamic.entries,
+            dynamic_capacity,
+            ctx,
+        )?;
+    } else {
+        write_dynamic(
+            &mut new_bytes,
+            dynamic_phdr.p_offset as usize,
+            &dynamic.entries,
+            capacity,
+            ctx,
+        )?;
+    }
+
+    let mut header = elf.header;
+    header.e_phoff = offset;
+    header.e_phnum = phdrs.len() as u16;
+    new_bytes.pwrite_with(header, 0, ctx.le)?;
+
+    // Point the section headers at the new locations, for tools that read those
+    let mut section_headers = elf.section_headers.clone();
+    let dynamic_section = section_headers
+        .iter()
+        .position(|section| section.sh_type == SHT_DYNAMIC);
+    if let Some(dynamic_section) = dynamic_section {
+        let strtab_section = section_headers[dynamic_section].sh_link as usize;
+        if let Some(section) = section_headers.get_mut(strtab_section) {
+            section.sh_offset = offset + strtab_start;
+            section.sh_addr = vaddr + strtab_start;
+            section.sh_size = dynamic.strtab.len() as u64;
+        }
+        if move_dynamic {
+            let section = &mut section_headers[dynamic_section];
+            section.sh_offset = offset + dynamic_start;
+            section.sh_addr = vaddr + dynamic_start;
+            section.sh_size = (dynamic_capacity * dyn_size) as u64;
+        }
+    }
+    let shdr_size = SectionHeader::size(ctx);
+    for (idx, section) in section_headers.into_iter().enumerate() {
+        if elf.header.e_shoff == 0 {
+            break;
+        }
+        new_bytes.pwrite_with(section, elf.header.e_shoff as usize + idx * shdr_size, ctx)?;
+    }
+    Ok(Some(new_bytes))
+}
+
+fn write_dynamic(
+    bytes: &mut [u8],
+    offset: usize,
+    entries: &[Dyn],
+    capacity: usize,
+    ctx: Ctx,
+) -> Result<()> {
+    let dyn_size = if ctx.container == Container::Big {
+        16
+    } else {
+        8
+    };
+    let null = Dyn {
+        d_tag: DT_NULL,
+        d_val: 0,
+    };
+    let padding = std::iter::repeat(&null).take(capacity - entries.len());
+    for (idx, entry) in entries.iter().chain(padding).enumerate() {
+        bytes.pwrite_with(entry.clone(), offset + idx * dyn_size, ctx)?;
+    }
+    Ok(())
+}
+
+/// Finds the file offset of an address in the loaded segments
+fn vaddr_to_offset(phdrs: &[ProgramHeader], vaddr: u64) -> Option<usize> {
+    phdrs
+        .iter()
+        .find(|phdr| {
+            phdr.p_type == PT_LOAD && phdr.p_vaddr <= vaddr && vaddr < phdr.p_vaddr + phdr.p_filesz
+        })
+        .map(|phdr| (vaddr - phdr.p_vaddr + phdr.p_offset) as usize)
+}
+
+fn align_up(value: u64, alignment: u64) -> u64 {
+    (value + alignment - 1) / alignment * alignment
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    fn cc(args: &[&str], dir: &Path) {
+        let status = Command::new("cc")
+            .args(args)
+            .current_dir(dir)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    /// Runs `readelf`, which prints warnings for inconsistent headers, and returns its output
+    fn readelf(file: &Path) -> String {
+        let output = Command::new("readelf")
+            .args([
+                "--dynamic",
+                "--program-headers",
+                "--section-headers",
+                "--wide",
+            ])
+            .arg(file)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert_eq!(stderr, "", "readelf complained about {}", file.display());
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    fn phnum(file: &Path) -> u16 {
+        let bytes = fs_err::read(file).unwrap();
+        Elf::parse(&bytes).unwrap().header.e_phnum
+    }
+
+    #[test]
+    fn patched_files_load() {
+        let tmp_dir = TempDir::new().unwrap();
+        let dir = tmp_dir.path();
+        fs_err::create_dir_all(dir.join("lib")).unwrap();
+        fs_err::create_dir_all(dir.join("bin")).unwrap();
+        fs_err::write(dir.join("dep.c"), "int dep_value(void) { return 42; }\n").unwrap();
+        fs_err::write(
+            dir.join("main.c"),
+            "int dep_value(void);\nint main(void) { return dep_value() == 42 ? 0 : 1; }\n",
+        )
+        .unwrap();
+        cc(
+            &[
+                "-shared",
+                "-fPIC",
+                "-Wl,-soname,libdep.so.1",
+                "-o",
+                "libdep.so",
+                "dep.c",
+            ],
+            dir,
+        );
+        cc(&["-o", "bin/main", "main.c", "-L.", "-ldep"], dir);
+
+        // Graft the library under a new name, like a wheel repair does
+        let lib = dir.join("lib/libdep-0123abcd.so.1");
+        let main = dir.join("bin/main");
+        fs_err::copy(dir.join("libdep.so"), &lib).unwrap();
+        set_soname(&lib, &"libdep-0123abcd.so.1").unwrap();
+        set_rpath(&lib, &"$ORIGIN").unwrap();
+        replace_needed(&main, &[("libdep.so.1", "libdep-0123abcd.so.1")]).unwrap();
+        set_rpath(&main, &"$ORIGIN/../lib").unwrap();
+
+        let lib_info = readelf(&lib);
+        assert!(lib_info.contains("Library soname: [libdep-0123abcd.so.1]"));
+        assert!(lib_info.contains("Library rpath: [$ORIGIN]"));
+        let main_info = readelf(&main);
+        assert!(main_info.contains("Shared library: [libdep-0123abcd.so.1]"));
+        assert!(!main_info.contains("[libdep.so.1]"));
+        assert!(main_info.contains("Library rpath: [$ORIGIN/../lib]"));
+        assert_eq!(get_rpath(&main).unwrap(), vec!["$ORIGIN/../lib"]);
+
+        // The kernel and the dynamic linker accept the new program headers
+        let status = Command::new(&main).status().unwrap();
+        assert!(status.success());
+
+        // Editing again replaces the added segment instead of adding another one
+        let phnum_before = phnum(&main);
+        set_rpath(&main, &"$ORIGIN/../lib:/nonexistent").unwrap();
+        assert_eq!(phnum(&main), phnum_before);
+        assert!(readelf(&main).contains("Library rpath: [$ORIGIN/../lib:/nonexistent]"));
+        assert!(Command::new(&main).status().unwrap().success());
+
+        remove_rpath(&main).unwrap();
+        assert!(get_rpath(&main).unwrap().is_empty());
+        readelf(&main);
+        // Without the rpath, the dynamic linker can't find the library anymore
+        assert!(!Command::new(&main).output().unwrap().status.success());
+    }
+}
@@ -41,17 +41,39 @@ impl PlatformTag {
         Self::Manylinux { x: 2, y: 17 }
     }
 
-    /// manylinux aliases
+    /// Parses the name of a policy, which is either `linux`, `manylinux_<x>_<y>` or
+    /// `musllinux_<x>_<y>`
+    pub(crate) fn from_policy_name(name: &str) -> Option<Self> {
+        if name == "linux" {
+            return Some(PlatformTag::Linux);
+        }
+        let (family, version) = name.split_once('_')?;
+        let (x, y) = version.split_once('_')?;
+        let tag = match family {
+            "manylinux" => PlatformTag::Manylinux {
+                x: x.parse().ok()?,
+                y: y.parse().ok()?,
+            },
+            "musllinux" => PlatformTag::Musllinux {
+                x: x.parse().ok()?,
+                y: y.parse().ok()?,
+            },
+            _ => return None,
+        };
+        // Reject non-canonical spellings such as `manylinux_02_17`
+        (tag.to_string() == name).then_some(tag)
+    }
+
+    /// manylinux and musllinux aliases of the built-in policies, see [crate::Policies::aliases]
     pub fn aliases(&self) -> Vec<String> {
         match self {
-            PlatformTag::Manylinux { .. } => {
+            PlatformTag::Manylinux { .. } | PlatformTag::Musllinux { .. } => {
                 if let Some(policy) = Policy::from_name(&self.to_string()) {
                     policy.aliases
                 } else {
                     Vec::new()
                 }
             }
-            PlatformTag::Musllinux { .. } => Vec::new(),
             PlatformTag::Linux => Vec::new(),
         }
     }
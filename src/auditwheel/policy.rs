@@ -1,32 +1,189 @@
 use crate::auditwheel::PlatformTag;
 use crate::target::Arch;
+use anyhow::{bail, format_err, Context, Result};
+use fs_err as fs;
 use once_cell::sync::Lazy;
 use serde::Deserialize;
 use std::cmp::{Ordering, PartialOrd};
 use std::collections::{HashMap, HashSet};
+use std::env;
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use std::path::Path;
 
-/// The policies (allowed symbols) for the different manylinux tags, sorted from highest
-/// priority to lowest
-pub static MANYLINUX_POLICIES: Lazy<Vec<Policy>> = Lazy::new(|| {
-    // https://github.com/pypa/auditwheel/blob/master/auditwheel/policy/manylinux-policy.json
-    let mut policies: Vec<Policy> = serde_json::from_slice(include_bytes!("manylinux-policy.json"))
-        .expect("invalid manylinux policy.json file");
-    policies.sort_by_key(|policy| -policy.priority);
-    policies
-});
+/// Environment variable with the path of a JSON file with additional or overriding
+/// manylinux/musllinux policies
+pub const POLICY_FILE_ENV: &str = "MATURIN_POLICY_FILE";
 
-/// The policies (allowed symbols) for the different musllinux tags, sorted from highest
-/// priority to lowest
-pub static MUSLLINUX_POLICIES: Lazy<Vec<Policy>> = Lazy::new(|| {
+/// The policies that are compiled into maturin
+static BUILTIN_POLICIES: Lazy<Policies> = Lazy::new(|| {
+    // https://github.com/pypa/auditwheel/blob/master/auditwheel/policy/manylinux-policy.json
+    let mut manylinux: Vec<Policy> =
+        serde_json::from_slice(include_bytes!("manylinux-policy.json"))
+            .expect("invalid manylinux policy.json file");
+    manylinux.sort_by_key(|policy| -policy.priority);
     // https://github.com/pypa/auditwheel/blob/master/auditwheel/policy/musllinux-policy.json
-    let mut policies: Vec<Policy> = serde_json::from_slice(include_bytes!("musllinux-policy.json"))
-        .expect("invalid musllinux policy.json file");
-    policies.sort_by_key(|policy| -policy.priority);
-    policies
+    let mut musllinux: Vec<Policy> =
+        serde_json::from_slice(include_bytes!("musllinux-policy.json"))
+            .expect("invalid musllinux policy.json file");
+    musllinux.sort_by_key(|policy| -policy.priority);
+    Policies {
+        manylinux,
+        musllinux,
+    }
 });
 
+/// The policies (allowed symbols) for the different manylinux and musllinux tags, each sorted
+/// from highest priority to lowest
+///
+/// These are the built-in policies, optionally extended by policy files in the format of
+/// auditwheel's `manylinux-policy.json`, so that new glibc or musl baselines can be used without
+/// a new maturin release. Each build has its own set of policies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Policies {
+    manylinux: Vec<Policy>,
+    musllinux: Vec<Policy>,
+}
+
+impl Default for Policies {
+    fn default() -> Self {
+        BUILTIN_POLICIES.clone()
+    }
+}
+
+impl Policies {
+    /// The built-in policies extended by the policy file from `MATURIN_POLICY_FILE`, if set
+    pub fn from_env() -> Result<Self> {
+        let mut policies = Self::default();
+        policies.load_from_env()?;
+        Ok(policies)
+    }
+
+    /// Loads manylinux and musllinux policies from a JSON file in the format of auditwheel's
+    /// `manylinux-policy.json`, replacing those with the same name
+    pub fn load(&mut self, path: &Path) -> Result<()> {
+        let contents = fs::read(path)?;
+        let policies: Vec<Policy> = serde_json::from_slice(&contents)
+            .with_context(|| format!("{} is not a valid policy file", path.display()))?;
+        let names: Vec<String> = policies.iter().map(|policy| policy.name.clone()).collect();
+        self.merge(policies)
+            .with_context(|| format!("Failed to load the policies in {}", path.display()))?;
+        eprintln!(
+            "📜 Using the policies for {} from {}",
+            names.join(", "),
+            path.display()
+        );
+        Ok(())
+    }
+
+    /// Loads the policy file given by the `MATURIN_POLICY_FILE` environment variable, if set
+    pub fn load_from_env(&mut self) -> Result<()> {
+        match env::var_os(POLICY_FILE_ENV) {
+            Some(path) if !path.is_empty() => self
+                .load(Path::new(&path))
+                .with_context(|| format!("Invalid {POLICY_FILE_ENV}")),
+            _ => Ok(()),
+        }
+    }
+
+    /// The manylinux policies, sorted from highest priority to lowest
+    pub fn manylinux(&self) -> &[Policy] {
+        &self.manylinux
+    }
+
+    /// The musllinux policies, sorted from highest priority to lowest
+    pub fn musllinux(&self) -> &[Policy] {
+        &self.musllinux
+    }
+
+    /// Get a policy by its platform tag name or one of its aliases
+    pub fn from_name(&self, name: &str) -> Option<Policy> {
+        self.manylinux
+            .iter()
+            .chain(&self.musllinux)
+            .find(|p| p.name == name || p.aliases.iter().any(|alias| alias == name))
+            .cloned()
+    }
+
+    /// Parses a platform tag, accepting the names and aliases of all policies in addition to
+    /// the forms that [PlatformTag] parses
+    pub fn parse_platform_tag(&self, value: &str) -> Result<PlatformTag> {
+        if let Some(tag) = self
+            .from_name(&value.to_ascii_lowercase())
+            .and_then(|policy| PlatformTag::from_policy_name(&policy.name))
+        {
+            return Ok(tag);
+        }
+        value
+            .parse()
+            .map_err(|err| format_err!("Invalid platform tag `{value}`: {err}"))
+    }
+
+    /// The aliases of a platform tag, e.g. `manylinux2014` for `manylinux_2_17`
+    pub fn aliases(&self, platform_tag: &PlatformTag) -> Vec<String> {
+        match platform_tag {
+            PlatformTag::Manylinux { .. } | PlatformTag::Musllinux { .. } => self
+                .from_name(&platform_tag.to_string())
+                .map(|policy| policy.aliases)
+                .unwrap_or_default(),
+            PlatformTag::Linux => Vec::new(),
+        }
+    }
+
+    /// Adds the policies, replacing those of the same name, after checking that they fit into
+    /// the priority ordering
+    fn merge(&mut self, policies: Vec<Policy>) -> Result<()> {
+        let mut manylinux = self.manylinux.clone();
+        let mut musllinux = self.musllinux.clone();
+        for policy in policies {
+            let family = match PlatformTag::from_policy_name(&policy.name) {
+                Some(PlatformTag::Manylinux { .. }) => &mut manylinux,
+                Some(PlatformTag::Musllinux { .. }) => &mut musllinux,
+                _ => bail!(
+                    "Invalid policy name `{}`, expected manylinux_<x>_<y> or musllinux_<x>_<y>",
+                    policy.name
+                ),
+            };
+            if policy.priority <= 0 {
+                bail!(
+                    "The priority of {} must be higher than the priority of linux (0)",
+                    policy.name
+                );
+            }
+            family.retain(|existing| existing.name != policy.name);
+            family.push(policy);
+        }
+        for family in [&mut manylinux, &mut musllinux] {
+            family.sort_by_key(|policy| -policy.priority);
+            if let Some(pair) = family
+                .windows(2)
+                .find(|pair| pair[0].priority == pair[1].priority)
+            {
+                bail!(
+                    "{} and {} have the same priority {}",
+                    pair[0].name,
+                    pair[1].name,
+                    pair[0].priority
+                );
+            }
+        }
+        let mut names = HashMap::new();
+        for policy in manylinux.iter().chain(&musllinux) {
+            if policy.name == "linux" {
+                continue;
+            }
+            for name in std::iter::once(&policy.name).chain(&policy.aliases) {
+                if let Some(other) = names.insert(name.as_str(), policy.name.as_str()) {
+                    bail!("{name} is used by both {other} and {}", policy.name);
+                }
+            }
+        }
+        self.manylinux = manylinux;
+        self.musllinux = musllinux;
+        Ok(())
+    }
+}
+
 /// Manylinux policy
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 pub struct Policy {
@@ -76,20 +233,12 @@ impl PartialOrd for Policy {
 impl Policy {
     /// Get platform tag from this policy
     pub fn platform_tag(&self) -> PlatformTag {
-        self.name.parse().expect("unknown platform tag")
+        PlatformTag::from_policy_name(&self.name).expect("unknown platform tag")
     }
 
-    /// Get policy by it's platform tag name
+    /// Get a built-in policy by it's platform tag name
     pub fn from_name(name: &str) -> Option<Self> {
-        let policies = if name.starts_with("musllinux") {
-            &MUSLLINUX_POLICIES
-        } else {
-            &MANYLINUX_POLICIES
-        };
-        policies
-            .iter()
-            .find(|p| p.name == name || p.aliases.iter().any(|alias| alias == name))
-            .cloned()
+        BUILTIN_POLICIES.from_name(name)
     }
 
     pub(crate) fn fixup_musl_libc_so_name(&mut self, target_arch: Arch) {
@@ -115,7 +264,8 @@ impl Policy {
 
 #[cfg(test)]
 mod test {
-    use super::{Arch, Policy, MANYLINUX_POLICIES, MUSLLINUX_POLICIES};
+    use super::{Arch, Policies, Policy};
+    use crate::auditwheel::PlatformTag;
     use pretty_assertions::assert_eq;
 
     #[test]
@@ -136,14 +286,14 @@ mod test {
 
     #[test]
     fn test_policy_manylinux_tag() {
-        for policy in MANYLINUX_POLICIES.iter() {
+        for policy in Policies::default().manylinux() {
             let _tag = policy.platform_tag();
         }
     }
 
     #[test]
     fn test_policy_musllinux_tag() {
-        for policy in MUSLLINUX_POLICIES.iter() {
+        for policy in Policies::default().musllinux() {
             let _tag = policy.platform_tag();
         }
     }
@@ -154,4 +304,64 @@ mod test {
         policy.fixup_musl_libc_so_name(Arch::Aarch64);
         assert!(policy.lib_whitelist.contains("libc.musl-aarch64.so.1"));
     }
+
+    #[test]
+    fn test_merge_policies() {
+        let mut policies = Policies::default();
+        let mut manylinux_2_39 = policies.manylinux[1].clone();
+        manylinux_2_39.name = "manylinux_2_39".to_string();
+        manylinux_2_39.aliases = vec!["manylinux2039".to_string()];
+        manylinux_2_39.priority = 50;
+        let mut musllinux_1_2 = policies.musllinux[0].clone();
+        musllinux_1_2.lib_whitelist.insert("libz.so.1".to_string());
+        policies.merge(vec![manylinux_2_39, musllinux_1_2]).unwrap();
+        let names: Vec<&str> = policies
+            .manylinux()
+            .iter()
+            .rev()
+            .take(3)
+            .map(|policy| policy.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["linux", "manylinux_2_39", "manylinux_2_35"]);
+        assert_eq!(
+            policies.parse_platform_tag("manylinux2039").unwrap(),
+            PlatformTag::Manylinux { x: 2, y: 39 }
+        );
+        assert_eq!(
+            policies.aliases(&PlatformTag::Manylinux { x: 2, y: 39 }),
+            vec!["manylinux2039"]
+        );
+        // Other policies don't know the alias
+        assert!(Policies::default()
+            .parse_platform_tag("manylinux2039")
+            .is_err());
+        let musllinux_1_2 = policies
+            .musllinux
+            .iter()
+            .filter(|policy| policy.name == "musllinux_1_2")
+            .collect::<Vec<_>>();
+        assert_eq!(musllinux_1_2.len(), 1);
+        assert!(musllinux_1_2[0].lib_whitelist.contains("libz.so.1"));
+    }
+
+    #[test]
+    fn test_merge_invalid_policies() {
+        let policies = Policies::default();
+        let template = policies.manylinux[0].clone();
+        let invalid = [
+            ("linux", vec![], 1000),
+            ("manylinux2039", vec![], 1000),
+            ("manylinux_2_39", vec![], 0),
+            ("manylinux_2_39", vec![], policies.manylinux[0].priority),
+            ("manylinux_2_39", vec!["manylinux2014"], 1000),
+        ];
+        for (name, aliases, priority) in invalid {
+            let mut policy = template.clone();
+            policy.name = name.to_string();
+            policy.aliases = aliases.into_iter().map(ToString::to_string).collect();
+            policy.priority = priority;
+            let mut merged = policies.clone();
+            assert!(merged.merge(vec![policy]).is_err(), "{name} was accepted");
+        }
+    }
 }
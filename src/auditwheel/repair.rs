@@ -1,21 +1,49 @@
 use super::audit::AuditWheelError;
 use crate::auditwheel::Policy;
 use anyhow::Result;
-use lddtree::DependencyAnalyzer;
+use glob::Pattern;
+use lddtree::{DependencyAnalyzer, DependencyTree};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
 /// Find external shared library dependencies
+///
+/// Libraries whose name matches one of the `exclude` patterns, e.g. `libcuda.so.*`, are skipped
+/// together with the libraries that are only needed through them.
 #[allow(clippy::result_large_err)]
 pub fn find_external_libs(
     artifact: impl AsRef<Path>,
     policy: &Policy,
     sysroot: PathBuf,
     ld_paths: Vec<PathBuf>,
+    exclude: &[Pattern],
 ) -> Result<Vec<lddtree::Library>, AuditWheelError> {
     let dep_analyzer = DependencyAnalyzer::new(sysroot).library_paths(ld_paths);
     let deps = dep_analyzer
         .analyze(artifact)
         .map_err(AuditWheelError::DependencyAnalysisError)?;
+    Ok(external_libs(deps, policy, exclude))
+}
+
+/// The libraries of the dependency tree that aren't allowed by the policy and are reachable
+/// without going through an excluded one
+fn external_libs(
+    deps: DependencyTree,
+    policy: &Policy,
+    exclude: &[Pattern],
+) -> Vec<lddtree::Library> {
+    let is_excluded = |name: &str| exclude.iter().any(|pattern| pattern.matches(name));
+    let mut reachable = HashSet::new();
+    let mut queue = deps.needed.clone();
+    while let Some(name) = queue.pop() {
+        if is_excluded(&name) || reachable.contains(&name) {
+            continue;
+        }
+        if let Some(lib) = deps.libraries.get(&name) {
+            queue.extend(lib.needed.iter().cloned());
+        }
+        reachable.insert(name);
+    }
     let mut ext_libs = Vec::new();
     for (_, lib) in deps.libraries {
         let name = &lib.name;
@@ -27,10 +55,88 @@ pub fn find_external_libs(
             || name.starts_with("ld-musl")
             || name.starts_with("libc.")
             || policy.lib_whitelist.contains(name)
+            || !reachable.contains(name)
         {
             continue;
         }
         ext_libs.push(lib);
     }
-    Ok(ext_libs)
+    ext_libs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn dependency_tree(needed: &[&str], libraries: &[(&str, &[&str])]) -> DependencyTree {
+        let to_strings = |names: &[&str]| names.iter().map(ToString::to_string).collect();
+        DependencyTree {
+            interpreter: None,
+            needed: to_strings(needed),
+            libraries: libraries
+                .iter()
+                .map(|(name, needed)| {
+                    let library = lddtree::Library {
+                        name: name.to_string(),
+                        path: PathBuf::from(format!("/usr/lib/{name}")),
+                        realpath: Some(PathBuf::from(format!("/usr/lib/{name}"))),
+                        needed: to_strings(needed),
+                        rpath: Vec::new(),
+                        runpath: Vec::new(),
+                    };
+                    (name.to_string(), library)
+                })
+                .collect::<HashMap<_, _>>(),
+            rpath: Vec::new(),
+            runpath: Vec::new(),
+        }
+    }
+
+    fn names(libs: Vec<lddtree::Library>) -> Vec<String> {
+        let mut names: Vec<String> = libs.into_iter().map(|lib| lib.name).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn skips_excluded_libraries_and_their_dependencies() {
+        // libfoo -> libbar, libcuda -> libnvidia-ptxjitcompiler, libcuda -> libbar
+        let deps = dependency_tree(
+            &["libfoo.so.1", "libcuda.so.1", "libc.so.6"],
+            &[
+                ("libfoo.so.1", &["libbar.so.2", "libc.so.6"]),
+                ("libbar.so.2", &["libc.so.6"]),
+                (
+                    "libcuda.so.1",
+                    &["libnvidia-ptxjitcompiler.so.1", "libbar.so.2"],
+                ),
+                ("libnvidia-ptxjitcompiler.so.1", &["libm.so.6"]),
+                ("libm.so.6", &["libc.so.6"]),
+                ("libc.so.6", &[]),
+            ],
+        );
+        let policy = Policy::from_name("manylinux_2_17").unwrap();
+
+        assert_eq!(
+            names(external_libs(deps.clone(), &policy, &[])),
+            vec![
+                "libbar.so.2",
+                "libcuda.so.1",
+                "libfoo.so.1",
+                "libnvidia-ptxjitcompiler.so.1"
+            ]
+        );
+        // libbar is still needed by libfoo, the nvidia library only through libcuda
+        let exclude = [Pattern::new("libcuda.so.*").unwrap()];
+        assert_eq!(
+            names(external_libs(deps.clone(), &policy, &exclude)),
+            vec!["libbar.so.2", "libfoo.so.1"]
+        );
+        let exclude = [
+            Pattern::new("libcuda.so.*").unwrap(),
+            Pattern::new("libfoo.so.*").unwrap(),
+        ];
+        assert!(external_libs(deps, &policy, &exclude).is_empty());
+    }
 }
@@ -0,0 +1,650 @@
+//! `maturin audit`, which checks the native libraries of any wheel against the manylinux and
+//! musllinux policies, independent of a build
+use super::audit::{
+    find_policy_violations, find_versioned_libraries, policy_is_satisfied, version_key,
+    AuditWheelError, PolicyViolations, VersionedLibrary,
+};
+use super::linker_map::LinkerMap;
+use super::policy::{Policies, Policy};
+use crate::module_writer::WheelFilename;
+use crate::target::Arch;
+use anyhow::{bail, Context, Result};
+use fs_err::File;
+use goblin::elf::header::{EM_386, EM_AARCH64, EM_ARM, EM_PPC64, EM_RISCV, EM_S390, EM_X86_64};
+use goblin::elf::Elf;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// `EM_LOONGARCH`, which goblin doesn't define yet
+const EM_LOONGARCH: u16 = 258;
+
+/// How `maturin audit` prints its results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum AuditFormat {
+    /// Human readable report
+    #[default]
+    Human,
+    /// JSON, one object per audited wheel
+    Json,
+}
+
+/// The manylinux or musllinux compliance of a shared library or executable in a wheel
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryAudit {
+    /// The path inside the wheel
+    pub path: String,
+    /// The architecture of the ELF file, e.g. `x86_64`
+    pub arch: String,
+    /// All `DT_NEEDED` entries
+    pub needed: Vec<String>,
+    /// The `DT_NEEDED` entries that are neither allowed by any policy nor shipped in the wheel
+    pub external: Vec<String>,
+    /// The versions of the versioned symbols that are needed from each library, e.g.
+    /// `GLIBC_2.17` from `libc.so.6`, sorted from oldest to newest
+    pub symbol_versions: BTreeMap<String, Vec<String>>,
+    /// The highest priority policy this library satisfies, if any
+    pub highest: Option<String>,
+    /// Why the policies above [LibraryAudit::highest] aren't satisfied, from the highest
+    /// claimed policy down, or only the next higher policy if nothing higher was claimed
+    pub rejected: Vec<PolicyRejection>,
+    #[serde(skip)]
+    highest_policy: Option<Policy>,
+}
+
+/// Why a library doesn't satisfy a policy
+#[derive(Debug, Clone, Serialize)]
+pub struct PolicyRejection {
+    /// The name of the policy, e.g. `manylinux_2_17`
+    pub policy: String,
+    /// The versioned symbols, black-listed symbols and libraries that disqualify the library
+    #[serde(flatten)]
+    pub violations: PolicyViolations,
+    /// The crates or object files that use each of the too new symbols, if a linker map was
+    /// given
+    pub origins: BTreeMap<String, Vec<String>>,
+}
+
+/// A manylinux or musllinux tag that a wheel claims in its filename
+#[derive(Debug, Clone, Serialize)]
+pub struct ClaimedTag {
+    /// The platform tag, e.g. `manylinux_2_17_x86_64`
+    pub tag: String,
+    /// The policy of the tag, if maturin knows it
+    pub policy: Option<String>,
+    /// Why the libraries in the wheel don't satisfy the tag, empty if they do
+    pub problems: Vec<String>,
+}
+
+/// The compliance report of a whole wheel
+#[derive(Debug, Clone, Serialize)]
+pub struct WheelAudit {
+    /// Path to the wheel
+    pub path: PathBuf,
+    /// The manylinux and musllinux tags from the filename
+    pub claimed: Vec<ClaimedTag>,
+    /// The highest priority policy that all libraries in the wheel satisfy, `linux` if there
+    /// is none, or nothing for wheels without native libraries
+    pub highest: Option<String>,
+    /// The shared libraries and executables in the wheel
+    pub libraries: Vec<LibraryAudit>,
+}
+
+impl WheelAudit {
+    /// Reads all ELF files from a wheel and checks them against the manylinux or musllinux
+    /// policies, and against the policies of the tags in the filename
+    ///
+    /// Libraries that are shipped in the wheel, e.g. grafted into `<package>.libs` by a
+    /// repair, are audited themselves instead of counting as external dependencies.
+    /// The symbols that keep a library from a policy are traced back to the crates that use
+    /// them with the `linker_map`, if given.
+    pub fn open(
+        path: impl AsRef<Path>,
+        linker_map: Option<&LinkerMap>,
+        policies: &Policies,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let filename = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .with_context(|| format!("Invalid file name {}", path.display()))?;
+        let wheel_filename = WheelFilename::parse(filename)?;
+        let claimed_tags: Vec<(String, Option<(Policy, String)>)> = wheel_filename
+            .platform_tag
+            .split('.')
+            .filter(|tag| tag.starts_with("manylinux") || tag.starts_with("musllinux"))
+            .map(|tag| (tag.to_string(), parse_platform_tag(tag, policies)))
+            .collect();
+
+        let mut archive = ZipArchive::new(File::open(path)?)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut elf_files = Vec::new();
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index)?;
+            if file.is_dir() {
+                continue;
+            }
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes)?;
+            if bytes.starts_with(b"\x7fELF") {
+                elf_files.push((file.name().to_string(), bytes));
+            }
+        }
+
+        let mut elfs = Vec::new();
+        for (name, bytes) in &elf_files {
+            let elf = Elf::parse(bytes)
+                .map_err(AuditWheelError::GoblinError)
+                .with_context(|| format!("Failed to parse {name}"))?;
+            elfs.push((name.as_str(), elf));
+        }
+        // The libraries in the wheel, by file name and soname
+        let mut shipped = HashSet::new();
+        for (name, elf) in &elfs {
+            shipped.insert(name.rsplit('/').next().unwrap_or(name).to_string());
+            shipped.extend(elf.soname.map(ToString::to_string));
+        }
+        let is_musl = claimed_tags
+            .iter()
+            .any(|(tag, _)| tag.starts_with("musllinux"))
+            || elfs.iter().any(|(_, elf)| {
+                elf.libraries
+                    .iter()
+                    .any(|lib| lib.starts_with("libc.musl") || lib.starts_with("ld-musl"))
+            });
+
+        let mut libraries = Vec::new();
+        let mut claimed: Vec<ClaimedTag> = claimed_tags
+            .iter()
+            .map(|(tag, policy)| ClaimedTag {
+                tag: tag.clone(),
+                policy: policy.as_ref().map(|(policy, _)| policy.name.clone()),
+                problems: Vec::new(),
+            })
+            .collect();
+        for (name, elf) in &elfs {
+            let arch = elf_arch(elf).with_context(|| {
+                format!(
+                    "{name} has the unsupported machine type {}",
+                    elf.header.e_machine
+                )
+            })?;
+            let policies = family_policies(policies, is_musl, arch);
+            let requested_priority = claimed_tags
+                .iter()
+                .filter_map(|(_, policy)| policy.as_ref())
+                .filter(|(policy, _)| policies.iter().any(|known| known.name == policy.name))
+                .map(|(policy, _)| policy.priority)
+                .max();
+            let audit = audit_library(
+                name,
+                elf,
+                arch,
+                &policies,
+                &shipped,
+                requested_priority,
+                linker_map,
+            );
+            for ((_, policy), claim) in claimed_tags.iter().zip(&mut claimed) {
+                let Some((policy, tag_arch)) = policy else {
+                    continue;
+                };
+                if tag_arch != &arch.to_string() {
+                    claim
+                        .problems
+                        .push(format!("{name} is built for {arch}, not for {tag_arch}"));
+                    continue;
+                }
+                let mut policy = policy.clone();
+                policy.fixup_musl_libc_so_name(arch);
+                let (deps, versioned_libraries) = dependencies(elf, &shipped);
+                if let Err(err) = policy_is_satisfied(
+                    &policy,
+                    elf,
+                    &arch.to_string(),
+                    &deps,
+                    &versioned_libraries,
+                    false,
+                ) {
+                    claim.problems.push(format!("{name}: {err}"));
+                }
+            }
+            libraries.push(audit);
+        }
+
+        let highest = if libraries.is_empty() {
+            None
+        } else {
+            let lowest = libraries
+                .iter()
+                .map(|library| library.highest_policy.as_ref())
+                .min_by_key(|policy| policy.map_or(i64::MIN, |policy| policy.priority))
+                .flatten();
+            Some(lowest.map_or_else(|| "linux".to_string(), |policy| policy.name.clone()))
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            claimed,
+            highest,
+            libraries,
+        })
+    }
+
+    /// Whether any of the claimed tags isn't satisfied
+    pub fn overclaims(&self) -> bool {
+        self.claimed.iter().any(|claim| !claim.problems.is_empty())
+    }
+
+    /// Prints the report in a human readable format to stdout
+    pub fn print_human(&self) {
+        println!("{}", self.path.display());
+        match &self.highest {
+            Some(highest) => println!("  Highest compatible policy: {highest}"),
+            None => println!("  No native libraries"),
+        }
+        for claim in &self.claimed {
+            match (&claim.policy, claim.problems.is_empty()) {
+                (None, _) => println!("  Tag {}: unknown policy, not checked", claim.tag),
+                (Some(_), true) => println!("  Tag {}: satisfied", claim.tag),
+                (Some(_), false) => {
+                    println!("  Tag {}: not satisfied", claim.tag);
+                    for problem in &claim.problems {
+                        println!("    {problem}");
+                    }
+                }
+            }
+        }
+        for library in &self.libraries {
+            println!();
+            println!("  {} ({})", library.path, library.arch);
+            println!(
+                "    Highest policy: {}",
+                library.highest.as_deref().unwrap_or("linux")
+            );
+            for rejection in &library.rejected {
+                println!("    Rejected {}:", rejection.policy);
+                rejection.print_human();
+            }
+            println!("    Needed: {}", join_or_none(&library.needed));
+            println!("    External: {}", join_or_none(&library.external));
+            if !library.symbol_versions.is_empty() {
+                println!("    Symbol versions:");
+                for (lib, versions) in &library.symbol_versions {
+                    println!("      {lib}: {}", versions.join(", "));
+                }
+            }
+        }
+    }
+}
+
+impl PolicyRejection {
+    fn print_human(&self) {
+        for too_new in &self.violations.too_new_versions {
+            let symbols: Vec<String> = too_new
+                .symbols
+                .iter()
+                .map(|symbol| match self.origins.get(symbol) {
+                    Some(origins) if !origins.is_empty() => {
+                        format!("{symbol} (from {})", origins.join(", "))
+                    }
+                    _ => symbol.clone(),
+                })
+                .collect();
+            if symbols.is_empty() {
+                println!("      {} {}", too_new.library, too_new.version);
+            } else {
+                println!(
+                    "      {} {}: {}",
+                    too_new.library,
+                    too_new.version,
+                    symbols.join(", ")
+                );
+            }
+        }
+        for (library, symbols) in &self.violations.blacklisted_symbols {
+            println!("      {library} black-listed: {}", symbols.join(", "));
+        }
+        if !self.violations.forbidden_libraries.is_empty() {
+            println!(
+                "      Not allowed: {}",
+                self.violations.forbidden_libraries.join(", ")
+            );
+        }
+    }
+}
+
+fn join_or_none(values: &[String]) -> String {
+    if values.is_empty() {
+        "none".to_string()
+    } else {
+        values.join(", ")
+    }
+}
+
+/// Splits a platform tag such as `manylinux_2_17_x86_64` or `manylinux2014_aarch64` into its
+/// policy and architecture
+fn parse_platform_tag(tag: &str, policies: &Policies) -> Option<(Policy, String)> {
+    policies
+        .manylinux()
+        .iter()
+        .chain(policies.musllinux())
+        .filter(|policy| policy.name != "linux")
+        .find_map(|policy| {
+            std::iter::once(&policy.name)
+                .chain(&policy.aliases)
+                .find_map(|name| tag.strip_prefix(&format!("{name}_")))
+                .filter(|arch| !arch.starts_with(|c: char| c.is_ascii_digit()))
+                .map(|arch| (policy.clone(), arch.to_string()))
+        })
+}
+
+/// The architecture of an ELF file, named as in the policies
+fn elf_arch(elf: &Elf) -> Option<Arch> {
+    match elf.header.e_machine {
+        EM_386 => Some(Arch::X86),
+        EM_X86_64 => Some(Arch::X86_64),
+        EM_AARCH64 => Some(Arch::Aarch64),
+        EM_ARM => Some(Arch::Armv7L),
+        EM_PPC64 if elf.little_endian => Some(Arch::Powerpc64Le),
+        EM_PPC64 => Some(Arch::Powerpc64),
+        EM_S390 => Some(Arch::S390X),
+        EM_RISCV if elf.is_64 => Some(Arch::Riscv64),
+        EM_LOONGARCH => Some(Arch::LoongArch64),
+        _ => None,
+    }
+}
+
+/// The manylinux or the musllinux policies, from highest to lowest priority, without `linux`
+fn family_policies(policies: &Policies, is_musl: bool, arch: Arch) -> Vec<Policy> {
+    let policies = if is_musl {
+        policies.musllinux()
+    } else {
+        policies.manylinux()
+    };
+    policies
+        .iter()
+        .filter(|policy| policy.name != "linux")
+        .cloned()
+        .map(|mut policy| {
+            policy.fixup_musl_libc_so_name(arch);
+            policy
+        })
+        .collect()
+}
+
+/// The `DT_NEEDED` entries and versioned libraries that aren't shipped in the wheel
+fn dependencies(elf: &Elf, shipped: &HashSet<String>) -> (Vec<String>, Vec<VersionedLibrary>) {
+    let deps = elf
+        .libraries
+        .iter()
+        .filter(|lib| !shipped.contains(**lib))
+        .map(ToString::to_string)
+        .collect();
+    let versioned_libraries = find_versioned_libraries(elf)
+        .into_iter()
+        .filter(|lib| !shipped.contains(&lib.name))
+        .collect();
+    (deps, versioned_libraries)
+}
+
+/// Checks a single ELF file against the policies, which are sorted from highest to lowest
+/// priority, and explains why the policies between the `requested_priority` and the highest
+/// satisfied one are rejected
+fn audit_library(
+    path: &str,
+    elf: &Elf,
+    arch: Arch,
+    policies: &[Policy],
+    shipped: &HashSet<String>,
+    requested_priority: Option<i64>,
+    linker_map: Option<&LinkerMap>,
+) -> LibraryAudit {
+    let (deps, versioned_libraries) = dependencies(elf, shipped);
+    let mut highest_policy = None;
+    let mut rejected = Vec::new();
+    for policy in policies {
+        match find_policy_violations(
+            policy,
+            elf,
+            &arch.to_string(),
+            &deps,
+            &versioned_libraries,
+            false,
+        ) {
+            Ok(violations) if violations.is_empty() => {
+                highest_policy = Some(policy.clone());
+                break;
+            }
+            Ok(violations) => rejected.push((policy.priority, policy.name.clone(), violations)),
+            // Older policies don't know about newer architectures
+            Err(_) => {}
+        }
+    }
+    // Only explain the policies that were asked for and the closest miss
+    let closest_miss = rejected.last().map(|(priority, ..)| *priority);
+    let rejected = rejected
+        .into_iter()
+        .filter(|(priority, ..)| {
+            Some(*priority) == closest_miss
+                || requested_priority.is_some_and(|requested| *priority <= requested)
+        })
+        .map(|(_, policy, violations)| {
+            let origins = linker_map
+                .map(|linker_map| {
+                    violations
+                        .too_new_versions
+                        .iter()
+                        .flat_map(|too_new| &too_new.symbols)
+                        .map(|symbol| (symbol.clone(), linker_map.origins(symbol)))
+                        .filter(|(_, origins)| !origins.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default();
+            PolicyRejection {
+                policy,
+                violations,
+                origins,
+            }
+        })
+        .collect();
+
+    let whitelisted: HashSet<&String> = policies
+        .iter()
+        .flat_map(|policy| &policy.lib_whitelist)
+        .collect();
+    let external = deps
+        .iter()
+        .filter(|dep| {
+            !whitelisted.contains(dep)
+                && !dep.starts_with("ld-linux")
+                && !dep.starts_with("ld64.so")
+        })
+        .cloned()
+        .collect();
+    let symbol_versions = find_versioned_libraries(elf)
+        .into_iter()
+        .map(|library| {
+            let versions: BTreeSet<String> = library.versions.into_iter().collect();
+            let mut versions: Vec<String> = versions.into_iter().collect();
+            versions.sort_by_key(|version| version_key(version));
+            (library.name, versions)
+        })
+        .collect();
+    LibraryAudit {
+        path: path.to_string(),
+        arch: arch.to_string(),
+        needed: elf.libraries.iter().map(ToString::to_string).collect(),
+        external,
+        symbol_versions,
+        highest: highest_policy.as_ref().map(|policy| policy.name.clone()),
+        rejected,
+        highest_policy,
+    }
+}
+
+/// Audits the wheels and prints the reports, failing if any wheel claims a tag that its native
+/// libraries don't satisfy
+pub fn audit_wheels(
+    files: &[PathBuf],
+    format: AuditFormat,
+    linker_map: Option<&Path>,
+) -> Result<()> {
+    let linker_map = linker_map.map(LinkerMap::read).transpose()?;
+    let policies = Policies::from_env()?;
+    let audits = files
+        .iter()
+        .map(|file| {
+            WheelAudit::open(file, linker_map.as_ref(), &policies)
+                .with_context(|| format!("Failed to audit {}", file.display()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    match format {
+        AuditFormat::Human => {
+            for (idx, audit) in audits.iter().enumerate() {
+                if idx > 0 {
+                    println!();
+                }
+                audit.print_human();
+            }
+        }
+        AuditFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&audits)?);
+        }
+    }
+    let overclaiming = audits.iter().filter(|audit| audit.overclaims()).count();
+    if overclaiming > 0 {
+        bail!(
+            "{overclaiming} of {} wheels claim a platform tag that their libraries don't satisfy",
+            audits.len()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_claimed_tags() {
+        let policies = Policies::default();
+        let (policy, arch) = parse_platform_tag("manylinux2014_x86_64", &policies).unwrap();
+        assert_eq!(
+            (policy.name.as_str(), arch.as_str()),
+            ("manylinux_2_17", "x86_64")
+        );
+        let (policy, arch) = parse_platform_tag("manylinux_2_28_aarch64", &policies).unwrap();
+        assert_eq!(
+            (policy.name.as_str(), arch.as_str()),
+            ("manylinux_2_28", "aarch64")
+        );
+        let (policy, arch) = parse_platform_tag("musllinux_1_2_armv7l", &policies).unwrap();
+        assert_eq!(
+            (policy.name.as_str(), arch.as_str()),
+            ("musllinux_1_2", "armv7l")
+        );
+        assert!(parse_platform_tag("manylinux_2_99_x86_64", &policies).is_none());
+    }
+
+    #[test]
+    fn sorts_symbol_versions_numerically() {
+        let mut versions = vec!["GLIBC_2.17", "GLIBC_2.2.5", "GLIBC_2.3", "GLIBC_2.3.4"];
+        versions.sort_by_key(|version| version_key(version));
+        assert_eq!(
+            versions,
+            vec!["GLIBC_2.2.5", "GLIBC_2.3", "GLIBC_2.3.4", "GLIBC_2.17"]
+        );
+    }
+
+    /// Builds a wheel with a shared library that calls `memcpy`, which needs `GLIBC_2.14` on
+    /// x86_64, so it doesn't satisfy `manylinux_2_5`
+    #[cfg(all(target_os = "linux", target_env = "gnu", target_arch = "x86_64"))]
+    fn build_wheel(dir: &Path, platform_tag: &str) -> PathBuf {
+        use std::io::Write;
+        use std::process::Command;
+        use zip::write::FileOptions;
+        use zip::ZipWriter;
+
+        fs_err::write(
+            dir.join("copy.c"),
+            "#include <string.h>\nvoid copy(void *d, const void *s, unsigned long n) { memcpy(d, s, n); }\n",
+        )
+        .unwrap();
+        let status = Command::new("cc")
+            .args(["-O0", "-shared", "-fPIC", "-o", "copy.so", "copy.c"])
+            .current_dir(dir)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let wheel = dir.join(format!("copy-1.0-py3-none-{platform_tag}.whl"));
+        let mut writer = ZipWriter::new(File::create(&wheel).unwrap());
+        writer
+            .start_file("copy/copy.so", FileOptions::default())
+            .unwrap();
+        writer
+            .write_all(&fs_err::read(dir.join("copy.so")).unwrap())
+            .unwrap();
+        writer.finish().unwrap();
+        wheel
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", target_env = "gnu", target_arch = "x86_64"))]
+    fn reports_overclaimed_tags() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let wheel = build_wheel(
+            tmp_dir.path(),
+            "manylinux_2_5_x86_64.manylinux1_x86_64.manylinux_2_17_x86_64",
+        );
+
+        let audit = WheelAudit::open(&wheel, None, &Policies::default()).unwrap();
+        assert!(audit.overclaims());
+        let report = serde_json::to_value(&audit).unwrap();
+        let library = &report["libraries"][0];
+        assert_eq!(library["path"], "copy/copy.so");
+        assert_eq!(library["arch"], "x86_64");
+        assert!(library["symbol_versions"]["libc.so.6"]
+            .as_array()
+            .unwrap()
+            .contains(&"GLIBC_2.14".into()));
+        let rejected = library["rejected"].as_array().unwrap();
+        assert_eq!(rejected[0]["policy"], "manylinux_2_5");
+        assert!(rejected[0]["too_new_versions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|version| version["version"] == "GLIBC_2.14"
+                && version["symbols"]
+                    .as_array()
+                    .unwrap()
+                    .contains(&"memcpy".into())));
+        let claimed = report["claimed"].as_array().unwrap();
+        assert_eq!(claimed.len(), 3);
+        for tag in &claimed[..2] {
+            assert_eq!(tag["policy"], "manylinux_2_5");
+            assert!(!tag["problems"].as_array().unwrap().is_empty());
+        }
+        assert_eq!(claimed[2]["policy"], "manylinux_2_17");
+        assert!(claimed[2]["problems"].as_array().unwrap().is_empty());
+
+        let err = audit_wheels(&[wheel], AuditFormat::Json, None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "1 of 1 wheels claim a platform tag that their libraries don't satisfy"
+        );
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", target_env = "gnu", target_arch = "x86_64"))]
+    fn accepts_satisfied_tags() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let wheel = build_wheel(tmp_dir.path(), "manylinux_2_17_x86_64.manylinux2014_x86_64");
+        let audit = WheelAudit::open(&wheel, None, &Policies::default()).unwrap();
+        assert!(!audit.overclaims());
+        assert!(audit.libraries[0].highest.is_some());
+        audit_wheels(&[wheel], AuditFormat::Json, None).unwrap();
+    }
+}
@@ -1,35 +1,48 @@
 use crate::auditwheel::{get_policy_and_libs, patchelf, relpath};
-use crate::auditwheel::{PlatformTag, Policy};
-use crate::build_options::CargoOptions;
-use crate::compile::{warn_missing_py_init, CompileTarget};
+use crate::auditwheel::{AuditWheelMode, PlatformTag, Policies, Policy};
+use crate::build_options::{CargoOptions, MessageFormat};
+use crate::compile::{compile_captured, warn_missing_py_init, CompileTarget};
+use crate::inspect::DistributionKind;
+use crate::metadata::LicenseFile;
 use crate::module_writer::{
-    add_data, write_bin, write_bindings_module, write_cffi_module, write_python_part,
-    write_uniffi_module, write_wasm_launcher, WheelWriter,
+    add_data, bindings_module_filename, expand_tag, write_bin, write_bindings_module,
+    write_cffi_module, write_python_part, write_uniffi_module, write_wasm_launcher, WheelWriter,
 };
 use crate::project_layout::ProjectLayout;
+use crate::pyproject_toml::EditableMode;
 use crate::python_interpreter::InterpreterKind;
+use crate::sbom::{write_sbom, CompiledCrate, SbomFormat};
 use crate::source_distribution::source_distribution;
 use crate::target::{Arch, Os};
+use crate::third_party_licenses::third_party_license_files;
 use crate::{
-    compile, pyproject_toml::Format, BuildArtifact, Metadata23, ModuleWriter, PyProjectToml,
-    PythonInterpreter, Target,
+    compile,
+    pyproject_toml::{Format, ThirdPartyLicenses},
+    BuildArtifact, Metadata23, ModuleWriter, PyProjectToml, PythonInterpreter, Target,
 };
 use anyhow::{anyhow, bail, Context, Result};
 use cargo_metadata::Metadata;
 use fs_err as fs;
+use glob::Pattern;
 use ignore::overrides::{Override, OverrideBuilder};
 use indexmap::IndexMap;
 use lddtree::Library;
 use normpath::PathExt;
+use once_cell::sync::OnceCell;
 use pep508_rs::Requirement;
 use platform_info::*;
+use serde::Serialize;
 use sha2::{Digest, Sha256};
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fmt::{Display, Formatter};
 use std::io;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
 use tracing::instrument;
 
 /// The way the rust code is used in the wheel
@@ -172,8 +185,26 @@ pub struct BuildContext {
     pub release: bool,
     /// Strip the library for minimum file size
     pub strip: bool,
-    /// Skip checking the linked libraries for manylinux/musllinux compliance
-    pub skip_auditwheel: bool,
+    /// What to do when the linked libraries aren't manylinux/musllinux compliant
+    pub auditwheel: AuditWheelMode,
+    /// The sonames of shared libraries that aren't copied into the wheel
+    pub auditwheel_exclude: Vec<Pattern>,
+    /// The manylinux/musllinux policies, including those from policy files
+    pub policies: Policies,
+    /// Make the wheels and the source distribution byte-for-byte reproducible
+    pub reproducible: bool,
+    /// Embed a software bill of materials in this format
+    pub sbom: Option<SbomFormat>,
+    /// The crates compiled into the wheels, resolved with `cargo metadata` on first use
+    pub(crate) compiled_crates: OnceCell<Vec<CompiledCrate>>,
+    /// Bundle the license files of the third-party crates in the wheels with this configuration
+    pub third_party_licenses: Option<ThirdPartyLicenses>,
+    /// The license files of the third-party crates, collected when the first wheel is written
+    pub(crate) third_party_license_files: OnceCell<Vec<LicenseFile>>,
+    /// Build number for the wheel filenames and the WHEEL files
+    pub build_tag: Option<String>,
+    /// The local version label that was appended to the version in [BuildContext::metadata23]
+    pub local_version: Option<String>,
     /// When compiling for manylinux, use zig as linker to ensure glibc version compliance
     #[cfg(feature = "zig")]
     pub zig: bool,
@@ -181,6 +212,8 @@ pub struct BuildContext {
     pub platform_tag: Vec<PlatformTag>,
     /// The available python interpreter
     pub interpreter: Vec<PythonInterpreter>,
+    /// The number of interpreters to compile for concurrently
+    pub interpreter_jobs: usize,
     /// Cargo.toml as resolved by [cargo_metadata]
     pub cargo_metadata: Metadata,
     /// Whether to use universal2 or use the native macOS tag (off)
@@ -191,11 +224,94 @@ pub struct BuildContext {
     pub cargo_options: CargoOptions,
 }
 
-/// The wheel file location and its Python version tag (e.g. `py3`).
-///
-/// For bindings the version tag contains the Python interpreter version
-/// they bind against (e.g. `cp37`).
-pub type BuiltWheelMetadata = (PathBuf, String);
+/// A wheel or source distribution built by [BuildContext::build_wheels] or
+/// [BuildContext::build_source_distribution], as printed by `--message-format json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BuiltWheelMetadata {
+    /// Wheel or sdist
+    pub kind: DistributionKind,
+    /// The location of the wheel or source distribution
+    pub path: PathBuf,
+    /// The hex encoded sha256 of the file
+    pub sha256: String,
+    /// The file size in bytes
+    pub size: u64,
+    /// The Python version tag, e.g. `py3`, or `source` for source distributions
+    ///
+    /// For bindings the version tag contains the Python interpreter version
+    /// they bind against (e.g. `cp37`).
+    pub python_tag: String,
+    /// All `{python tag}-{abi tag}-{platform tag}` triples the wheel is compatible with,
+    /// empty for source distributions
+    pub tags: Vec<String>,
+    /// The bridge model, e.g. `pyo3` or `cffi`
+    pub bridge: String,
+    /// The interpreter the wheel was built for, if it was built for a specific one
+    pub interpreter: Option<BuiltInterpreter>,
+    /// The manylinux or musllinux tag auditwheel found the artifacts to be compliant with,
+    /// if auditwheel ran
+    pub auditwheel_platform_tag: Option<String>,
+    /// External shared libraries that were copied into the wheel
+    pub grafted_libraries: Vec<GraftedLibrary>,
+}
+
+/// The report printed by `--message-format json` after building
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildReport {
+    /// All built wheels and source distributions, in the order they were built
+    pub artifacts: Vec<BuiltWheelMetadata>,
+}
+
+impl BuildReport {
+    /// Prints the report as JSON to stdout if requested. The human readable output is printed
+    /// while building, so there is nothing to do for [MessageFormat::Human]
+    pub fn print(&self, message_format: MessageFormat) -> Result<()> {
+        match message_format {
+            MessageFormat::Human => {}
+            MessageFormat::Json => println!("{}", serde_json::to_string(self)?),
+        }
+        Ok(())
+    }
+}
+
+/// The python interpreter a wheel was built for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BuiltInterpreter {
+    /// CPython, PyPy or GraalPy
+    pub implementation: String,
+    /// Python major version
+    pub major: usize,
+    /// Python minor version
+    pub minor: usize,
+    /// The abiflags, e.g. `t` for free-threaded builds
+    pub abiflags: String,
+    /// The executable, if the interpreter was run on the host
+    pub executable: Option<PathBuf>,
+}
+
+impl From<&PythonInterpreter> for BuiltInterpreter {
+    fn from(interpreter: &PythonInterpreter) -> Self {
+        Self {
+            implementation: interpreter.interpreter_kind.to_string(),
+            major: interpreter.major,
+            minor: interpreter.minor,
+            abiflags: interpreter.abiflags.clone(),
+            executable: interpreter.runnable.then(|| interpreter.executable.clone()),
+        }
+    }
+}
+
+/// An external shared library that auditwheel copied into the wheel
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GraftedLibrary {
+    /// The name the artifacts were linked against, e.g. `libfoo.so.1`
+    pub name: String,
+    /// Where the library was copied from
+    pub source: PathBuf,
+    /// The path of the library inside the wheel, with its new, hashed soname
+    pub path: PathBuf,
+}
 
 impl BuildContext {
     /// Checks which kind of bindings we have (pyo3/rust-cypthon or cffi or bin) and calls the
@@ -273,7 +389,18 @@ impl BuildContext {
                 let sdist_path =
                     source_distribution(self, pyproject, self.excludes(Format::Sdist)?)
                         .context("Failed to build source distribution")?;
-                Ok(Some((sdist_path, "source".to_string())))
+                Ok(Some(BuiltWheelMetadata {
+                    kind: DistributionKind::Sdist,
+                    sha256: hash_file(&sdist_path)?,
+                    size: fs::metadata(&sdist_path)?.len(),
+                    path: sdist_path,
+                    python_tag: "source".to_string(),
+                    tags: Vec::new(),
+                    bridge: self.bridge().to_string(),
+                    interpreter: None,
+                    auditwheel_platform_tag: None,
+                    grafted_libraries: Vec::new(),
+                }))
             }
             None => Ok(None),
         }
@@ -285,7 +412,7 @@ impl BuildContext {
         platform_tag: &[PlatformTag],
         python_interpreter: Option<&PythonInterpreter>,
     ) -> Result<(Policy, Vec<Library>)> {
-        if self.skip_auditwheel {
+        if self.auditwheel == AuditWheelMode::Skip {
             return Ok((Policy::default(), Vec::new()));
         }
 
@@ -316,17 +443,31 @@ impl BuildContext {
 
         // only bin bindings allow linking to libpython, extension modules must not
         let allow_linking_libpython = self.bridge().is_bin();
-        if self.bridge().is_bin() && !musllinux.is_empty() {
-            return get_policy_and_libs(
-                artifact,
-                Some(musllinux[0]),
-                &self.target,
-                allow_linking_libpython,
-            );
-        }
+        let tag = if self.bridge().is_bin() && !musllinux.is_empty() {
+            Some(musllinux[0])
+        } else {
+            others.first().or_else(|| musllinux.first()).copied()
+        };
+        let result = get_policy_and_libs(
+            artifact,
+            tag,
+            &self.target,
+            allow_linking_libpython,
+            &self.auditwheel_exclude,
+            &self.policies,
+        );
+        apply_auditwheel_mode(self.auditwheel, &artifact.path, result)
+    }
 
-        let tag = others.first().or_else(|| musllinux.first()).copied();
-        get_policy_and_libs(artifact, tag, &self.target, allow_linking_libpython)
+    /// The platform tags of a wheel, which are the requested ones unless auditwheel fell back
+    /// to `linux` with `--auditwheel warn`
+    fn wheel_platform_tags(&self, policy: &Policy) -> Vec<PlatformTag> {
+        wheel_platform_tags(
+            self.auditwheel,
+            self.target.is_linux(),
+            &self.platform_tag,
+            policy,
+        )
     }
 
     /// Add library search paths in Cargo target directory rpath when building in editable mode
@@ -361,12 +502,13 @@ impl BuildContext {
         writer: &mut WheelWriter,
         artifacts: &[&BuildArtifact],
         ext_libs: &[Vec<Library>],
-    ) -> Result<()> {
+    ) -> Result<Vec<GraftedLibrary>> {
         if self.editable {
-            return self.add_rpath(artifacts);
+            self.add_rpath(artifacts)?;
+            return Ok(Vec::new());
         }
         if ext_libs.iter().all(|libs| libs.is_empty()) {
-            return Ok(());
+            return Ok(Vec::new());
         }
 
         patchelf::verify_patchelf()?;
@@ -386,6 +528,7 @@ impl BuildContext {
         let temp_dir = tempfile::tempdir()?;
         let mut soname_map = HashMap::new();
         let mut libs_copied = HashSet::new();
+        let mut grafted = Vec::new();
         for lib in ext_libs.iter().flatten() {
             let lib_path = lib.realpath.clone().with_context(|| {
                 format!(
@@ -406,7 +549,13 @@ impl BuildContext {
             // for example soname and rpath
             let dest_path = temp_dir.path().join(&new_soname);
             fs::copy(&lib_path, &dest_path)?;
-            libs_copied.insert(lib_path);
+            if libs_copied.insert(lib_path.clone()) {
+                grafted.push(GraftedLibrary {
+                    name: lib.name.clone(),
+                    source: lib_path,
+                    path: libs_dir.join(&new_soname),
+                });
+            }
 
             // fs::copy copies permissions as well, and the original
             // file may have been read-only
@@ -478,12 +627,58 @@ impl BuildContext {
             let new_rpath = new_rpaths.join(":");
             patchelf::set_rpath(&artifact.path, &new_rpath)?;
         }
+        Ok(grafted)
+    }
+
+    /// The metadata of the wheels, which unlike the source distribution also lists the license
+    /// files of the third-party crates. Those are collected for the first wheel, so that source
+    /// distributions don't need to resolve them
+    pub fn wheel_metadata23(&self) -> Result<Cow<'_, Metadata23>> {
+        let Some(config) = &self.third_party_licenses else {
+            return Ok(Cow::Borrowed(&self.metadata23));
+        };
+        let license_files = self
+            .third_party_license_files
+            .get_or_try_init(|| third_party_license_files(self, config))?;
+        if license_files.is_empty() {
+            Ok(Cow::Borrowed(&self.metadata23))
+        } else {
+            let mut metadata23 = self.metadata23.clone();
+            metadata23
+                .license_files
+                .extend(license_files.iter().cloned());
+            Ok(Cow::Owned(metadata23))
+        }
+    }
+
+    fn add_sbom(
+        &self,
+        writer: &mut WheelWriter,
+        metadata23: &Metadata23,
+        grafted_libraries: &[GraftedLibrary],
+    ) -> Result<()> {
+        if let Some(format) = self.sbom {
+            write_sbom(writer, self, metadata23, format, grafted_libraries)?;
+        }
         Ok(())
     }
 
-    fn add_pth(&self, writer: &mut WheelWriter) -> Result<()> {
+    /// Makes the python sources importable for editable installs, using the `[tool.maturin]`
+    /// editable mode. `extension` is the file name of the extension module that was copied into
+    /// the source tree, if any
+    fn add_pth(&self, writer: &mut WheelWriter, extension: Option<&str>) -> Result<()> {
         if self.editable {
-            writer.add_pth(&self.project_layout, &self.metadata23)?;
+            let editable_mode = self
+                .pyproject_toml
+                .as_ref()
+                .map(|pyproject| pyproject.editable_mode())
+                .unwrap_or_default();
+            match editable_mode {
+                EditableMode::Pth => writer.add_pth(&self.project_layout, &self.metadata23)?,
+                EditableMode::Finder => {
+                    writer.add_editable_finder(&self.project_layout, &self.metadata23, extension)?
+                }
+            }
         }
         Ok(())
     }
@@ -533,7 +728,7 @@ impl BuildContext {
                 let mut tags = vec![];
                 for platform_tag in platform_tags {
                     tags.push(format!("{platform_tag}_{arch}"));
-                    for alias in platform_tag.aliases() {
+                    for alias in self.policies.aliases(&platform_tag) {
                         tags.push(format!("{alias}_{arch}"));
                     }
                 }
@@ -647,6 +842,41 @@ impl BuildContext {
         Ok((tag, tags))
     }
 
+    /// Collects the metadata of a freshly written wheel. The interpreter and the auditwheel
+    /// result are filled in by the caller
+    fn built_wheel(
+        &self,
+        wheel_path: PathBuf,
+        python_tag: String,
+        tags: &[String],
+        grafted_libraries: Vec<GraftedLibrary>,
+    ) -> Result<BuiltWheelMetadata> {
+        Ok(BuiltWheelMetadata {
+            kind: DistributionKind::Wheel,
+            sha256: hash_file(&wheel_path)?,
+            size: fs::metadata(&wheel_path)?.len(),
+            path: wheel_path,
+            python_tag,
+            tags: tags.iter().flat_map(|tag| expand_tag(tag)).collect(),
+            bridge: self.bridge().to_string(),
+            interpreter: None,
+            auditwheel_platform_tag: None,
+            grafted_libraries,
+        })
+    }
+
+    /// The platform tag auditwheel decided on, `None` if it didn't run
+    fn auditwheel_platform_tag(&self, policy: &Policy) -> Option<String> {
+        if self.auditwheel == AuditWheelMode::Skip
+            || !self.target.is_linux()
+            || policy.name == "linux"
+        {
+            None
+        } else {
+            Some(policy.platform_tag().to_string())
+        }
+    }
+
     fn write_binding_wheel_abi3(
         &self,
         artifact: BuildArtifact,
@@ -658,14 +888,17 @@ impl BuildContext {
         let platform = self.get_platform_tag(platform_tags)?;
         let tag = format!("cp{major}{min_minor}-abi3-{platform}");
 
+        let metadata23 = self.wheel_metadata23()?;
         let mut writer = WheelWriter::new(
             &tag,
             &self.out,
-            &self.metadata23,
+            &metadata23,
+            self.build_tag.as_deref(),
             &[tag.clone()],
             self.excludes(Format::Wheel)?,
+            self.reproducible,
         )?;
-        self.add_external_libs(&mut writer, &[&artifact], &[ext_libs])?;
+        let grafted = self.add_external_libs(&mut writer, &[&artifact], &[ext_libs])?;
 
         write_bindings_module(
             &mut writer,
@@ -679,10 +912,17 @@ impl BuildContext {
         )
         .context("Failed to add the files to the wheel")?;
 
-        self.add_pth(&mut writer)?;
+        self.add_sbom(&mut writer, &metadata23, &grafted)?;
+        let extension = bindings_module_filename(
+            &self.project_layout.extension_name,
+            self.interpreter.first(),
+            true,
+            &self.target,
+        );
+        self.add_pth(&mut writer, Some(&extension))?;
         add_data(&mut writer, self.project_layout.data.as_deref())?;
         let wheel_path = writer.finish()?;
-        Ok((wheel_path, format!("cp{major}{min_minor}")))
+        self.built_wheel(wheel_path, format!("cp{major}{min_minor}"), &[tag], grafted)
     }
 
     /// For abi3 we only need to build a single wheel and we don't even need a python interpreter
@@ -703,26 +943,24 @@ impl BuildContext {
         )?;
         let (policy, external_libs) =
             self.auditwheel(&artifact, &self.platform_tag, python_interpreter)?;
-        let platform_tags = if self.platform_tag.is_empty() {
-            vec![policy.platform_tag()]
-        } else {
-            self.platform_tag.clone()
-        };
-        let (wheel_path, tag) = self.write_binding_wheel_abi3(
+        let platform_tags = self.wheel_platform_tags(&policy);
+        let mut wheel = self.write_binding_wheel_abi3(
             artifact,
             &platform_tags,
             external_libs,
             major,
             min_minor,
         )?;
+        wheel.interpreter = python_interpreter.map(BuiltInterpreter::from);
+        wheel.auditwheel_platform_tag = self.auditwheel_platform_tag(&policy);
 
         eprintln!(
             "📦 Built wheel for abi3 Python ≥ {}.{} to {}",
             major,
             min_minor,
-            wheel_path.display()
+            wheel.path.display()
         );
-        wheels.push((wheel_path, tag));
+        wheels.push(wheel);
 
         Ok(wheels)
     }
@@ -736,14 +974,17 @@ impl BuildContext {
     ) -> Result<BuiltWheelMetadata> {
         let tag = python_interpreter.get_tag(self, platform_tags)?;
 
+        let metadata23 = self.wheel_metadata23()?;
         let mut writer = WheelWriter::new(
             &tag,
             &self.out,
-            &self.metadata23,
+            &metadata23,
+            self.build_tag.as_deref(),
             &[tag.clone()],
             self.excludes(Format::Wheel)?,
+            self.reproducible,
         )?;
-        self.add_external_libs(&mut writer, &[&artifact], &[ext_libs])?;
+        let grafted = self.add_external_libs(&mut writer, &[&artifact], &[ext_libs])?;
 
         write_bindings_module(
             &mut writer,
@@ -757,13 +998,24 @@ impl BuildContext {
         )
         .context("Failed to add the files to the wheel")?;
 
-        self.add_pth(&mut writer)?;
+        self.add_sbom(&mut writer, &metadata23, &grafted)?;
+        let extension = bindings_module_filename(
+            &self.project_layout.extension_name,
+            Some(python_interpreter),
+            false,
+            &self.target,
+        );
+        self.add_pth(&mut writer, Some(&extension))?;
         add_data(&mut writer, self.project_layout.data.as_deref())?;
         let wheel_path = writer.finish()?;
-        Ok((
+        let mut wheel = self.built_wheel(
             wheel_path,
             format!("cp{}{}", python_interpreter.major, python_interpreter.minor),
-        ))
+            &[tag],
+            grafted,
+        )?;
+        wheel.interpreter = Some(python_interpreter.into());
+        Ok(wheel)
     }
 
     /// Builds wheels for a Cargo project for all given python versions.
@@ -778,34 +1030,40 @@ impl BuildContext {
         interpreters: &[PythonInterpreter],
     ) -> Result<Vec<BuiltWheelMetadata>> {
         let mut wheels = Vec::new();
+        let mut precompiled = if self.interpreter_jobs > 1 && interpreters.len() > 1 {
+            self.compile_cdylibs_concurrently(interpreters)?
+        } else {
+            Vec::new()
+        }
+        .into_iter();
         for python_interpreter in interpreters {
-            let artifact = self.compile_cdylib(
-                Some(python_interpreter),
-                Some(&self.project_layout.extension_name),
-            )?;
+            let artifact = match precompiled.next() {
+                Some(artifact) => artifact,
+                None => self.compile_cdylib(
+                    Some(python_interpreter),
+                    Some(&self.project_layout.extension_name),
+                )?,
+            };
             let (policy, external_libs) =
                 self.auditwheel(&artifact, &self.platform_tag, Some(python_interpreter))?;
-            let platform_tags = if self.platform_tag.is_empty() {
-                vec![policy.platform_tag()]
-            } else {
-                self.platform_tag.clone()
-            };
-            let (wheel_path, tag) = self.write_binding_wheel(
+            let platform_tags = self.wheel_platform_tags(&policy);
+            let mut wheel = self.write_binding_wheel(
                 python_interpreter,
                 artifact,
                 &platform_tags,
                 external_libs,
             )?;
+            wheel.auditwheel_platform_tag = self.auditwheel_platform_tag(&policy);
             eprintln!(
                 "📦 Built wheel for {} {}.{}{} to {}",
                 python_interpreter.interpreter_kind,
                 python_interpreter.major,
                 python_interpreter.minor,
                 python_interpreter.abiflags,
-                wheel_path.display()
+                wheel.path.display()
             );
 
-            wheels.push((wheel_path, tag));
+            wheels.push(wheel);
         }
 
         Ok(wheels)
@@ -822,6 +1080,94 @@ impl BuildContext {
     ) -> Result<BuildArtifact> {
         let artifacts = compile(self, python_interpreter, &self.compile_targets)
             .context("Failed to build a native library through cargo")?;
+        self.cdylib_artifact(artifacts, extension_name)
+    }
+
+    /// Compiles the cdylib for each interpreter with up to [BuildContext::interpreter_jobs]
+    /// cargo builds running at the same time
+    ///
+    /// Each interpreter gets its own target directory from [interpreter_target_dir], since pyo3's
+    /// build script reruns whenever `PYO3_PYTHON` changes. The output of each build is printed
+    /// once it and all builds for the interpreters before it finished, so it's in the same order
+    /// as the interpreters. After the first failed build no further builds are started.
+    fn compile_cdylibs_concurrently(
+        &self,
+        interpreters: &[PythonInterpreter],
+    ) -> Result<Vec<BuildArtifact>> {
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|scope| {
+            for _ in 0..self.interpreter_jobs.min(interpreters.len()) {
+                let sender = sender.clone();
+                let next = &next;
+                let failed = &failed;
+                scope.spawn(move || loop {
+                    if failed.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(interpreter) = interpreters.get(index) else {
+                        break;
+                    };
+                    let mut context = self.clone();
+                    context.target_dir = interpreter_target_dir(
+                        &self.target_dir,
+                        &interpreter.environment_signature(),
+                    );
+                    context.cargo_options.target_dir = Some(context.target_dir.clone());
+                    let (artifacts, output) =
+                        compile_captured(&context, Some(interpreter), &context.compile_targets);
+                    let artifact = artifacts
+                        .context("Failed to build a native library through cargo")
+                        .and_then(|artifacts| {
+                            context.cdylib_artifact(
+                                artifacts,
+                                Some(&self.project_layout.extension_name),
+                            )
+                        });
+                    if artifact.is_err() {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+                    if sender.send((index, artifact, output)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(sender);
+
+            let mut finished = HashMap::new();
+            let mut artifacts = Vec::with_capacity(interpreters.len());
+            for (index, artifact, output) in receiver {
+                finished.insert(index, (artifact, output));
+                while let Some((artifact, output)) = finished.remove(&artifacts.len()) {
+                    let interpreter = &interpreters[artifacts.len()];
+                    let result = if artifact.is_ok() {
+                        "Compiled"
+                    } else {
+                        "Failed to compile"
+                    };
+                    eprintln!(
+                        "🔨 {result} for {} {}.{}{}",
+                        interpreter.interpreter_kind,
+                        interpreter.major,
+                        interpreter.minor,
+                        interpreter.abiflags,
+                    );
+                    eprint!("{output}");
+                    artifacts.push(artifact);
+                }
+            }
+            artifacts.into_iter().collect()
+        })
+    }
+
+    /// Picks the cdylib from the artifacts of [compile] and copies it for auditwheel
+    fn cdylib_artifact(
+        &self,
+        artifacts: Vec<HashMap<String, BuildArtifact>>,
+        extension_name: Option<&str>,
+    ) -> Result<BuildArtifact> {
         let error_msg = "Cargo didn't build a cdylib. Did you miss crate-type = [\"cdylib\"] \
                  in the lib section of your Cargo.toml?";
         let artifacts = artifacts.first().context(error_msg)?;
@@ -837,7 +1183,7 @@ impl BuildContext {
             let _ = warn_missing_py_init(&artifact.path, extension_name);
         }
 
-        if self.editable || self.skip_auditwheel {
+        if self.editable || self.auditwheel == AuditWheelMode::Skip {
             return Ok(artifact);
         }
         // auditwheel repair will edit the file, so we need to copy it to avoid errors in reruns
@@ -858,14 +1204,17 @@ impl BuildContext {
     ) -> Result<BuiltWheelMetadata> {
         let (tag, tags) = self.get_universal_tags(platform_tags)?;
 
+        let metadata23 = self.wheel_metadata23()?;
         let mut writer = WheelWriter::new(
             &tag,
             &self.out,
-            &self.metadata23,
+            &metadata23,
+            self.build_tag.as_deref(),
             &tags,
             self.excludes(Format::Wheel)?,
+            self.reproducible,
         )?;
-        self.add_external_libs(&mut writer, &[&artifact], &[ext_libs])?;
+        let grafted = self.add_external_libs(&mut writer, &[&artifact], &[ext_libs])?;
 
         write_cffi_module(
             &mut writer,
@@ -879,10 +1228,11 @@ impl BuildContext {
             self.pyproject_toml.as_ref(),
         )?;
 
-        self.add_pth(&mut writer)?;
+        self.add_sbom(&mut writer, &metadata23, &grafted)?;
+        self.add_pth(&mut writer, None)?;
         add_data(&mut writer, self.project_layout.data.as_deref())?;
         let wheel_path = writer.finish()?;
-        Ok((wheel_path, "py3".to_string()))
+        self.built_wheel(wheel_path, "py3".to_string(), &tags, grafted)
     }
 
     /// Builds a wheel with cffi bindings
@@ -890,12 +1240,9 @@ impl BuildContext {
         let mut wheels = Vec::new();
         let artifact = self.compile_cdylib(None, None)?;
         let (policy, external_libs) = self.auditwheel(&artifact, &self.platform_tag, None)?;
-        let platform_tags = if self.platform_tag.is_empty() {
-            vec![policy.platform_tag()]
-        } else {
-            self.platform_tag.clone()
-        };
-        let (wheel_path, tag) = self.write_cffi_wheel(artifact, &platform_tags, external_libs)?;
+        let platform_tags = self.wheel_platform_tags(&policy);
+        let mut wheel = self.write_cffi_wheel(artifact, &platform_tags, external_libs)?;
+        wheel.auditwheel_platform_tag = self.auditwheel_platform_tag(&policy);
 
         // Warn if cffi isn't specified in the requirements
         if !self
@@ -910,8 +1257,8 @@ impl BuildContext {
             );
         }
 
-        eprintln!("📦 Built wheel to {}", wheel_path.display());
-        wheels.push((wheel_path, tag));
+        eprintln!("📦 Built wheel to {}", wheel.path.display());
+        wheels.push(wheel);
 
         Ok(wheels)
     }
@@ -924,14 +1271,17 @@ impl BuildContext {
     ) -> Result<BuiltWheelMetadata> {
         let (tag, tags) = self.get_universal_tags(platform_tags)?;
 
+        let metadata23 = self.wheel_metadata23()?;
         let mut writer = WheelWriter::new(
             &tag,
             &self.out,
-            &self.metadata23,
+            &metadata23,
+            self.build_tag.as_deref(),
             &tags,
             self.excludes(Format::Wheel)?,
+            self.reproducible,
         )?;
-        self.add_external_libs(&mut writer, &[&artifact], &[ext_libs])?;
+        let grafted = self.add_external_libs(&mut writer, &[&artifact], &[ext_libs])?;
 
         write_uniffi_module(
             &mut writer,
@@ -945,10 +1295,11 @@ impl BuildContext {
             self.pyproject_toml.as_ref(),
         )?;
 
-        self.add_pth(&mut writer)?;
+        self.add_sbom(&mut writer, &metadata23, &grafted)?;
+        self.add_pth(&mut writer, None)?;
         add_data(&mut writer, self.project_layout.data.as_deref())?;
         let wheel_path = writer.finish()?;
-        Ok((wheel_path, "py3".to_string()))
+        self.built_wheel(wheel_path, "py3".to_string(), &tags, grafted)
     }
 
     /// Builds a wheel with uniffi bindings
@@ -956,15 +1307,12 @@ impl BuildContext {
         let mut wheels = Vec::new();
         let artifact = self.compile_cdylib(None, None)?;
         let (policy, external_libs) = self.auditwheel(&artifact, &self.platform_tag, None)?;
-        let platform_tags = if self.platform_tag.is_empty() {
-            vec![policy.platform_tag()]
-        } else {
-            self.platform_tag.clone()
-        };
-        let (wheel_path, tag) = self.write_uniffi_wheel(artifact, &platform_tags, external_libs)?;
+        let platform_tags = self.wheel_platform_tags(&policy);
+        let mut wheel = self.write_uniffi_wheel(artifact, &platform_tags, external_libs)?;
+        wheel.auditwheel_platform_tag = self.auditwheel_platform_tag(&policy);
 
-        eprintln!("📦 Built wheel to {}", wheel_path.display());
-        wheels.push((wheel_path, tag));
+        eprintln!("📦 Built wheel to {}", wheel.path.display());
+        wheels.push(wheel);
 
         Ok(wheels)
     }
@@ -1013,17 +1361,19 @@ impl BuildContext {
         }
 
         let metadata23 = if self.target.is_wasi() {
-            bin_wasi_helper(&artifacts_and_files, self.metadata23.clone())?
+            bin_wasi_helper(&artifacts_and_files, self.wheel_metadata23()?.into_owned())?
         } else {
-            self.metadata23.clone()
+            self.wheel_metadata23()?.into_owned()
         };
 
         let mut writer = WheelWriter::new(
             &tag,
             &self.out,
             &metadata23,
+            self.build_tag.as_deref(),
             &tags,
             self.excludes(Format::Wheel)?,
+            self.reproducible,
         )?;
 
         if self.project_layout.python_module.is_some() && self.target.is_wasi() {
@@ -1048,12 +1398,15 @@ impl BuildContext {
                 write_wasm_launcher(&mut writer, &self.metadata23, bin_name)?;
             }
         }
-        self.add_external_libs(&mut writer, &artifacts_ref, ext_libs)?;
+        let grafted = self.add_external_libs(&mut writer, &artifacts_ref, ext_libs)?;
 
-        self.add_pth(&mut writer)?;
+        self.add_sbom(&mut writer, &metadata23, &grafted)?;
+        self.add_pth(&mut writer, None)?;
         add_data(&mut writer, self.project_layout.data.as_deref())?;
         let wheel_path = writer.finish()?;
-        Ok((wheel_path, "py3".to_string()))
+        let mut wheel = self.built_wheel(wheel_path, "py3".to_string(), &tags, grafted)?;
+        wheel.interpreter = python_interpreter.map(BuiltInterpreter::from);
+        Ok(wheel)
     }
 
     /// Builds a wheel that contains a binary
@@ -1085,20 +1438,17 @@ impl BuildContext {
             artifact_paths.push(artifact);
         }
         let policy = policies.iter().min_by_key(|p| p.priority).unwrap();
-        let platform_tags = if self.platform_tag.is_empty() {
-            vec![policy.platform_tag()]
-        } else {
-            self.platform_tag.clone()
-        };
+        let platform_tags = self.wheel_platform_tags(policy);
 
-        let (wheel_path, tag) = self.write_bin_wheel(
+        let mut wheel = self.write_bin_wheel(
             python_interpreter,
             &artifact_paths,
             &platform_tags,
             &ext_libs,
         )?;
-        eprintln!("📦 Built wheel to {}", wheel_path.display());
-        wheels.push((wheel_path, tag));
+        wheel.auditwheel_platform_tag = self.auditwheel_platform_tag(policy);
+        eprintln!("📦 Built wheel to {}", wheel.path.display());
+        wheels.push(wheel);
 
         Ok(wheels)
     }
@@ -1118,6 +1468,12 @@ impl BuildContext {
     }
 }
 
+/// The cargo target directory for the builds of one interpreter ABI, so that building for
+/// several interpreters doesn't make cargo rebuild for each of them in turn
+pub(crate) fn interpreter_target_dir(target_dir: &Path, key: &str) -> PathBuf {
+    target_dir.join("interpreters").join(key)
+}
+
 /// Calculate the sha256 of a file
 pub fn hash_file(path: impl AsRef<Path>) -> Result<String, io::Error> {
     let mut file = fs::File::open(path.as_ref())?;
@@ -1263,10 +1619,82 @@ fn emcc_version() -> Result<String> {
     Ok(String::from_utf8(version.as_bytes().to_vec())?)
 }
 
+/// Applies the `--auditwheel` mode to the result of the policy check of an artifact
+///
+/// `check` fails when libraries would need to be copied into the wheel and `warn` falls back to
+/// the `linux` policy instead of failing.
+fn apply_auditwheel_mode(
+    mode: AuditWheelMode,
+    artifact: &Path,
+    result: Result<(Policy, Vec<Library>)>,
+) -> Result<(Policy, Vec<Library>)> {
+    match mode {
+        AuditWheelMode::Repair | AuditWheelMode::Skip => result,
+        AuditWheelMode::Check => {
+            let (policy, external_libs) = result?;
+            if !external_libs.is_empty() {
+                bail!(
+                    "{} links {}, which would need to be copied into the wheel for {policy} \
+                    compliance. Use `--auditwheel repair` to copy them or \
+                    `--auditwheel-exclude` if the system provides them",
+                    artifact.display(),
+                    external_libs
+                        .iter()
+                        .map(|lib| lib.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+            Ok((policy, external_libs))
+        }
+        AuditWheelMode::Warn => match result {
+            Ok((policy, external_libs)) if external_libs.is_empty() => Ok((policy, external_libs)),
+            Ok((policy, external_libs)) => {
+                eprintln!(
+                    "⚠️  Warning: {} links {}, which isn't allowed by {policy}, \
+                    using the linux tag instead",
+                    artifact.display(),
+                    external_libs
+                        .iter()
+                        .map(|lib| lib.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                Ok((Policy::default(), Vec::new()))
+            }
+            Err(err) => {
+                eprintln!("⚠️  Warning: {err:#}, using the linux tag instead");
+                Ok((Policy::default(), Vec::new()))
+            }
+        },
+    }
+}
+
+/// The platform tags of a wheel, which are the requested ones unless auditwheel fell back
+/// to `linux` with `--auditwheel warn`
+fn wheel_platform_tags(
+    mode: AuditWheelMode,
+    is_linux: bool,
+    requested: &[PlatformTag],
+    policy: &Policy,
+) -> Vec<PlatformTag> {
+    let warned = mode == AuditWheelMode::Warn && is_linux && policy.name == "linux";
+    if requested.is_empty() || warned {
+        vec![policy.platform_tag()]
+    } else {
+        requested.to_vec()
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::macosx_deployment_target;
+    use super::{
+        apply_auditwheel_mode, macosx_deployment_target, wheel_platform_tags, AuditWheelMode,
+        Library, PlatformTag, Policy,
+    };
+    use anyhow::anyhow;
     use pretty_assertions::assert_eq;
+    use std::path::{Path, PathBuf};
 
     #[test]
     fn test_macosx_deployment_target() {
@@ -1303,4 +1731,95 @@ mod test {
             ((11, 0), (11, 0))
         );
     }
+
+    fn library(name: &str) -> Library {
+        Library {
+            name: name.to_string(),
+            path: PathBuf::from(format!("/usr/lib/{name}")),
+            realpath: Some(PathBuf::from(format!("/usr/lib/{name}"))),
+            needed: Vec::new(),
+            rpath: Vec::new(),
+            runpath: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_auditwheel_modes() {
+        let artifact = Path::new("target/release/libfoo.so");
+        let manylinux = Policy::from_name("manylinux_2_17").unwrap();
+        let compliant = || Ok((manylinux.clone(), Vec::new()));
+        let needs_repair = || Ok((manylinux.clone(), vec![library("libz.so.1")]));
+        let too_new = || Err(anyhow!("too-recent versioned symbols"));
+
+        for mode in [
+            AuditWheelMode::Repair,
+            AuditWheelMode::Check,
+            AuditWheelMode::Warn,
+        ] {
+            let (policy, libs) = apply_auditwheel_mode(mode, artifact, compliant()).unwrap();
+            assert_eq!((policy.name.as_str(), libs.len()), ("manylinux_2_17", 0));
+        }
+
+        // repair grafts the libraries
+        let (policy, libs) =
+            apply_auditwheel_mode(AuditWheelMode::Repair, artifact, needs_repair()).unwrap();
+        assert_eq!((policy.name.as_str(), libs.len()), ("manylinux_2_17", 1));
+        assert!(apply_auditwheel_mode(AuditWheelMode::Repair, artifact, too_new()).is_err());
+
+        // check fails instead of grafting
+        let err = apply_auditwheel_mode(AuditWheelMode::Check, artifact, needs_repair())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("links libz.so.1"), "{err}");
+        assert!(apply_auditwheel_mode(AuditWheelMode::Check, artifact, too_new()).is_err());
+
+        // warn falls back to linux
+        for result in [needs_repair(), too_new()] {
+            let (policy, libs) =
+                apply_auditwheel_mode(AuditWheelMode::Warn, artifact, result).unwrap();
+            assert_eq!((policy.name.as_str(), libs.len()), ("linux", 0));
+        }
+    }
+
+    #[test]
+    fn test_wheel_platform_tags() {
+        let manylinux = Policy::from_name("manylinux_2_17").unwrap();
+        let linux = Policy::default();
+        let requested = [PlatformTag::manylinux2014()];
+
+        for mode in [
+            AuditWheelMode::Repair,
+            AuditWheelMode::Check,
+            AuditWheelMode::Warn,
+            AuditWheelMode::Skip,
+        ] {
+            // Without a requested tag, the policy decides
+            assert_eq!(
+                wheel_platform_tags(mode, true, &[], &manylinux),
+                vec![PlatformTag::manylinux2014()]
+            );
+            assert_eq!(
+                wheel_platform_tags(mode, true, &requested, &manylinux),
+                requested.to_vec()
+            );
+        }
+        // Only warn replaces the requested tag when it fell back to linux
+        assert_eq!(
+            wheel_platform_tags(AuditWheelMode::Warn, true, &requested, &linux),
+            vec![PlatformTag::Linux]
+        );
+        assert_eq!(
+            wheel_platform_tags(AuditWheelMode::Repair, true, &requested, &linux),
+            requested.to_vec()
+        );
+        assert_eq!(
+            wheel_platform_tags(AuditWheelMode::Skip, true, &[], &linux),
+            vec![PlatformTag::Linux]
+        );
+        // Other platforms don't use the policy
+        assert_eq!(
+            wheel_platform_tags(AuditWheelMode::Warn, false, &requested, &linux),
+            requested.to_vec()
+        );
+    }
 }
@@ -1,20 +1,24 @@
-use crate::auditwheel::PlatformTag;
+use crate::auditwheel::{AuditWheelMode, PlatformTag, Policies};
 use crate::build_context::BridgeModel;
 use crate::compile::{CompileTarget, LIB_CRATE_TYPES};
 use crate::cross_compile::{find_sysconfigdata, parse_sysconfigdata};
+use crate::module_writer::validate_build_tag;
 use crate::project_layout::ProjectResolver;
-use crate::pyproject_toml::ToolMaturin;
+use crate::pyproject_toml::{ThirdPartyLicenses, ToolMaturin};
 use crate::python_interpreter::{InterpreterConfig, InterpreterKind, MINIMUM_PYTHON_MINOR};
+use crate::sbom::SbomFormat;
 use crate::{BuildContext, PythonInterpreter, Target};
 use anyhow::{bail, format_err, Context, Result};
 use cargo_metadata::{Metadata, Node};
 use cargo_options::heading;
+use glob::Pattern;
+use once_cell::sync::OnceCell;
 use pep440_rs::VersionSpecifiers;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::env;
 use std::ops::{Deref, DerefMut};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tracing::{debug, instrument};
 
 // This is used for BridgeModel::Bindings("pyo3-ffi") and BridgeModel::Bindings("pyo3").
@@ -164,7 +168,7 @@ pub struct BuildOptions {
         num_args = 0..,
         action = clap::ArgAction::Append
     )]
-    pub platform_tag: Vec<PlatformTag>,
+    pub platform_tag: Vec<String>,
 
     /// The python versions to build wheels for, given as the executables of
     /// interpreters such as `python3.9` or `/usr/bin/python3.8`.
@@ -175,6 +179,14 @@ pub struct BuildOptions {
     #[arg(short = 'f', long, conflicts_with = "interpreter")]
     pub find_interpreter: bool,
 
+    /// The number of interpreters to compile for at the same time, each in a separate target
+    /// directory `<target dir>/interpreters/<interpreter>`
+    ///
+    /// Without it, the interpreters are compiled for one after another, which rebuilds pyo3 for
+    /// each of them. This is independent of `--jobs`, which is passed to each cargo build.
+    #[arg(long, value_name = "N")]
+    pub interpreter_jobs: Option<usize>,
+
     /// Which kind of bindings to use.
     #[arg(short, long, value_parser = ["pyo3", "pyo3-ffi", "rust-cpython", "cffi", "uniffi", "bin"])]
     pub bindings: Option<String>,
@@ -184,10 +196,56 @@ pub struct BuildOptions {
     #[arg(short, long)]
     pub out: Option<PathBuf>,
 
-    /// Don't check for manylinux compliance
-    #[arg(long = "skip-auditwheel")]
+    /// Don't check for manylinux compliance, same as `--auditwheel skip`
+    #[arg(long = "skip-auditwheel", conflicts_with = "auditwheel")]
     pub skip_auditwheel: bool,
 
+    /// What to do when the wheel isn't manylinux or musllinux compliant
+    ///
+    /// Defaults to `[tool.maturin.target.<triple>.auditwheel]`, then to
+    /// `[tool.maturin.auditwheel]` and then to `repair`.
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub auditwheel: Option<AuditWheelMode>,
+
+    /// Don't copy shared libraries whose soname matches the glob pattern into the wheel, e.g.
+    /// `libcuda.so.*`, because the system provides them
+    ///
+    /// Libraries that are only needed by the excluded ones are left out, too.
+    #[arg(long, value_name = "SONAME_GLOB")]
+    pub auditwheel_exclude: Vec<String>,
+
+    /// Make the wheels and the source distribution byte-for-byte reproducible
+    ///
+    /// Sorts the archive entries and normalizes their permissions, owners and timestamps.
+    /// The timestamps are taken from `SOURCE_DATE_EPOCH` if set, otherwise 1980-01-01 is used.
+    #[arg(long)]
+    pub reproducible: bool,
+
+    /// Embed a software bill of materials (SBOM) of the compiled crates and the grafted shared
+    /// libraries in `.dist-info/sboms/`
+    #[arg(long, value_enum)]
+    pub sbom: Option<SbomFormat>,
+
+    /// Bundle the license files of the third-party crates compiled into the wheel in
+    /// `.dist-info/licenses/`, configured in `[tool.maturin.third-party-licenses]`
+    #[arg(long)]
+    pub third_party_licenses: bool,
+
+    /// Build number for the wheels, e.g. `1` for `my_project-1.0-1-cp38-abi3-linux_x86_64.whl`,
+    /// which must start with a digit
+    #[arg(long, value_name = "BUILD")]
+    pub build_tag: Option<String>,
+
+    /// Local version label appended to the version of the wheels and the source distribution,
+    /// e.g. `cpu` for `1.0+cpu`
+    #[arg(long, value_name = "LABEL")]
+    pub local_version: Option<String>,
+
+    /// The output format for the built artifacts. `json` prints a report with the path, hash,
+    /// tags and grafted libraries of each artifact to stdout
+    #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+    pub message_format: MessageFormat,
+
     /// For manylinux targets, use zig to ensure compliance for the chosen manylinux version
     ///
     /// Default to manylinux2014/manylinux_2_17 if you do not specify an `--compatibility`
@@ -202,6 +260,17 @@ pub struct BuildOptions {
     pub cargo: CargoOptions,
 }
 
+/// How the results of a build are reported
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageFormat {
+    /// Human readable messages on stderr
+    #[default]
+    Human,
+    /// A JSON build report on stdout, in addition to the messages on stderr
+    Json,
+}
+
 impl Deref for BuildOptions {
     type Target = CargoOptions;
 
@@ -495,7 +564,7 @@ impl BuildOptions {
             pyproject_toml_path,
             pyproject_toml,
             module_name,
-            metadata23,
+            mut metadata23,
             mut cargo_options,
             cargo_metadata,
             mut pyproject_toml_maturin_options,
@@ -596,9 +665,63 @@ impl BuildOptions {
             }
         }
 
+        let mut policies = Policies::default();
+        if let Some(policy_file) = pyproject.and_then(|x| x.policy_file()) {
+            pyproject_toml_maturin_options.push("policy-file");
+            let pyproject_dir = pyproject_toml_path.parent().unwrap_or(Path::new(""));
+            policies.load(&pyproject_dir.join(policy_file))?;
+        }
+        // The environment variable takes precedence over pyproject.toml
+        policies.load_from_env()?;
+
         let strip = pyproject.map(|x| x.strip()).unwrap_or_default() || strip;
-        let skip_auditwheel =
-            pyproject.map(|x| x.skip_auditwheel()).unwrap_or_default() || self.skip_auditwheel;
+        let target_config = pyproject.and_then(|x| x.target_config(target.target_triple()));
+        let auditwheel = self
+            .skip_auditwheel
+            .then_some(AuditWheelMode::Skip)
+            .or(self.auditwheel)
+            .or_else(|| target_config.and_then(|config| config.auditwheel))
+            .or_else(|| pyproject.and_then(|x| x.auditwheel()))
+            .unwrap_or_default();
+        let auditwheel_exclude = if !self.auditwheel_exclude.is_empty() {
+            self.auditwheel_exclude.as_slice()
+        } else if let Some(exclude) =
+            target_config.and_then(|config| config.auditwheel_exclude.as_deref())
+        {
+            exclude
+        } else {
+            pyproject
+                .and_then(|x| x.auditwheel_exclude())
+                .unwrap_or_default()
+        };
+        let auditwheel_exclude = auditwheel_exclude
+            .iter()
+            .map(|pattern| {
+                Pattern::new(pattern)
+                    .with_context(|| format!("Invalid auditwheel exclude pattern `{pattern}`"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let reproducible =
+            pyproject.map(|x| x.reproducible()).unwrap_or_default() || self.reproducible;
+        let sbom = self.sbom.or_else(|| pyproject.and_then(|x| x.sbom()));
+        let build_tag = self
+            .build_tag
+            .clone()
+            .or_else(|| pyproject.and_then(|x| x.build_tag().map(ToString::to_string)));
+        if let Some(build_tag) = &build_tag {
+            validate_build_tag(build_tag)?;
+        }
+        let local_version = self
+            .local_version
+            .clone()
+            .or_else(|| pyproject.and_then(|x| x.local_version().map(ToString::to_string)));
+        if let Some(local_version) = &local_version {
+            metadata23.add_local_version(local_version)?;
+        }
+        let third_party_licenses = pyproject
+            .and_then(|x| x.third_party_licenses())
+            .cloned()
+            .or_else(|| self.third_party_licenses.then(ThirdPartyLicenses::default));
         let platform_tags = if self.platform_tag.is_empty() {
             #[cfg(feature = "zig")]
             let use_zig = self.zig;
@@ -611,6 +734,12 @@ impl BuildOptions {
                     }
                     x.compatibility()
                 })
+                .map(|tag| {
+                    policies
+                        .parse_platform_tag(tag)
+                        .context("Invalid `compatibility` in pyproject.toml")
+                })
+                .transpose()?
                 .or(if use_zig {
                     if target.is_musl_libc() {
                         // Zig bundles musl 1.2
@@ -634,7 +763,11 @@ impl BuildOptions {
                 Vec::new()
             }
         } else {
+            // Tags are only parsed now because they may be defined by the policy files
             self.platform_tag
+                .iter()
+                .map(|tag| policies.parse_platform_tag(tag))
+                .collect::<Result<Vec<_>>>()?
         };
 
         for platform_tag in &platform_tags {
@@ -687,11 +820,21 @@ impl BuildOptions {
             out: wheel_dir,
             release,
             strip,
-            skip_auditwheel,
+            auditwheel,
+            auditwheel_exclude,
+            policies,
+            reproducible,
+            sbom,
+            compiled_crates: OnceCell::new(),
+            third_party_licenses,
+            third_party_license_files: OnceCell::new(),
+            build_tag,
+            local_version,
             #[cfg(feature = "zig")]
             zig: self.zig,
             platform_tag: platform_tags,
             interpreter,
+            interpreter_jobs: self.interpreter_jobs.unwrap_or(1).max(1),
             cargo_metadata,
             universal2,
             editable,
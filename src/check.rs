@@ -0,0 +1,353 @@
+//! Verifies built wheels against PEP 427 and the core metadata specification
+use crate::inspect::InspectedDistribution;
+use crate::module_writer::{expand_tag, WheelFilename};
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use fs_err::File;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::collections::{BTreeSet, HashMap};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// https://packaging.python.org/en/latest/specifications/core-metadata/#name
+static NAME_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^([A-Z0-9]|[A-Z0-9][A-Z0-9._-]*[A-Z0-9])$").unwrap());
+
+/// Re-opens a wheel and returns a list of all violations of the wheel and core metadata
+/// specifications, or an empty list if the wheel is valid
+///
+/// The following is checked:
+///  * Every RECORD entry has a matching sha256 hash and size
+///  * No file is missing from or extra to RECORD
+///  * The tags in the filename match the `Tag:` lines in WHEEL
+///  * The name and version in the filename and the `.dist-info` directory match METADATA
+///  * METADATA parses and has the required fields
+pub fn wheel_problems(wheel_path: impl AsRef<Path>) -> Result<Vec<String>> {
+    let wheel_path = wheel_path.as_ref();
+    let filename = wheel_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .with_context(|| format!("Invalid file name {}", wheel_path.display()))?;
+    let mut problems = Vec::new();
+
+    let wheel_filename = match WheelFilename::parse(filename) {
+        Ok(wheel_filename) => Some(wheel_filename),
+        Err(err) => {
+            problems.push(err.to_string());
+            None
+        }
+    };
+
+    // The other checks need METADATA, WHEEL and RECORD, so a wheel where they are missing or
+    // don't parse only gets this problem
+    let distribution = match InspectedDistribution::open(wheel_path) {
+        Ok(distribution) => distribution,
+        Err(err) => {
+            problems.push(format!("{err:#}"));
+            return Ok(problems);
+        }
+    };
+    let metadata = &distribution.metadata;
+    let dist_info_dir = metadata.get_dist_info_dir();
+    let dist_info_dir = dist_info_dir.to_str().unwrap();
+
+    if !NAME_RE.is_match(&metadata.name) {
+        problems.push(format!(
+            "METADATA: Name {:?} is not a valid distribution name",
+            metadata.name
+        ));
+    }
+    if !distribution
+        .files
+        .iter()
+        .any(|file| file.path == format!("{dist_info_dir}/METADATA"))
+    {
+        problems.push(format!(
+            "The .dist-info directory doesn't match the name and version in METADATA, expected {dist_info_dir}"
+        ));
+    }
+
+    if let Some(wheel_filename) = &wheel_filename {
+        if wheel_filename.distribution != metadata.get_distribution_escaped() {
+            problems.push(format!(
+                "Filename distribution {} doesn't match METADATA Name {}",
+                wheel_filename.distribution, metadata.name
+            ));
+        }
+        if wheel_filename.version != metadata.get_version_escaped() {
+            problems.push(format!(
+                "Filename version {} doesn't match METADATA Version {}",
+                wheel_filename.version, metadata.version
+            ));
+        }
+        let wheel = distribution.wheel.as_ref().unwrap();
+        if wheel_filename.build_tag != wheel.build {
+            problems.push(format!(
+                "Filename build tag {:?} doesn't match WHEEL Build {:?}",
+                wheel_filename.build_tag, wheel.build
+            ));
+        }
+        let filename_tags: BTreeSet<String> = wheel_filename.expanded_tags().into_iter().collect();
+        let wheel_tags: BTreeSet<String> =
+            wheel.tags.iter().flat_map(|tag| expand_tag(tag)).collect();
+        for tag in filename_tags.difference(&wheel_tags) {
+            problems.push(format!("Filename tag {tag} is missing from WHEEL"));
+        }
+        for tag in wheel_tags.difference(&filename_tags) {
+            problems.push(format!("WHEEL tag {tag} is missing from the filename"));
+        }
+    }
+
+    let record_path = format!("{dist_info_dir}/RECORD");
+    let mut recorded: HashMap<&str, (Option<&str>, Option<u64>)> = HashMap::new();
+    for entry in &distribution.record {
+        if recorded
+            .insert(&entry.path, (entry.hash.as_deref(), entry.size))
+            .is_some()
+        {
+            problems.push(format!("RECORD lists {} more than once", entry.path));
+        }
+    }
+
+    let mut archive = ZipArchive::new(File::open(wheel_path)?)?;
+    for idx in 0..archive.len() {
+        let mut file = archive.by_index(idx)?;
+        let name = file.name().to_string();
+        if file.is_dir() {
+            continue;
+        }
+        // RECORD can't contain its own hash, and neither can its signatures
+        let is_unhashed = name == record_path
+            || name == format!("{record_path}.jws")
+            || name == format!("{record_path}.p7s");
+        let Some((hash, size)) = recorded.remove(name.as_str()) else {
+            problems.push(format!("{name} is missing from RECORD"));
+            continue;
+        };
+        if is_unhashed {
+            continue;
+        }
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+        match size {
+            Some(size) if size == contents.len() as u64 => {}
+            Some(size) => problems.push(format!(
+                "{name} has a size of {} bytes, but RECORD says {size}",
+                contents.len()
+            )),
+            None => problems.push(format!("{name} has no size in RECORD")),
+        }
+        match hash.map(|hash| hash.split_once('=')) {
+            Some(Some((algorithm, expected))) => {
+                let actual = match algorithm {
+                    "sha256" => URL_SAFE_NO_PAD.encode(Sha256::digest(&contents)),
+                    "sha384" => URL_SAFE_NO_PAD.encode(Sha384::digest(&contents)),
+                    "sha512" => URL_SAFE_NO_PAD.encode(Sha512::digest(&contents)),
+                    _ => {
+                        problems.push(format!(
+                            "{name} uses the unsupported hash algorithm {algorithm} in RECORD"
+                        ));
+                        continue;
+                    }
+                };
+                if actual != expected {
+                    problems.push(format!(
+                        "{name} has the {algorithm} hash {actual}, but RECORD says {expected}"
+                    ));
+                }
+            }
+            Some(None) => problems.push(format!("{name} has an invalid hash in RECORD")),
+            None => problems.push(format!("{name} has no hash in RECORD")),
+        }
+    }
+    let mut extra: Vec<_> = recorded.into_keys().collect();
+    extra.sort_unstable();
+    for name in extra {
+        problems.push(format!(
+            "{name} is listed in RECORD but missing from the wheel"
+        ));
+    }
+
+    Ok(problems)
+}
+
+/// Fails with a list of all problems if the wheel isn't valid, see [wheel_problems]
+pub fn check_wheel(wheel_path: impl AsRef<Path>) -> Result<()> {
+    let wheel_path = wheel_path.as_ref();
+    let problems = wheel_problems(wheel_path)
+        .with_context(|| format!("Failed to check {}", wheel_path.display()))?;
+    if !problems.is_empty() {
+        bail!(
+            "{} is not a valid wheel:\n  - {}",
+            wheel_path.display(),
+            problems.join("\n  - ")
+        );
+    }
+    Ok(())
+}
+
+/// Implementation of `maturin check`: checks all given wheels and reports every invalid one
+pub fn check_wheels(files: &[PathBuf]) -> Result<()> {
+    let mut failed = 0;
+    for file in files {
+        match check_wheel(file) {
+            Ok(()) => eprintln!("✅ {}", file.display()),
+            Err(err) => {
+                failed += 1;
+                eprintln!("❌ {err:#}");
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("{failed} of {} wheels failed the check", files.len());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metadata23, ModuleWriter, WheelWriter};
+    use ignore::overrides::Override;
+    use pep440_rs::Version;
+    use std::io::Write;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    fn build_wheel(dir: &Path) -> Result<PathBuf> {
+        let metadata = Metadata23::new("check-me".to_string(), Version::new([0, 1]));
+        let tag = "cp38-abi3-manylinux_2_17_x86_64.manylinux2014_x86_64";
+        let mut writer = WheelWriter::new(
+            tag,
+            dir,
+            &metadata,
+            None,
+            &[tag.to_string()],
+            Override::empty(),
+            false,
+        )?;
+        writer.add_bytes("check_me/__init__.py", b"print('hello')\n")?;
+        Ok(writer.finish()?)
+    }
+
+    /// Copies a wheel, applying `modify` to every file's name and content
+    fn rewrite_wheel(
+        source: &Path,
+        target: &Path,
+        modify: impl Fn(&str, Vec<u8>) -> Option<(String, Vec<u8>)>,
+    ) -> Result<()> {
+        let mut archive = ZipArchive::new(File::open(source)?)?;
+        let mut writer = ZipWriter::new(File::create(target)?);
+        for idx in 0..archive.len() {
+            let mut file = archive.by_index(idx)?;
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents)?;
+            if let Some((name, contents)) = modify(file.name(), contents) {
+                writer.start_file(name, FileOptions::default())?;
+                writer.write_all(&contents)?;
+            }
+        }
+        writer.finish()?;
+        Ok(())
+    }
+
+    #[test]
+    fn valid_wheel() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let wheel = build_wheel(tmp_dir.path())?;
+        assert_eq!(wheel_problems(&wheel)?, Vec::<String>::new());
+        check_wheel(&wheel)
+    }
+
+    #[test]
+    fn modified_file() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let wheel = build_wheel(tmp_dir.path())?;
+        let modified = tmp_dir
+            .path()
+            .join("modified")
+            .join(wheel.file_name().unwrap());
+        fs_err::create_dir(modified.parent().unwrap())?;
+        rewrite_wheel(&wheel, &modified, |name, contents| {
+            if name == "check_me/__init__.py" {
+                Some((name.to_string(), b"print('tampered')\n".to_vec()))
+            } else {
+                Some((name.to_string(), contents))
+            }
+        })?;
+        let problems = wheel_problems(&modified)?;
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert!(problems[0].starts_with("check_me/__init__.py has a size of 18 bytes"));
+        assert!(problems[1].starts_with("check_me/__init__.py has the sha256 hash"));
+        Ok(())
+    }
+
+    #[test]
+    fn unrecorded_and_missing_files() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let wheel = build_wheel(tmp_dir.path())?;
+        let modified = tmp_dir
+            .path()
+            .join("modified")
+            .join(wheel.file_name().unwrap());
+        fs_err::create_dir(modified.parent().unwrap())?;
+        rewrite_wheel(&wheel, &modified, |name, contents| {
+            if name == "check_me/__init__.py" {
+                Some(("check_me/other.py".to_string(), contents))
+            } else {
+                Some((name.to_string(), contents))
+            }
+        })?;
+        assert_eq!(
+            wheel_problems(&modified)?,
+            [
+                "check_me/other.py is missing from RECORD",
+                "check_me/__init__.py is listed in RECORD but missing from the wheel",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn mismatched_tags() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let wheel = build_wheel(tmp_dir.path())?;
+        let renamed = tmp_dir
+            .path()
+            .join("check_me-0.1-cp38-abi3-manylinux_2_28_x86_64.whl");
+        fs_err::copy(&wheel, &renamed)?;
+        assert_eq!(
+            wheel_problems(&renamed)?,
+            [
+                "Filename tag cp38-abi3-manylinux_2_28_x86_64 is missing from WHEEL",
+                "WHEEL tag cp38-abi3-manylinux2014_x86_64 is missing from the filename",
+                "WHEEL tag cp38-abi3-manylinux_2_17_x86_64 is missing from the filename",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn missing_wheel_file() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let wheel = build_wheel(tmp_dir.path())?;
+        let modified = tmp_dir
+            .path()
+            .join("modified")
+            .join(wheel.file_name().unwrap());
+        fs_err::create_dir(modified.parent().unwrap())?;
+        rewrite_wheel(&wheel, &modified, |name, contents| {
+            (name != "check_me-0.1.dist-info/WHEEL").then(|| (name.to_string(), contents))
+        })?;
+        assert_eq!(
+            wheel_problems(&modified)?,
+            ["Missing check_me-0.1.dist-info/WHEEL: specified file not found in archive"]
+        );
+        assert!(check_wheel(&modified).is_err());
+        Ok(())
+    }
+}
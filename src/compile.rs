@@ -9,10 +9,12 @@ use fs_err::{self as fs, File};
 use normpath::PathExt;
 use std::collections::HashMap;
 use std::env;
+use std::fmt::Write as _;
 use std::io::{BufReader, Read};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::str;
+use std::thread;
 use tracing::{debug, instrument, trace};
 
 /// The first version of pyo3 that supports building Windows abi3 wheel
@@ -48,11 +50,32 @@ pub fn compile(
     context: &BuildContext,
     python_interpreter: Option<&PythonInterpreter>,
     targets: &[CompileTarget],
+) -> Result<Vec<HashMap<String, BuildArtifact>>> {
+    compile_with_output(context, python_interpreter, targets, None)
+}
+
+/// Like [compile], but collects the output of cargo instead of printing it, so that concurrent
+/// builds don't interleave their output. The output is returned even if the build failed.
+pub(crate) fn compile_captured(
+    context: &BuildContext,
+    python_interpreter: Option<&PythonInterpreter>,
+    targets: &[CompileTarget],
+) -> (Result<Vec<HashMap<String, BuildArtifact>>>, String) {
+    let mut output = String::new();
+    let result = compile_with_output(context, python_interpreter, targets, Some(&mut output));
+    (result, output)
+}
+
+fn compile_with_output(
+    context: &BuildContext,
+    python_interpreter: Option<&PythonInterpreter>,
+    targets: &[CompileTarget],
+    output: Option<&mut String>,
 ) -> Result<Vec<HashMap<String, BuildArtifact>>> {
     if context.universal2 {
-        compile_universal2(context, python_interpreter, targets)
+        compile_universal2(context, python_interpreter, targets, output)
     } else {
-        compile_targets(context, python_interpreter, targets)
+        compile_targets(context, python_interpreter, targets, output)
     }
 }
 
@@ -61,16 +84,22 @@ fn compile_universal2(
     context: &BuildContext,
     python_interpreter: Option<&PythonInterpreter>,
     targets: &[CompileTarget],
+    mut output: Option<&mut String>,
 ) -> Result<Vec<HashMap<String, BuildArtifact>>> {
     let mut aarch64_context = context.clone();
     aarch64_context.target = Target::from_target_triple(Some("aarch64-apple-darwin".to_string()))?;
 
-    let aarch64_artifacts = compile_targets(&aarch64_context, python_interpreter, targets)
-        .context("Failed to build a aarch64 library through cargo")?;
+    let aarch64_artifacts = compile_targets(
+        &aarch64_context,
+        python_interpreter,
+        targets,
+        output.as_deref_mut(),
+    )
+    .context("Failed to build a aarch64 library through cargo")?;
     let mut x86_64_context = context.clone();
     x86_64_context.target = Target::from_target_triple(Some("x86_64-apple-darwin".to_string()))?;
 
-    let x86_64_artifacts = compile_targets(&x86_64_context, python_interpreter, targets)
+    let x86_64_artifacts = compile_targets(&x86_64_context, python_interpreter, targets, output)
         .context("Failed to build a x86_64 library through cargo")?;
 
     let mut universal_artifacts = Vec::with_capacity(targets.len());
@@ -138,11 +167,16 @@ fn compile_targets(
     context: &BuildContext,
     python_interpreter: Option<&PythonInterpreter>,
     targets: &[CompileTarget],
+    mut output: Option<&mut String>,
 ) -> Result<Vec<HashMap<String, BuildArtifact>>> {
     let mut artifacts = Vec::with_capacity(targets.len());
     for target in targets {
         let build_command = cargo_build_command(context, python_interpreter, target)?;
-        artifacts.push(compile_target(context, build_command)?);
+        artifacts.push(compile_target(
+            context,
+            build_command,
+            output.as_deref_mut(),
+        )?);
     }
     Ok(artifacts)
 }
@@ -460,8 +494,12 @@ fn cargo_build_command(
 fn compile_target(
     context: &BuildContext,
     mut build_command: Command,
+    output: Option<&mut String>,
 ) -> Result<HashMap<String, BuildArtifact>> {
     debug!("Running {:?}", build_command);
+    if output.is_some() {
+        build_command.stderr(Stdio::piped());
+    }
 
     let using_cross = build_command
         .get_program()
@@ -471,6 +509,16 @@ fn compile_target(
         .spawn()
         .context("Failed to run `cargo rustc`")?;
 
+    // Read stderr concurrently, so that neither of the pipes fills up and blocks cargo
+    let stderr_reader = cargo_build.stderr.take().map(|mut stderr| {
+        thread::spawn(move || {
+            let mut contents = String::new();
+            let _ = stderr.read_to_string(&mut contents);
+            contents
+        })
+    });
+    let mut messages = String::new();
+
     let mut artifacts = HashMap::new();
     let mut linked_paths = Vec::new();
 
@@ -544,7 +592,11 @@ fn compile_target(
                 }
             }
             cargo_metadata::Message::CompilerMessage(msg) => {
-                println!("{}", msg.message);
+                if output.is_some() {
+                    writeln!(messages, "{}", msg.message)?;
+                } else {
+                    println!("{}", msg.message);
+                }
             }
             _ => (),
         }
@@ -558,6 +610,12 @@ fn compile_target(
     let status = cargo_build
         .wait()
         .expect("Failed to wait on cargo child process");
+    if let Some(output) = output {
+        if let Some(stderr_reader) = stderr_reader {
+            output.push_str(&stderr_reader.join().unwrap_or_default());
+        }
+        output.push_str(&messages);
+    }
 
     if !status.success() {
         bail!(
@@ -0,0 +1,309 @@
+//! Support for `maturin develop` in conda environments: the installed package gets a record in
+//! `conda-meta` so that conda knows about it, and the dependencies are checked instead of being
+//! installed with pip behind the back of conda's solver
+use crate::install::{normalize_name, InstallScheme, InstalledDistribution};
+use crate::lockfile::marker_environment;
+use anyhow::{bail, Context, Result};
+use fs_err as fs;
+use pep440_rs::Version;
+use pep508_rs::{MarkerEnvironment, Requirement, VersionOrUrl};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use tracing::debug;
+
+/// The build string conda uses for packages that were installed from wheels
+const PYPI_BUILD: &str = "pypi_0";
+
+/// Prints the names and versions of the installed distributions as JSON
+const INSTALLED_VERSIONS_SCRIPT: &str = r#"
+import importlib.metadata, json
+
+print(json.dumps({
+    dist.metadata["Name"]: dist.version
+    for dist in importlib.metadata.distributions()
+    if dist.metadata["Name"]
+}))
+"#;
+
+/// The subset of a conda `PrefixRecord` that `conda list` and `conda remove` need
+#[derive(Debug, Serialize)]
+struct PrefixRecord {
+    name: String,
+    version: String,
+    build: &'static str,
+    build_number: u32,
+    channel: &'static str,
+    subdir: &'static str,
+    #[serde(rename = "fn")]
+    file_name: String,
+    depends: Vec<String>,
+    files: Vec<String>,
+    paths_data: PathsData,
+    installer: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct PathsData {
+    paths: Vec<PathData>,
+    paths_version: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct PathData {
+    #[serde(rename = "_path")]
+    path: String,
+    path_type: &'static str,
+    sha256: String,
+    size_in_bytes: u64,
+}
+
+/// Whether the environment is a conda environment, which conda marks with `conda-meta`
+pub fn is_conda_env(prefix: &Path) -> bool {
+    prefix.join("conda-meta").is_dir()
+}
+
+/// Writes the `conda-meta/<name>-<version>-pypi_0.json` record for a distribution installed
+/// from the wheel `wheel_name`, replacing the records of other versions, and returns its path
+pub fn write_conda_record(
+    scheme: &InstallScheme,
+    distribution: &InstalledDistribution,
+    wheel_name: &str,
+) -> Result<PathBuf> {
+    let name = normalize_name(&distribution.name);
+    remove_conda_records(&scheme.root, &name)?;
+
+    let mut files = Vec::new();
+    let mut paths = Vec::new();
+    for file in distribution.existing_files(scheme) {
+        let Ok(relative) = file.strip_prefix(&scheme.root) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let contents = fs::read(file)?;
+        paths.push(PathData {
+            path: relative.clone(),
+            path_type: "hardlink",
+            sha256: format!("{:x}", Sha256::digest(&contents)),
+            size_in_bytes: contents.len() as u64,
+        });
+        files.push(relative);
+    }
+    let record = PrefixRecord {
+        name: name.clone(),
+        version: distribution.version.clone(),
+        build: PYPI_BUILD,
+        build_number: 0,
+        channel: "pypi",
+        subdir: "pypi",
+        file_name: wheel_name.to_string(),
+        depends: Vec::new(),
+        files,
+        paths_data: PathsData {
+            paths,
+            paths_version: 1,
+        },
+        installer: "maturin",
+    };
+    let path = scheme
+        .root
+        .join("conda-meta")
+        .join(format!("{name}-{}-{PYPI_BUILD}.json", distribution.version));
+    debug!("Writing conda record {}", path.display());
+    fs::write(&path, serde_json::to_string_pretty(&record)?)?;
+    Ok(path)
+}
+
+/// The `conda-meta` records of the wheels of a distribution that are installed in the prefix
+pub fn conda_records(prefix: &Path, name: &str) -> Result<Vec<PathBuf>> {
+    let conda_meta = prefix.join("conda-meta");
+    if !conda_meta.is_dir() {
+        return Ok(Vec::new());
+    }
+    let name = normalize_name(name);
+    let mut records = Vec::new();
+    for entry in fs::read_dir(&conda_meta)? {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let Some(stem) = file_name.strip_suffix(&format!("-{PYPI_BUILD}.json")) else {
+            continue;
+        };
+        // The version can't contain a dash, but the name can
+        let Some((record_name, _version)) = stem.rsplit_once('-') else {
+            continue;
+        };
+        if normalize_name(record_name) == name {
+            records.push(path);
+        }
+    }
+    records.sort();
+    Ok(records)
+}
+
+/// Removes the `conda-meta` records of the wheels of a distribution, returning the removed files
+pub fn remove_conda_records(prefix: &Path, name: &str) -> Result<Vec<PathBuf>> {
+    let records = conda_records(prefix, name)?;
+    for record in &records {
+        fs::remove_file(record)?;
+    }
+    Ok(records)
+}
+
+/// The requirements that aren't satisfied by the distributions installed in the environment
+/// of the interpreter, skipping those whose markers don't match
+pub fn missing_requirements(python: &Path, requirements: &[String]) -> Result<Vec<String>> {
+    if requirements.is_empty() {
+        return Ok(Vec::new());
+    }
+    let env = marker_environment(python)?;
+    let output = Command::new(python)
+        .args(["-c", INSTALLED_VERSIONS_SCRIPT])
+        .output()
+        .with_context(|| format!("Failed to run {}", python.display()))?;
+    if !output.status.success() {
+        bail!(
+            "Failed to list the installed packages of {}: {}",
+            python.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let installed: BTreeMap<String, String> =
+        serde_json::from_slice(&output.stdout).context("Failed to parse the installed packages")?;
+    let installed = installed
+        .into_iter()
+        .map(|(name, version)| (normalize_name(&name), version))
+        .collect();
+    Ok(unsatisfied(requirements, &env, &installed))
+}
+
+/// Checks the requirements against the installed versions, which are keyed by normalized name
+fn unsatisfied(
+    requirements: &[String],
+    env: &MarkerEnvironment,
+    installed: &BTreeMap<String, String>,
+) -> Vec<String> {
+    requirements
+        .iter()
+        .filter(|requirement| {
+            let Ok(parsed) = Requirement::from_str(requirement) else {
+                // Let the user figure out what to do with it
+                return true;
+            };
+            if !parsed.evaluate_markers(env, &[]) {
+                return false;
+            }
+            let Some(version) = installed.get(&normalize_name(parsed.name.as_ref())) else {
+                return true;
+            };
+            match &parsed.version_or_url {
+                Some(VersionOrUrl::VersionSpecifier(specifiers)) => {
+                    !Version::from_str(version).is_ok_and(|version| specifiers.contains(&version))
+                }
+                Some(VersionOrUrl::Url(_)) | None => false,
+            }
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn checks_installed_versions() {
+        let env: MarkerEnvironment = serde_json::from_value(serde_json::json!({
+            "implementation_name": "cpython",
+            "implementation_version": "3.12.1",
+            "os_name": "posix",
+            "platform_machine": "x86_64",
+            "platform_python_implementation": "CPython",
+            "platform_release": "",
+            "platform_system": "Linux",
+            "platform_version": "",
+            "python_full_version": "3.12.1",
+            "python_version": "3.12",
+            "sys_platform": "linux",
+        }))
+        .unwrap();
+        let installed = BTreeMap::from([
+            ("numpy".to_string(), "1.26.4".to_string()),
+            ("typing-extensions".to_string(), "4.9.0".to_string()),
+        ]);
+        let requirements = [
+            "numpy>=1.20",
+            "Typing_Extensions>=4.10",
+            "cffi",
+            "tomli; python_version < '3.11'",
+            "pywin32; sys_platform == 'win32'",
+            "numpy @ https://example.com/numpy.whl",
+        ]
+        .map(str::to_string);
+        assert_eq!(
+            unsatisfied(&requirements, &env, &installed),
+            vec!["Typing_Extensions>=4.10", "cffi"]
+        );
+    }
+
+    #[test]
+    fn replaces_records() -> Result<()> {
+        let prefix = TempDir::new()?;
+        let root = dunce::canonicalize(prefix.path())?;
+        let site_packages = root.join("lib/python3.12/site-packages");
+        fs::create_dir_all(site_packages.join("my_project"))?;
+        fs::create_dir_all(root.join("conda-meta"))?;
+        fs::write(site_packages.join("my_project/__init__.py"), "")?;
+        fs::write(root.join("conda-meta/my-project-0.1.0-pypi_0.json"), "{}")?;
+        fs::write(
+            root.join("conda-meta/my-project-extra-1.0-pypi_0.json"),
+            "{}",
+        )?;
+        fs::write(root.join("conda-meta/numpy-1.26.4-py312_0.json"), "{}")?;
+        let scheme = InstallScheme {
+            root: root.clone(),
+            purelib: site_packages.clone(),
+            platlib: site_packages.clone(),
+            scripts: root.join("bin"),
+            data: root.clone(),
+            include: root.join("include"),
+            python: root.join("bin/python"),
+            windows: false,
+        };
+        let distribution = InstalledDistribution {
+            name: "my_project".to_string(),
+            version: "0.2.0".to_string(),
+            dist_info: site_packages.join("my_project-0.2.0.dist-info"),
+            files: vec![
+                site_packages.join("my_project/__init__.py"),
+                PathBuf::from("/outside/of/the/prefix"),
+            ],
+        };
+        assert!(is_conda_env(&root));
+        let record =
+            write_conda_record(&scheme, &distribution, "my_project-0.2.0-py3-none-any.whl")?;
+        assert_eq!(record, root.join("conda-meta/my-project-0.2.0-pypi_0.json"));
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&record)?)?;
+        assert_eq!(json["name"], "my-project");
+        assert_eq!(json["build"], "pypi_0");
+        assert_eq!(
+            json["files"],
+            serde_json::json!(["lib/python3.12/site-packages/my_project/__init__.py"])
+        );
+        assert!(!root
+            .join("conda-meta/my-project-0.1.0-pypi_0.json")
+            .exists());
+        assert!(root
+            .join("conda-meta/my-project-extra-1.0-pypi_0.json")
+            .exists());
+
+        assert_eq!(remove_conda_records(&root, "My.Project")?, vec![record]);
+        assert!(root.join("conda-meta/numpy-1.26.4-py312_0.json").exists());
+        Ok(())
+    }
+}
@@ -1,19 +1,28 @@
-use crate::build_options::CargoOptions;
+use crate::build_context::interpreter_target_dir;
+use crate::build_options::{CargoOptions, MessageFormat};
+use crate::conda::{is_conda_env, missing_requirements, write_conda_record};
+use crate::develop_cache::DevelopCache;
+use crate::install::{
+    extract_packages, install_wheel, set_direct_url, InstallScheme, InstalledDistribution,
+};
+use crate::lockfile::{locked_requirements, marker_environment};
 use crate::target::Arch;
+use crate::watch::{fingerprint, Snapshot, WatchedInputs};
+use crate::BridgeModel;
 use crate::BuildContext;
 use crate::BuildOptions;
+use crate::BuildReport;
 use crate::PlatformTag;
 use crate::PythonInterpreter;
 use crate::Target;
 use anyhow::{anyhow, bail, Context, Result};
 use cargo_options::heading;
 use pep508_rs::{MarkerExpression, MarkerOperator, MarkerTree, MarkerValue};
-use regex::Regex;
-use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::Command;
 use std::str;
+use std::{env, iter};
 use tempfile::TempDir;
 use tracing::{debug, instrument};
 use url::Url;
@@ -26,6 +35,9 @@ enum InstallBackend {
         path: PathBuf,
         args: Vec<&'static str>,
     },
+    /// Only reports the missing dependencies in conda environments, so that they can be installed
+    /// with conda instead of pip
+    Conda,
 }
 
 impl InstallBackend {
@@ -54,6 +66,7 @@ impl InstallBackend {
                 cmd.args(args).arg("pip");
                 cmd
             }
+            InstallBackend::Conda => unreachable!("The conda backend doesn't run an installer"),
         }
     }
 }
@@ -117,12 +130,38 @@ pub struct DevelopOptions {
         action = clap::ArgAction::Append
     )]
     pub extras: Vec<String>,
+    /// Install the requirements of dependency groups from `[dependency-groups]` in
+    /// pyproject.toml (PEP 735)
+    ///
+    /// Use as `--group=test,lint`
+    #[arg(
+        long = "group",
+        value_name = "GROUP",
+        value_delimiter = ',',
+        action = clap::ArgAction::Append
+    )]
+    pub groups: Vec<String>,
+    /// Install exactly the dependencies pinned in a `pylock.toml` (PEP 751) or a `uv.lock`
+    /// instead of resolving them
+    ///
+    /// The selected extras and groups are taken from a `uv.lock`, while all packages of a
+    /// `pylock.toml` that match the environment are installed.
+    #[arg(long, value_name = "PATH")]
+    pub lockfile: Option<PathBuf>,
     /// Skip installation, only build the extension module inplace
     ///
-    /// Only works with mixed Rust/Python project layout
+    /// With a mixed Rust/Python project layout, the extension module is copied next to the python
+    /// sources. Pure Rust projects get an importable package in `--inplace-dir` instead.
     #[arg(long)]
     pub skip_install: bool,
-    /// Use a specific pip installation instead of the default one.
+    /// The directory to write the package of a pure Rust project to with `--skip-install`, which
+    /// can then be put on `PYTHONPATH`. Neither the package nor its dependencies are installed
+    /// into the virtualenv
+    ///
+    /// Defaults to `<target dir>/maturin/inplace`
+    #[arg(long, value_name = "PATH", requires = "skip_install")]
+    pub inplace_dir: Option<PathBuf>,
+    /// Use a specific pip installation instead of the default one to install the dependencies.
     ///
     /// This can be used to supply the path to a pip executable when the
     /// current virtualenv does not provide one.
@@ -131,21 +170,84 @@ pub struct DevelopOptions {
     /// `cargo rustc` options
     #[command(flatten)]
     pub cargo_options: CargoOptions,
-    /// Use `uv` to install packages instead of `pip`
+    /// Rebuild and reinstall whenever the crate, its path dependencies, the python sources or
+    /// pyproject.toml change
+    ///
+    /// Errors are reported without exiting, the next change triggers another build.
+    #[arg(long)]
+    pub watch: bool,
+    /// Shell command to run after each successful install, e.g. `pytest`, with the virtualenv
+    /// activated
+    #[arg(long, value_name = "COMMAND")]
+    pub exec: Option<String>,
+    /// Use `uv` to install the dependencies instead of `pip`
+    ///
+    /// The wheel itself is installed by maturin, so neither pip nor uv are needed for projects
+    /// without dependencies. On Windows, wheels with scripts still need them for the `.exe`
+    /// launchers
     #[arg(long)]
     pub uv: bool,
+    /// Install the dependencies with pip or uv in conda environments, too
+    ///
+    /// By default, maturin only lists the dependencies that are missing in a conda environment,
+    /// so that you can install them with conda without conflicting with its solver
+    #[arg(long)]
+    pub install_deps: bool,
+    /// The output format for the built wheel. `json` prints a report with the hash, the tags
+    /// and the interpreter of the installed wheel to stdout
+    #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+    pub message_format: MessageFormat,
+    /// Install into this virtualenv instead of the activated one, can be repeated to install
+    /// into several virtualenvs at once
+    ///
+    /// The package is built once for each distinct interpreter ABI, using a separate target
+    /// directory for each, or once for all of them with abi3.
+    #[arg(long = "venv", value_name = "PATH")]
+    pub venvs: Vec<PathBuf>,
+    /// Install into all virtualenvs named `.venv` or `.venv-*` in the current directory, e.g.
+    /// `.venv-3.9` and `.venv-3.12`
+    #[arg(long, conflicts_with = "venvs")]
+    pub all_venvs: bool,
 }
 
-#[instrument(skip_all)]
-fn install_dependencies(
+/// The requirements to install before the package itself
+#[derive(Debug)]
+struct Dependencies {
+    requirements: Vec<String>,
+    /// Whether the requirements are a fully pinned set from a lockfile, which is installed
+    /// without resolving their dependencies
+    locked: bool,
+}
+
+/// Collects the dependencies of the package with the selected extras and dependency groups,
+/// or the pinned dependencies from the lockfile
+fn resolve_dependencies(
     build_context: &BuildContext,
     extras: &[String],
+    groups: &[String],
+    lockfile: Option<&Path>,
     interpreter: &PythonInterpreter,
-    install_backend: &InstallBackend,
-) -> Result<()> {
-    if !build_context.metadata23.requires_dist.is_empty() {
-        let mut args = vec!["install".to_string()];
-        args.extend(build_context.metadata23.requires_dist.iter().map(|x| {
+) -> Result<Dependencies> {
+    if let Some(lockfile) = lockfile {
+        let env = marker_environment(&interpreter.executable)?;
+        let requirements = locked_requirements(
+            lockfile,
+            &build_context.metadata23.name,
+            extras,
+            groups,
+            &env,
+        )?;
+        return Ok(Dependencies {
+            requirements,
+            locked: true,
+        });
+    }
+
+    let mut requirements: Vec<String> = build_context
+        .metadata23
+        .requires_dist
+        .iter()
+        .filter_map(|x| {
             let mut pkg = x.clone();
             // Remove extra marker to make it installable with pip
             // Keep in sync with `Metadata21::merge_pyproject_toml()`!
@@ -169,27 +271,75 @@ fn install_dependencies(
                     }
                 });
             }
-            pkg.to_string()
-        }));
-        let status = install_backend
-            .make_command(&interpreter.executable)
-            .args(&args)
-            .status()
-            .context("Failed to run pip install")?;
+            // pip would skip the dependencies of extras that weren't selected anyway, and
+            // skipping them here means that we don't need pip at all when there's nothing left
+            if pkg.marker.as_ref().is_some_and(requires_extra) {
+                return None;
+            }
+            Some(pkg.to_string())
+        })
+        .collect();
+    if !groups.is_empty() {
+        let pyproject_toml = build_context
+            .pyproject_toml
+            .as_ref()
+            .context("Dependency groups require a pyproject.toml")?;
+        for group in groups {
+            requirements.extend(pyproject_toml.dependency_group(group)?);
+        }
+    }
+    Ok(Dependencies {
+        requirements,
+        locked: false,
+    })
+}
+
+#[instrument(skip_all)]
+fn install_dependencies(
+    dependencies: &Dependencies,
+    interpreter: &PythonInterpreter,
+    install_backend: &InstallBackend,
+) -> Result<()> {
+    if let InstallBackend::Conda = install_backend {
+        let missing = missing_requirements(&interpreter.executable, &dependencies.requirements)?;
+        if !missing.is_empty() {
+            eprintln!(
+                "⚠️  Warning: Not installing dependencies into a conda environment, install these \
+                with conda or pass `--install-deps` to install them with pip:"
+            );
+            for requirement in missing {
+                eprintln!("    {requirement}");
+            }
+        }
+        return Ok(());
+    }
+    if !dependencies.requirements.is_empty() {
+        let mut command = install_backend.make_command(&interpreter.executable);
+        command.arg("install");
+        if dependencies.locked {
+            command.arg("--no-deps");
+        }
+        let status = command.args(&dependencies.requirements).status().context(
+            "Failed to run pip install for the dependencies, \
+                install pip into the virtualenv or use `--pip-path` or `--uv`",
+        )?;
         if !status.success() {
-            bail!(r#"pip install finished with "{}""#, status)
+            bail!(
+                r#"pip install for the dependencies finished with "{}""#,
+                status
+            )
         }
     }
     Ok(())
 }
 
+/// Installs the wheel with pip or uv, which maturin needs on Windows for the `.exe` launchers of
+/// the console and gui scripts
 #[instrument(skip_all, fields(wheel_filename = %wheel_filename.display()))]
 fn pip_install_wheel(
-    build_context: &BuildContext,
     python: &Path,
     venv_dir: &Path,
     wheel_filename: &Path,
-    pip_path: Option<PathBuf>,
     install_backend: &InstallBackend,
 ) -> Result<()> {
     let mut cmd = install_backend.make_command(python);
@@ -220,90 +370,347 @@ fn pip_install_wheel(
             String::from_utf8_lossy(&output.stderr).trim(),
         );
     }
-    // pip show --files is not supported by uv, thus
-    // default to using pip backend all the time
-    fix_direct_url(
-        build_context,
-        python,
-        &InstallBackend::Pip { path: pip_path },
-    )?;
     Ok(())
 }
 
+/// Whether a marker can only be true with an extra selected
+fn requires_extra(marker: &MarkerTree) -> bool {
+    match marker {
+        MarkerTree::Expression(MarkerExpression {
+            l_value: MarkerValue::Extra,
+            operator: MarkerOperator::Equal,
+            ..
+        }) => true,
+        MarkerTree::Expression(_) => false,
+        MarkerTree::And(markers) => markers.iter().any(requires_extra),
+        MarkerTree::Or(markers) => markers.iter().all(requires_extra),
+    }
+}
+
 /// Each editable-installed python package has a direct_url.json file that includes a file:// URL
 /// indicating the location of the source code of that project. The maturin import hook uses this
 /// URL to locate and rebuild editable-installed projects.
-///
-/// When a maturin package is installed using `pip install -e`, pip takes care of writing the
-/// correct URL, however when a maturin package is installed with `maturin develop`, the URL is
-/// set to the path to the temporary wheel file created during installation.
-#[instrument(skip_all)]
-fn fix_direct_url(
+fn editable_direct_url(build_context: &BuildContext) -> Result<String> {
+    let project_dir = build_context
+        .pyproject_toml_path
+        .parent()
+        .ok_or_else(|| anyhow!("failed to get project directory"))?;
+    let uri = Url::from_file_path(project_dir)
+        .map_err(|_| anyhow!("failed to convert project directory to file URL"))?;
+    Ok(serde_json::json!({"dir_info": {"editable": true}, "url": uri}).to_string())
+}
+
+/// Builds the wheel once, installs its dependencies and installs it into each of the
+/// virtualenvs, skipping those where the same build is already installed
+#[allow(clippy::too_many_arguments)]
+fn build_and_install(
     build_context: &BuildContext,
-    python: &Path,
-    install_backend: &InstallBackend,
+    venv_dirs: &[PathBuf],
+    target: &Target,
+    extras: &[String],
+    groups: &[String],
+    lockfile: Option<&Path>,
+    skip_install: bool,
+    inplace_dir: &Path,
+    install_backend: impl Fn(&Path, &PythonInterpreter) -> Result<InstallBackend>,
+    message_format: MessageFormat,
 ) -> Result<()> {
-    println!("✏️  Setting installed package as editable");
-    let mut cmd = install_backend.make_command(python);
-    let output = cmd
-        .args(["show", "--files"])
-        .arg(&build_context.metadata23.name)
-        .output()
-        .context(format!(
-            "pip show failed (ran {:?} with {:?})",
-            cmd.get_program(),
-            &cmd.get_args().collect::<Vec<_>>(),
-        ))?;
-    if let Some(direct_url_path) = parse_direct_url_path(&String::from_utf8_lossy(&output.stdout))?
-    {
-        let project_dir = build_context
-            .pyproject_toml_path
-            .parent()
-            .ok_or_else(|| anyhow!("failed to get project directory"))?;
-        let uri = Url::from_file_path(project_dir)
-            .map_err(|_| anyhow!("failed to convert project directory to file URL"))?;
-        let content = format!("{{\"dir_info\": {{\"editable\": true}}, \"url\": \"{uri}\"}}");
-        fs::write(direct_url_path, content)?;
+    let wheels = build_context.build_wheels()?;
+    // Pure Rust projects have no python sources to build the extension next to
+    let write_inplace_dir = skip_install && build_context.project_layout.python_module.is_none();
+    if write_inplace_dir {
+        for wheel in wheels.iter() {
+            let packages = extract_packages(&wheel.path, inplace_dir).with_context(|| {
+                format!(
+                    "Failed to write the package from {} to {}",
+                    wheel.path.display(),
+                    inplace_dir.display()
+                )
+            })?;
+            if packages.is_empty() {
+                eprintln!(
+                    "⚠️  Warning: {} doesn't contain an importable package",
+                    wheel.path.display()
+                );
+            } else {
+                eprintln!(
+                    "📦 Wrote {} to {}, add it to PYTHONPATH to import it",
+                    packages.join(", "),
+                    inplace_dir.display()
+                );
+            }
+        }
+    }
+    let direct_url = editable_direct_url(build_context)?;
+    let metadata23 = &build_context.metadata23;
+    for venv_dir in venv_dirs {
+        let python = target.get_venv_python(venv_dir);
+        let interpreter =
+            PythonInterpreter::check_executable(&python, target, build_context.bridge())?
+                .ok_or_else(|| {
+                    anyhow!("Expected `python` to be a python interpreter inside a virtualenv ಠ_ಠ")
+                })?;
+        let scheme = InstallScheme::venv(venv_dir, &interpreter, target)?;
+        let install_backend = install_backend(&scheme.root, &interpreter)?;
+        let dependencies =
+            resolve_dependencies(build_context, extras, groups, lockfile, &interpreter)?;
+        let cache = if skip_install {
+            None
+        } else {
+            Some(DevelopCache::new(
+                build_context,
+                &interpreter,
+                &scheme,
+                &dependencies.requirements,
+                &wheels,
+            )?)
+        };
+        if let Some(cache) = &cache {
+            if cache.is_fresh(&scheme, &metadata23.name)? {
+                eprintln!(
+                    "✨ {}-{} is already installed and up to date in {}",
+                    metadata23.name,
+                    metadata23.version,
+                    venv_dir.display()
+                );
+                continue;
+            }
+        }
+
+        if !write_inplace_dir {
+            install_dependencies(&dependencies, &interpreter, &install_backend)?;
+        }
+
+        let Some(cache) = cache else {
+            continue;
+        };
+        // maturin can't generate the `.exe` launchers that Windows needs for entry points
+        let use_pip =
+            scheme.windows && !(metadata23.scripts.is_empty() && metadata23.gui_scripts.is_empty());
+        for wheel in wheels.iter() {
+            let dist_info = if use_pip {
+                // The conda backend only leaves the dependencies to conda, the wheel itself
+                // still needs pip
+                let install_backend = match install_backend {
+                    InstallBackend::Conda => &InstallBackend::Pip { path: None },
+                    ref install_backend => install_backend,
+                };
+                pip_install_wheel(&python, venv_dir, &wheel.path, install_backend)?;
+                let installed = InstalledDistribution::find(&scheme.platlib, &metadata23.name)?;
+                let [installed] = installed.as_slice() else {
+                    bail!(
+                        "Expected pip to install exactly one version of {} into {}",
+                        metadata23.name,
+                        venv_dir.display()
+                    );
+                };
+                // pip points direct_url.json to the temporary wheel, the maturin import hook
+                // needs the project directory instead
+                println!("✏️  Setting installed package as editable");
+                set_direct_url(&installed.dist_info, &direct_url)?;
+                installed.dist_info.clone()
+            } else {
+                install_wheel(&wheel.path, &scheme, Some(&direct_url)).with_context(|| {
+                    format!(
+                        "Failed to install {} into {}",
+                        wheel.path.display(),
+                        venv_dir.display()
+                    )
+                })?
+            };
+            cache.store(&dist_info)?;
+            if is_conda_env(&scheme.root) {
+                let wheel_name = wheel.path.file_name().unwrap().to_string_lossy();
+                for distribution in InstalledDistribution::find(&scheme.purelib, &metadata23.name)?
+                {
+                    write_conda_record(&scheme, &distribution, &wheel_name)?;
+                }
+            }
+            eprintln!(
+                "🛠 Installed {}-{} into {}",
+                metadata23.name,
+                metadata23.version,
+                venv_dir.display()
+            );
+        }
     }
+    BuildReport { artifacts: wheels }.print(message_format)?;
+
     Ok(())
 }
 
-fn parse_direct_url_path(pip_show_output: &str) -> Result<Option<PathBuf>> {
-    if let Some(Some(location)) = Regex::new(r"Location: ([^\r\n]*)")?
-        .captures(pip_show_output)
-        .map(|c| c.get(1))
-    {
-        if let Some(Some(direct_url_path)) = Regex::new(r"  (.*direct_url.json)")?
-            .captures(pip_show_output)
-            .map(|c| c.get(1))
-        {
-            return Ok(Some(
-                PathBuf::from(location.as_str()).join(direct_url_path.as_str()),
-            ));
+/// Virtualenvs whose interpreters get the same key can share a build: all of them for abi3 on
+/// CPython and for bindings that don't depend on the interpreter, else those with the same
+/// [PythonInterpreter::environment_signature]
+fn build_key(bridge: &BridgeModel, interpreter: &PythonInterpreter) -> String {
+    match bridge {
+        BridgeModel::Bin(None) | BridgeModel::Cffi | BridgeModel::UniFfi => String::new(),
+        BridgeModel::BindingsAbi3(..) if interpreter.interpreter_kind.is_cpython() => {
+            "abi3".to_string()
+        }
+        _ => interpreter.environment_signature(),
+    }
+}
+
+/// Groups the virtualenvs by their [build_key], in the order in which the keys first appear
+fn group_by_build_key(
+    bridge: &BridgeModel,
+    venvs: &[(PathBuf, PythonInterpreter)],
+) -> Vec<(String, Vec<PathBuf>)> {
+    let mut groups: Vec<(String, Vec<PathBuf>)> = Vec::new();
+    for (venv_dir, interpreter) in venvs {
+        let key = build_key(bridge, interpreter);
+        match groups.iter_mut().find(|(existing, _)| existing == &key) {
+            Some((_, group)) => group.push(venv_dir.clone()),
+            None => groups.push((key, vec![venv_dir.clone()])),
+        }
+    }
+    groups
+}
+
+/// Splits the virtualenvs into groups that share a build, each with the build context to use
+///
+/// With more than one group, each group gets a separate cargo target directory from
+/// [interpreter_target_dir], so that the builds for different interpreters don't invalidate each
+/// other.
+fn plan_builds(
+    build_context: BuildContext,
+    build_options: &BuildOptions,
+    venv_dirs: &[PathBuf],
+    release: bool,
+    strip: bool,
+) -> Result<Vec<(BuildContext, Vec<PathBuf>)>> {
+    if venv_dirs.len() < 2 {
+        return Ok(vec![(build_context, venv_dirs.to_vec())]);
+    }
+    let target = &build_context.target;
+    let mut venvs = Vec::new();
+    for venv_dir in venv_dirs {
+        let python = target.get_venv_python(venv_dir);
+        let interpreter =
+            PythonInterpreter::check_executable(&python, target, build_context.bridge())?
+                .with_context(|| {
+                    format!(
+                        "Expected {} to be a python interpreter inside a virtualenv",
+                        python.display()
+                    )
+                })?;
+        venvs.push((venv_dir.clone(), interpreter));
+    }
+    let groups = group_by_build_key(build_context.bridge(), &venvs);
+    if groups.len() == 1 {
+        return Ok(vec![(build_context, venv_dirs.to_vec())]);
+    }
+
+    let mut builds = Vec::new();
+    for (key, venv_dirs) in groups {
+        let mut build_options = build_options.clone();
+        build_options.interpreter = vec![target.get_venv_python(&venv_dirs[0])];
+        build_options.cargo.target_dir =
+            Some(interpreter_target_dir(&build_context.target_dir, &key));
+        eprintln!(
+            "🐍 Building for {} in {}",
+            key,
+            venv_dirs
+                .iter()
+                .map(|venv_dir| venv_dir.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        builds.push((
+            build_options.into_build_context(release, strip, true)?,
+            venv_dirs,
+        ));
+    }
+    Ok(builds)
+}
+
+/// Runs the command given with `--exec` through the shell, with the virtualenv activated
+fn run_exec(command: &str, target: &Target, venv_dir: &Path) -> Result<()> {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+    let path = env::var_os("PATH").unwrap_or_default();
+    let path = env::join_paths(
+        iter::once(target.get_venv_bin_dir(venv_dir)).chain(env::split_paths(&path)),
+    )?;
+    eprintln!("🏃 Running `{command}`");
+    let status = cmd
+        .env("VIRTUAL_ENV", venv_dir)
+        .env("PATH", path)
+        .status()
+        .with_context(|| format!("Failed to run `{command}`"))?;
+    if !status.success() {
+        bail!("`{command}` failed with {status}");
+    }
+    Ok(())
+}
+
+/// Prints an error the same way as main does, but without exiting
+fn report_error(err: &anyhow::Error) {
+    eprintln!("💥 maturin failed");
+    for cause in err.chain() {
+        eprintln!("  Caused by: {cause}");
+    }
+}
+
+/// Waits for the watched files to change, reporting errors while walking them instead of
+/// stopping, e.g. when a directory is deleted during a branch switch
+fn wait_for_change(inputs: &WatchedInputs, snapshot: &Snapshot) -> Snapshot {
+    let mut last_error = None;
+    loop {
+        match inputs.wait_for_change(snapshot) {
+            Ok(changed) => return changed,
+            Err(err) => {
+                // The walk is retried on every poll, so only report new errors
+                let message = format!("{err:#}");
+                if last_error.as_ref() != Some(&message) {
+                    report_error(&err);
+                    last_error = Some(message);
+                }
+            }
         }
     }
-    Ok(None)
 }
 
 /// Installs a crate by compiling it and copying the shared library to site-packages.
 /// Also adds the dist-info directory to make sure pip and other tools detect the library
 ///
-/// Works only in a virtualenv.
-#[allow(clippy::too_many_arguments)]
-pub fn develop(develop_options: DevelopOptions, venv_dir: &Path) -> Result<()> {
+/// Works only in virtualenvs. The package is built once for each distinct interpreter ABI among
+/// the virtualenvs and installed into all of them. With `--watch`, this rebuilds and reinstalls
+/// whenever the inputs of the build change and only returns on errors before the first build.
+pub fn develop(develop_options: DevelopOptions, venv_dirs: &[PathBuf]) -> Result<()> {
     let DevelopOptions {
         bindings,
         release,
         strip,
         extras,
+        groups,
+        lockfile,
         skip_install,
+        inplace_dir,
         pip_path,
         cargo_options,
         uv,
+        install_deps,
+        watch,
+        exec,
+        message_format,
+        venvs: _,
+        all_venvs: _,
     } = develop_options;
+    let Some(first_venv) = venv_dirs.first() else {
+        bail!("No virtualenv to install into")
+    };
     let mut target_triple = cargo_options.target.as_ref().map(|x| x.to_string());
     let target = Target::from_target_triple(cargo_options.target)?;
-    let python = target.get_venv_python(venv_dir);
+    let python = target.get_venv_python(first_venv);
 
     // check python platform and architecture
     if !target.user_specified {
@@ -330,12 +737,21 @@ pub fn develop(develop_options: DevelopOptions, venv_dir: &Path) -> Result<()> {
     let wheel_dir = TempDir::new().context("Failed to create temporary directory")?;
 
     let build_options = BuildOptions {
-        platform_tag: vec![PlatformTag::Linux],
+        platform_tag: vec![PlatformTag::Linux.to_string()],
         interpreter: vec![python.clone()],
         find_interpreter: false,
+        interpreter_jobs: None,
         bindings,
         out: Some(wheel_dir.path().to_path_buf()),
         skip_auditwheel: false,
+        auditwheel: None,
+        auditwheel_exclude: Vec::new(),
+        reproducible: false,
+        sbom: None,
+        third_party_licenses: false,
+        build_tag: None,
+        local_version: None,
+        message_format: MessageFormat::Human,
         #[cfg(feature = "zig")]
         zig: false,
         cargo: CargoOptions {
@@ -344,122 +760,202 @@ pub fn develop(develop_options: DevelopOptions, venv_dir: &Path) -> Result<()> {
         },
     };
 
-    let build_context = build_options.into_build_context(release, strip, true)?;
-
-    let interpreter =
-        PythonInterpreter::check_executable(&python, &target, build_context.bridge())?.ok_or_else(
-            || anyhow!("Expected `python` to be a python interpreter inside a virtualenv ಠ_ಠ"),
-        )?;
-
-    let install_backend = if uv {
-        let (uv_path, uv_args) = find_uv_python(&interpreter.executable)
-            .or_else(|_| find_uv_bin())
-            .context("Failed to find uv")?;
-        InstallBackend::Uv {
-            path: uv_path,
-            args: uv_args,
-        }
-    } else {
-        InstallBackend::Pip {
-            path: pip_path.clone(),
+    let install_backend = |prefix: &Path, interpreter: &PythonInterpreter| {
+        if uv {
+            let (uv_path, uv_args) = find_uv_python(&interpreter.executable)
+                .or_else(|_| find_uv_bin())
+                .context("Failed to find uv")?;
+            Ok(InstallBackend::Uv {
+                path: uv_path,
+                args: uv_args,
+            })
+        } else if is_conda_env(prefix) && !install_deps && pip_path.is_none() {
+            Ok(InstallBackend::Conda)
+        } else {
+            Ok(InstallBackend::Pip {
+                path: pip_path.clone(),
+            })
         }
     };
-
-    install_dependencies(&build_context, &extras, &interpreter, &install_backend)?;
-
-    let wheels = build_context.build_wheels()?;
-    if !skip_install {
-        for (filename, _supported_version) in wheels.iter() {
-            pip_install_wheel(
+    let develop_once = |build_context: BuildContext| -> Result<()> {
+        // Shared by the builds for all virtualenvs, whose target directories differ
+        let inplace_dir = inplace_dir
+            .clone()
+            .unwrap_or_else(|| build_context.target_dir.join("maturin").join("inplace"));
+        for (build_context, venv_dirs) in
+            plan_builds(build_context, &build_options, venv_dirs, release, strip)?
+        {
+            build_and_install(
                 &build_context,
-                &python,
-                venv_dir,
-                filename,
-                pip_path.clone(),
-                &install_backend,
+                &venv_dirs,
+                &target,
+                &extras,
+                &groups,
+                lockfile.as_deref(),
+                skip_install,
+                &inplace_dir,
+                install_backend,
+                message_format,
             )?;
-            eprintln!(
-                "🛠 Installed {}-{}",
-                build_context.metadata23.name, build_context.metadata23.version
-            );
         }
+        Ok(())
+    };
+    let exec_command = || -> Result<()> {
+        if let Some(exec) = &exec {
+            for venv_dir in venv_dirs {
+                run_exec(exec, &target, venv_dir)?;
+            }
+        }
+        Ok(())
+    };
+
+    if !watch {
+        let build_context = build_options
+            .clone()
+            .into_build_context(release, strip, true)?;
+        develop_once(build_context)?;
+        return exec_command();
     }
 
-    Ok(())
+    let mut inputs: Option<WatchedInputs> = None;
+    let mut snapshot = Snapshot::new();
+    let mut built_fingerprint = None;
+    loop {
+        // Cargo.toml or pyproject.toml may have changed, so we start from scratch every time
+        match build_options
+            .clone()
+            .into_build_context(release, strip, true)
+        {
+            Ok(build_context) => {
+                match WatchedInputs::from_build_context(&build_context) {
+                    Ok(watched) => inputs = Some(watched),
+                    // Without any inputs, we don't know which files to watch
+                    Err(err) if inputs.is_none() => return Err(err),
+                    // Keep watching the previous inputs
+                    Err(err) => report_error(&err),
+                }
+                match inputs.as_ref().unwrap().snapshot() {
+                    Ok(current) => {
+                        snapshot = current;
+                        let current_fingerprint = fingerprint(&snapshot);
+                        if built_fingerprint.as_ref() == Some(&current_fingerprint) {
+                            eprintln!("👀 The sources didn't change, skipping the rebuild");
+                        } else {
+                            match develop_once(build_context) {
+                                Ok(()) => {
+                                    built_fingerprint = Some(current_fingerprint);
+                                    if let Err(err) = exec_command() {
+                                        report_error(&err);
+                                    }
+                                }
+                                Err(err) => report_error(&err),
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        report_error(&err);
+                        // Rebuild once the watched files can be read again
+                        snapshot = Snapshot::new();
+                    }
+                }
+            }
+            // Without a build context, we don't know which files to watch
+            Err(err) if inputs.is_none() => return Err(err),
+            Err(err) => report_error(&err),
+        }
+        eprintln!("👀 Watching for changes, press Ctrl+C to stop");
+        snapshot = wait_for_change(inputs.as_ref().unwrap(), &snapshot);
+        eprintln!("🔄 Detected changes, rebuilding");
+    }
 }
 
 #[cfg(test)]
-mod test {
-    use std::path::PathBuf;
+mod tests {
+    use super::*;
+    use crate::python_interpreter::{InterpreterConfig, InterpreterKind};
 
-    use super::parse_direct_url_path;
+    fn interpreter(kind: InterpreterKind, minor: usize) -> PythonInterpreter {
+        let target =
+            Target::from_target_triple(Some("x86_64-unknown-linux-gnu".to_string())).unwrap();
+        PythonInterpreter::from_config(
+            InterpreterConfig::lookup_one(&target, kind, (3, minor)).unwrap(),
+        )
+    }
 
     #[test]
-    #[cfg(not(target_os = "windows"))]
-    fn test_parse_direct_url() {
-        let example_with_direct_url = "\
-Name: my-project
-Version: 0.1.0
-Location: /foo bar/venv/lib/pythonABC/site-packages
-Editable project location: /tmp/temporary.whl
-Files:
-  my_project-0.1.0+abc123de.dist-info/INSTALLER
-  my_project-0.1.0+abc123de.dist-info/METADATA
-  my_project-0.1.0+abc123de.dist-info/RECORD
-  my_project-0.1.0+abc123de.dist-info/REQUESTED
-  my_project-0.1.0+abc123de.dist-info/WHEEL
-  my_project-0.1.0+abc123de.dist-info/direct_url.json
-  my_project-0.1.0+abc123de.dist-info/entry_points.txt
-  my_project.pth
-";
-        let expected_path = PathBuf::from("/foo bar/venv/lib/pythonABC/site-packages/my_project-0.1.0+abc123de.dist-info/direct_url.json");
-        assert_eq!(
-            parse_direct_url_path(example_with_direct_url).unwrap(),
-            Some(expected_path)
-        );
+    fn build_keys() {
+        let cpython = interpreter(InterpreterKind::CPython, 10);
+        let pypy = interpreter(InterpreterKind::PyPy, 10);
+        let abi3 = BridgeModel::BindingsAbi3(3, 8);
+        assert_eq!(build_key(&abi3, &cpython), "abi3");
+        // PyPy doesn't support abi3, so it needs a build of its own
+        assert_eq!(build_key(&abi3, &pypy), "pypy-3.10-64bit");
+        let bindings = BridgeModel::Bindings("pyo3".to_string(), 7);
+        assert_eq!(build_key(&bindings, &cpython), "cpython-3.10-64bit");
+        for bridge in [
+            BridgeModel::Bin(None),
+            BridgeModel::Cffi,
+            BridgeModel::UniFfi,
+        ] {
+            assert_eq!(build_key(&bridge, &cpython), "");
+            assert_eq!(build_key(&bridge, &pypy), "");
+        }
+    }
 
-        let example_without_direct_url = "\
-Name: my-project
-Version: 0.1.0
-Location: /foo bar/venv/lib/pythonABC/site-packages
-Files:
-  my_project-0.1.0+abc123de.dist-info/INSTALLER
-  my_project-0.1.0+abc123de.dist-info/METADATA
-  my_project-0.1.0+abc123de.dist-info/RECORD
-  my_project-0.1.0+abc123de.dist-info/REQUESTED
-  my_project-0.1.0+abc123de.dist-info/WHEEL
-  my_project-0.1.0+abc123de.dist-info/entry_points.txt
-  my_project.pth
-";
+    #[test]
+    fn grouping() {
+        let venvs = vec![
+            (
+                PathBuf::from(".venv-3.12"),
+                interpreter(InterpreterKind::CPython, 12),
+            ),
+            (
+                PathBuf::from(".venv-3.10"),
+                interpreter(InterpreterKind::CPython, 10),
+            ),
+            (
+                PathBuf::from(".venv-3.12-2"),
+                interpreter(InterpreterKind::CPython, 12),
+            ),
+        ];
 
+        let groups = group_by_build_key(&BridgeModel::BindingsAbi3(3, 8), &venvs);
         assert_eq!(
-            parse_direct_url_path(example_without_direct_url).unwrap(),
-            None
+            groups,
+            vec![(
+                "abi3".to_string(),
+                vec![
+                    PathBuf::from(".venv-3.12"),
+                    PathBuf::from(".venv-3.10"),
+                    PathBuf::from(".venv-3.12-2")
+                ]
+            )]
         );
-    }
 
-    #[test]
-    #[cfg(target_os = "windows")]
-    fn test_parse_direct_url_windows() {
-        let example_with_direct_url_windows = "\
-Name: my-project\r
-Version: 0.1.0\r
-Location: C:\\foo bar\\venv\\Lib\\site-packages\r
-Files:\r
-  my_project-0.1.0+abc123de.dist-info\\INSTALLER\r
-  my_project-0.1.0+abc123de.dist-info\\METADATA\r
-  my_project-0.1.0+abc123de.dist-info\\RECORD\r
-  my_project-0.1.0+abc123de.dist-info\\REQUESTED\r
-  my_project-0.1.0+abc123de.dist-info\\WHEEL\r
-  my_project-0.1.0+abc123de.dist-info\\direct_url.json\r
-  my_project-0.1.0+abc123de.dist-info\\entry_points.txt\r
-  my_project.pth\r
-";
-
-        let expected_path = PathBuf::from("C:\\foo bar\\venv\\Lib\\site-packages\\my_project-0.1.0+abc123de.dist-info\\direct_url.json");
+        let groups = group_by_build_key(&BridgeModel::Bindings("pyo3".to_string(), 7), &venvs);
+        assert_eq!(
+            groups,
+            vec![
+                (
+                    "cpython-3.12-64bit".to_string(),
+                    vec![PathBuf::from(".venv-3.12"), PathBuf::from(".venv-3.12-2")]
+                ),
+                (
+                    "cpython-3.10-64bit".to_string(),
+                    vec![PathBuf::from(".venv-3.10")]
+                ),
+            ]
+        );
+        let target_dirs: Vec<PathBuf> = groups
+            .iter()
+            .map(|(key, _)| interpreter_target_dir(Path::new("target"), key))
+            .collect();
         assert_eq!(
-            parse_direct_url_path(example_with_direct_url_windows).unwrap(),
-            Some(expected_path)
+            target_dirs,
+            vec![
+                Path::new("target/interpreters/cpython-3.12-64bit"),
+                Path::new("target/interpreters/cpython-3.10-64bit"),
+            ]
         );
     }
 }
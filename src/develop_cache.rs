@@ -0,0 +1,240 @@
+//! Lets `maturin develop` skip installing the wheel when neither the build nor the installed
+//! files changed since the last install into the same virtualenv
+use crate::inspect::InspectedDistribution;
+use crate::install::{InstallScheme, InstalledDistribution};
+use crate::watch::{fingerprint, target_dirs, WatchedInputs};
+use crate::{BuildContext, BuiltWheelMetadata, PythonInterpreter};
+use anyhow::Result;
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// What the last `maturin develop` installed into a virtualenv
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    /// The fingerprint of the build that was installed
+    fingerprint: String,
+    /// The `.dist-info` directory of the installed wheel
+    dist_info: PathBuf,
+}
+
+/// The fingerprint of a develop build and where it is cached
+///
+/// The fingerprint covers the maturin version, the interpreter, the requirements that are
+/// installed along with the package, the python source tree and the contents of the built wheels,
+/// which include the compiled library and the metadata. It is stored per virtualenv in
+/// `<target dir>/maturin/`.
+#[derive(Debug)]
+pub struct DevelopCache {
+    path: PathBuf,
+    fingerprint: String,
+}
+
+impl DevelopCache {
+    /// Computes the fingerprint of the wheels that were just built for the virtualenv
+    pub fn new(
+        build_context: &BuildContext,
+        interpreter: &PythonInterpreter,
+        scheme: &InstallScheme,
+        requirements: &[String],
+        wheels: &[BuiltWheelMetadata],
+    ) -> Result<Self> {
+        let mut inputs = vec![
+            env!("CARGO_PKG_VERSION").to_string(),
+            interpreter.environment_signature(),
+            interpreter.executable.to_string_lossy().to_string(),
+        ];
+        inputs.extend(requirements.iter().cloned());
+
+        let project_layout = &build_context.project_layout;
+        let mut python_sources: Vec<PathBuf> = project_layout
+            .python_packages
+            .iter()
+            .map(|package| project_layout.python_dir.join(package))
+            .collect();
+        python_sources.extend(project_layout.python_module.clone());
+        python_sources.extend(project_layout.data.clone());
+        let python_sources =
+            WatchedInputs::new(python_sources, Vec::new(), target_dirs(build_context));
+        inputs.push(fingerprint(&python_sources.snapshot()?));
+
+        // Different virtualenvs get different cache files
+        let venv_hash = format!(
+            "{:x}",
+            Sha256::digest(scheme.root.to_string_lossy().as_bytes())
+        );
+        let path = build_context
+            .target_dir
+            .join("maturin")
+            .join(format!("develop-{}.json", &venv_hash[..16]));
+        let wheels: Vec<&Path> = wheels.iter().map(|wheel| wheel.path.as_path()).collect();
+        Ok(Self {
+            path,
+            fingerprint: build_fingerprint(&inputs, &wheels)?,
+        })
+    }
+
+    /// Whether the last install into the virtualenv had the same fingerprint and is still
+    /// installed unmodified
+    pub fn is_fresh(&self, scheme: &InstallScheme, name: &str) -> Result<bool> {
+        let Ok(contents) = fs::read_to_string(&self.path) else {
+            return Ok(false);
+        };
+        let Ok(entry) = serde_json::from_str::<CacheEntry>(&contents) else {
+            return Ok(false);
+        };
+        if entry.fingerprint != self.fingerprint {
+            debug!("The develop build changed since the last install");
+            return Ok(false);
+        }
+        // The virtualenv may have been changed since, e.g. by pip or by deleting files
+        let installed = match InstalledDistribution::find(&scheme.purelib, name) {
+            Ok(installed) => installed,
+            Err(_) => return Ok(false),
+        };
+        match installed.as_slice() {
+            [installed] if installed.dist_info == entry.dist_info => installed.is_unmodified(),
+            _ => {
+                debug!("The installed distribution changed since the last install");
+                Ok(false)
+            }
+        }
+    }
+
+    /// Records the fingerprint after a successful install
+    pub fn store(&self, dist_info: &Path) -> Result<()> {
+        let entry = CacheEntry {
+            fingerprint: self.fingerprint.clone(),
+            dist_info: dist_info.to_path_buf(),
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string(&entry)?)?;
+        Ok(())
+    }
+}
+
+/// Hashes the inputs together with the files of the wheels as listed in their RECORD, which
+/// unlike the archives themselves doesn't depend on timestamps
+fn build_fingerprint(inputs: &[String], wheels: &[&Path]) -> Result<String> {
+    let mut hasher = Sha256::new();
+    let mut update = |value: &str| {
+        hasher.update(value.as_bytes());
+        hasher.update([0]);
+    };
+    for input in inputs {
+        update(input);
+    }
+    for wheel in wheels {
+        let mut record = InspectedDistribution::open(wheel)?.record;
+        record.sort_by(|a, b| a.path.cmp(&b.path));
+        for entry in record {
+            update(&entry.path);
+            update(entry.hash.as_deref().unwrap_or_default());
+        }
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::install::install_wheel;
+    use crate::module_writer::{ModuleWriter, WheelWriter};
+    use crate::Metadata23;
+    use ignore::overrides::Override;
+    use tempfile::TempDir;
+
+    fn test_scheme(root: &Path) -> InstallScheme {
+        let site_packages = root.join("lib").join("python3.12").join("site-packages");
+        InstallScheme {
+            root: root.to_path_buf(),
+            purelib: site_packages.clone(),
+            platlib: site_packages,
+            scripts: root.join("bin"),
+            data: root.to_path_buf(),
+            include: root.join("include").join("site").join("python3.12"),
+            python: root.join("bin").join("python"),
+            windows: false,
+        }
+    }
+
+    fn build_test_wheel(wheel_dir: &Path, module: &[u8]) -> Result<PathBuf> {
+        let metadata23 = Metadata23::new("my-project".to_string(), "1.0.0".parse().unwrap());
+        let mut writer = WheelWriter::new(
+            "py3-none-any",
+            wheel_dir,
+            &metadata23,
+            None,
+            &["py3-none-any".to_string()],
+            Override::empty(),
+            false,
+        )?;
+        writer.add_bytes("my_project/__init__.py", module)?;
+        Ok(writer.finish()?)
+    }
+
+    #[test]
+    fn fresh_until_changed() -> Result<()> {
+        let wheels = TempDir::new()?;
+        let venv = TempDir::new()?;
+        let scheme = test_scheme(&dunce::canonicalize(venv.path())?);
+        fs::create_dir_all(&scheme.purelib)?;
+        let cache = DevelopCache {
+            path: venv.path().join("cache").join("develop.json"),
+            fingerprint: "abc".to_string(),
+        };
+        let wheel = build_test_wheel(wheels.path(), b"")?;
+        let dist_info = install_wheel(&wheel, &scheme, None)?;
+        assert!(!cache.is_fresh(&scheme, "my-project")?);
+
+        cache.store(&dist_info)?;
+        assert!(cache.is_fresh(&scheme, "my-project")?);
+        let changed = DevelopCache {
+            path: cache.path.clone(),
+            fingerprint: "def".to_string(),
+        };
+        assert!(!changed.is_fresh(&scheme, "my-project")?);
+
+        // Editing an installed file needs a reinstall
+        let module = scheme.purelib.join("my_project/__init__.py");
+        fs::write(&module, "print('changed')")?;
+        assert!(!cache.is_fresh(&scheme, "my-project")?);
+        fs::write(&module, "")?;
+        assert!(cache.is_fresh(&scheme, "my-project")?);
+
+        // So does deleting one
+        fs::remove_file(&module)?;
+        assert!(!cache.is_fresh(&scheme, "my-project")?);
+        let dist_info = install_wheel(&wheel, &scheme, None)?;
+        cache.store(&dist_info)?;
+        assert!(cache.is_fresh(&scheme, "my-project")?);
+
+        // And uninstalling the package
+        InstalledDistribution::find(&scheme.purelib, "my-project")?[0].uninstall(&scheme)?;
+        assert!(!cache.is_fresh(&scheme, "my-project")?);
+        Ok(())
+    }
+
+    #[test]
+    fn fingerprint_of_wheels() -> Result<()> {
+        let first = TempDir::new()?;
+        let second = TempDir::new()?;
+        let inputs = vec!["1.0.0".to_string(), "cpython-3.12".to_string()];
+        let wheel = build_test_wheel(first.path(), b"")?;
+        let fingerprint = build_fingerprint(&inputs, &[&wheel])?;
+
+        // The same files written again give the same fingerprint
+        let rebuilt = build_test_wheel(second.path(), b"")?;
+        assert_eq!(build_fingerprint(&inputs, &[&rebuilt])?, fingerprint);
+
+        let changed = build_test_wheel(second.path(), b"print('changed')")?;
+        assert_ne!(build_fingerprint(&inputs, &[&changed])?, fingerprint);
+        let other_inputs = vec!["1.0.0".to_string(), "cpython-3.13".to_string()];
+        assert_ne!(build_fingerprint(&other_inputs, &[&wheel])?, fingerprint);
+        Ok(())
+    }
+}
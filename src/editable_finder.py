@@ -0,0 +1,40 @@
+import importlib.machinery
+import importlib.util
+import os
+import sys
+
+
+class MaturinEditableFinder:
+    """Imports exactly the packages of an editable install from the source tree
+
+    Unlike a .pth file that puts the whole source directory on sys.path, this doesn't expose
+    other top level modules such as tests or conftest.py
+    """
+
+    @classmethod
+    def find_spec(cls, fullname, path=None, target=None):
+        location = MAPPING.get(fullname)
+        if location is None:
+            return None
+        if not os.path.isdir(location):
+            return importlib.util.spec_from_file_location(fullname, location)
+        init = os.path.join(location, "__init__.py")
+        if os.path.isfile(init):
+            return importlib.util.spec_from_file_location(
+                fullname, init, submodule_search_locations=[location]
+            )
+        # A namespace package
+        spec = importlib.machinery.ModuleSpec(fullname, None, is_package=True)
+        spec.submodule_search_locations = [location]
+        return spec
+
+    @classmethod
+    def invalidate_caches(cls):
+        pass
+
+
+def install():
+    if MaturinEditableFinder not in sys.meta_path:
+        # Before the regular path based finders, so that the compiled extension resolves to
+        # the file from the last build even if there are others with different abi tags
+        sys.meta_path.insert(0, MaturinEditableFinder)
@@ -0,0 +1,328 @@
+//! Reads built wheels and source distributions back, e.g. for `maturin inspect`
+use crate::module_writer::{parse_entry_points, parse_record, RecordEntry, WheelFile};
+use crate::Metadata23;
+use anyhow::{bail, Context, Result};
+use flate2::read::GzDecoder;
+use fs_err::File;
+use serde::Serialize;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+use zip::ZipArchive;
+
+/// How `maturin inspect` prints its results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum InspectFormat {
+    /// Human readable table
+    #[default]
+    Table,
+    /// JSON, one object per inspected file
+    Json,
+}
+
+/// Whether the inspected file is a wheel or a source distribution
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistributionKind {
+    /// A `.whl` file
+    Wheel,
+    /// A `.tar.gz` source distribution
+    Sdist,
+}
+
+/// A single file inside a wheel or source distribution
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ArchiveFile {
+    /// The path inside the archive
+    pub path: String,
+    /// The uncompressed size in bytes
+    pub size: u64,
+    /// The compressed size in bytes, only known for wheels
+    pub compressed_size: Option<u64>,
+    /// The compression method, only known for wheels
+    pub compression: Option<String>,
+}
+
+/// The contents of a wheel or source distribution
+#[derive(Debug, Clone, Serialize)]
+pub struct InspectedDistribution {
+    /// Path to the inspected file
+    pub path: PathBuf,
+    /// Wheel or sdist
+    pub kind: DistributionKind,
+    /// The METADATA (wheel) or PKG-INFO (sdist)
+    pub metadata: Metadata23,
+    /// The WHEEL file, wheels only
+    pub wheel: Option<WheelFile>,
+    /// The RECORD file, wheels only
+    pub record: Vec<RecordEntry>,
+    /// All files in the archive, in archive order
+    pub files: Vec<ArchiveFile>,
+}
+
+impl InspectedDistribution {
+    /// Opens a `.whl` or `.tar.gz` file and reads its metadata and file list
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let filename = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .with_context(|| format!("Invalid file name {}", path.display()))?;
+        if filename.ends_with(".whl") {
+            Self::open_wheel(path)
+        } else if filename.ends_with(".tar.gz") {
+            Self::open_sdist(path)
+        } else {
+            bail!(
+                "Don't know how to inspect {}, expected a .whl or .tar.gz file",
+                path.display()
+            )
+        }
+    }
+
+    fn open_wheel(path: &Path) -> Result<Self> {
+        let mut archive = ZipArchive::new(File::open(path)?)
+            .with_context(|| format!("{} is not a valid zip file", path.display()))?;
+
+        let mut files = Vec::with_capacity(archive.len());
+        for idx in 0..archive.len() {
+            let file = archive.by_index(idx)?;
+            files.push(ArchiveFile {
+                path: file.name().to_string(),
+                size: file.size(),
+                compressed_size: Some(file.compressed_size()),
+                compression: Some(file.compression().to_string()),
+            });
+        }
+
+        let dist_info_dirs: Vec<&str> = files
+            .iter()
+            .filter_map(|file| {
+                let (dir, rest) = file.path.split_once('/')?;
+                (dir.ends_with(".dist-info") && rest == "METADATA").then_some(dir)
+            })
+            .collect();
+        let dist_info_dir = match dist_info_dirs.as_slice() {
+            [dir] => dir.to_string(),
+            [] => bail!("{} doesn't contain a .dist-info/METADATA", path.display()),
+            _ => bail!(
+                "{} contains multiple .dist-info directories: {}",
+                path.display(),
+                dist_info_dirs.join(", ")
+            ),
+        };
+        debug!("Found {} in {}", dist_info_dir, path.display());
+
+        let metadata = read_zip_file(&mut archive, &format!("{dist_info_dir}/METADATA"))?;
+        let mut metadata = Metadata23::from_file_contents(&metadata)
+            .with_context(|| format!("Failed to parse {dist_info_dir}/METADATA"))?;
+        let wheel = read_zip_file(&mut archive, &format!("{dist_info_dir}/WHEEL"))?;
+        let wheel = WheelFile::parse(&wheel)
+            .with_context(|| format!("Failed to parse {dist_info_dir}/WHEEL"))?;
+        let record = read_zip_file(&mut archive, &format!("{dist_info_dir}/RECORD"))?;
+        let record = parse_record(&record)
+            .with_context(|| format!("Failed to parse {dist_info_dir}/RECORD"))?;
+
+        let entry_points_path = format!("{dist_info_dir}/entry_points.txt");
+        if files.iter().any(|file| file.path == entry_points_path) {
+            let entry_points = read_zip_file(&mut archive, &entry_points_path)?;
+            let mut entry_points = parse_entry_points(&entry_points)
+                .with_context(|| format!("Failed to parse {entry_points_path}"))?;
+            metadata.scripts = entry_points
+                .shift_remove("console_scripts")
+                .unwrap_or_default();
+            metadata.gui_scripts = entry_points.shift_remove("gui_scripts").unwrap_or_default();
+            metadata.entry_points = entry_points;
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            kind: DistributionKind::Wheel,
+            metadata,
+            wheel: Some(wheel),
+            record,
+            files,
+        })
+    }
+
+    fn open_sdist(path: &Path) -> Result<Self> {
+        let mut archive = tar::Archive::new(GzDecoder::new(File::open(path)?));
+        let mut files = Vec::new();
+        let mut pkg_info = None;
+        for entry in archive
+            .entries()
+            .with_context(|| format!("{} is not a valid .tar.gz file", path.display()))?
+        {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let entry_path = entry.path()?.to_string_lossy().replace('\\', "/");
+            // PKG-INFO is located at `{name}-{version}/PKG-INFO`
+            if entry_path.split('/').count() == 2 && entry_path.ends_with("/PKG-INFO") {
+                let mut contents = String::new();
+                entry.read_to_string(&mut contents)?;
+                pkg_info = Some(contents);
+            }
+            files.push(ArchiveFile {
+                path: entry_path,
+                size: entry.size(),
+                compressed_size: None,
+                compression: None,
+            });
+        }
+
+        let pkg_info =
+            pkg_info.with_context(|| format!("{} doesn't contain a PKG-INFO", path.display()))?;
+        let metadata =
+            Metadata23::from_file_contents(&pkg_info).context("Failed to parse PKG-INFO")?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            kind: DistributionKind::Sdist,
+            metadata,
+            wheel: None,
+            record: Vec::new(),
+            files,
+        })
+    }
+
+    /// Prints a human readable summary to stdout
+    pub fn print_table(&self) {
+        let metadata = &self.metadata;
+        println!("{}", self.path.display());
+        println!("  Name: {}", metadata.name);
+        println!("  Version: {}", metadata.version);
+        println!("  Metadata-Version: {}", metadata.metadata_version);
+        if let Some(summary) = &metadata.summary {
+            println!("  Summary: {summary}");
+        }
+        if let Some(requires_python) = &metadata.requires_python {
+            println!("  Requires-Python: {requires_python}");
+        }
+        for requirement in &metadata.requires_dist {
+            println!("  Requires-Dist: {requirement}");
+        }
+        if let Some(wheel) = &self.wheel {
+            if let Some(generator) = &wheel.generator {
+                println!("  Generator: {generator}");
+            }
+            for tag in &wheel.tags {
+                println!("  Tag: {tag}");
+            }
+        }
+
+        let path_width = self
+            .files
+            .iter()
+            .map(|file| file.path.len())
+            .chain(["Path".len()])
+            .max()
+            .unwrap_or_default();
+        println!();
+        println!(
+            "  {:<path_width$}  {:>10}  {:>10}  Compression",
+            "Path", "Size", "Compressed"
+        );
+        for file in &self.files {
+            println!(
+                "  {:<path_width$}  {:>10}  {:>10}  {}",
+                file.path,
+                file.size,
+                file.compressed_size
+                    .map(|size| size.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                file.compression.as_deref().unwrap_or("-"),
+            );
+        }
+        let total: u64 = self.files.iter().map(|file| file.size).sum();
+        println!("  {} files, {} bytes uncompressed", self.files.len(), total);
+    }
+}
+
+/// Reads a file from a zip archive into a string
+fn read_zip_file(archive: &mut ZipArchive<File>, name: &str) -> Result<String> {
+    let mut file = archive
+        .by_name(name)
+        .with_context(|| format!("Missing {name}"))?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)
+        .with_context(|| format!("{name} is not valid utf-8"))?;
+    Ok(contents)
+}
+
+/// Implementation of `maturin inspect`: prints the contents of each wheel or sdist
+pub fn inspect(files: &[PathBuf], format: InspectFormat) -> Result<()> {
+    let distributions = files
+        .iter()
+        .map(|file| {
+            InspectedDistribution::open(file)
+                .with_context(|| format!("Failed to inspect {}", file.display()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    match format {
+        InspectFormat::Table => {
+            for (idx, distribution) in distributions.iter().enumerate() {
+                if idx > 0 {
+                    println!();
+                }
+                distribution.print_table();
+            }
+        }
+        InspectFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&distributions)?);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ModuleWriter, WheelWriter};
+    use ignore::overrides::Override;
+    use pep440_rs::Version;
+
+    #[test]
+    fn inspect_wheel() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let mut metadata = Metadata23::new("inspect-me".to_string(), Version::new([1, 2]));
+        metadata.summary = Some("A wheel to inspect".to_string());
+        metadata
+            .scripts
+            .insert("inspect-me".to_string(), "inspect_me:main".to_string());
+        let tags = vec!["py3-none-any".to_string()];
+        let mut writer = WheelWriter::new(
+            &tags[0],
+            tmp_dir.path(),
+            &metadata,
+            None,
+            &tags,
+            Override::empty(),
+            false,
+        )?;
+        writer.add_bytes("inspect_me/__init__.py", b"def main(): pass\n")?;
+        let wheel_path = writer.finish()?;
+
+        let inspected = InspectedDistribution::open(&wheel_path)?;
+        assert_eq!(inspected.kind, DistributionKind::Wheel);
+        assert_eq!(inspected.metadata.name, "inspect-me");
+        assert_eq!(inspected.metadata.summary, metadata.summary);
+        assert_eq!(inspected.metadata.scripts, metadata.scripts);
+        assert_eq!(inspected.wheel.unwrap().tags, tags);
+        let paths: Vec<_> = inspected.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "inspect_me-1.2.dist-info/METADATA",
+                "inspect_me-1.2.dist-info/WHEEL",
+                "inspect_me-1.2.dist-info/entry_points.txt",
+                "inspect_me/__init__.py",
+                "inspect_me-1.2.dist-info/RECORD",
+            ]
+        );
+        assert_eq!(inspected.record.len(), 5);
+        assert_eq!(inspected.files[3].size, 17);
+        Ok(())
+    }
+}
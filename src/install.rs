@@ -0,0 +1,890 @@
+//! Installs wheels into a virtualenv without pip, which is what `maturin develop` uses
+//!
+//! This implements the parts of the [binary distribution format](https://packaging.python.org/en/latest/specifications/binary-distribution-format/)
+//! and of [recording installed projects](https://packaging.python.org/en/latest/specifications/recording-installed-packages/)
+//! that are needed to install and uninstall a wheel
+use crate::module_writer::{parse_entry_points, parse_record, ModuleWriter, PathWriter, WheelFile};
+use crate::{PythonInterpreter, Target};
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use fs_err as fs;
+use fs_err::File;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::io;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use tracing::debug;
+
+/// Written to the INSTALLER file of the `.dist-info` directory
+const INSTALLER: &str = "maturin";
+
+/// The directories of an environment that the parts of a wheel are installed into
+///
+/// See https://docs.python.org/3/library/sysconfig.html#installation-paths
+#[derive(Debug, Clone)]
+pub struct InstallScheme {
+    /// The root of the environment, nothing is installed or uninstalled outside of it
+    pub root: PathBuf,
+    /// Pure python modules, i.e. site-packages
+    pub purelib: PathBuf,
+    /// Platform specific modules, which is the same directory as purelib in a virtualenv
+    pub platlib: PathBuf,
+    /// Executables and the launchers for the entry points
+    pub scripts: PathBuf,
+    /// The target of the `data` directory of a wheel
+    pub data: PathBuf,
+    /// C headers are installed into a subdirectory named after the distribution
+    pub include: PathBuf,
+    /// The python interpreter that scripts and launchers run with
+    pub python: PathBuf,
+    /// Whether this is a Windows environment, where entry points need `.exe` launchers that
+    /// maturin can't generate
+    pub windows: bool,
+}
+
+impl InstallScheme {
+    /// The scheme of a virtualenv or a conda environment
+    pub fn venv(venv_dir: &Path, interpreter: &PythonInterpreter, target: &Target) -> Result<Self> {
+        let root = dunce::canonicalize(venv_dir)
+            .with_context(|| format!("Failed to resolve {}", venv_dir.display()))?;
+        let site_packages = interpreter.get_venv_site_package(&root, target);
+        let include = if target.is_windows() {
+            root.join("Include")
+        } else {
+            root.join("include")
+        };
+        Ok(Self {
+            purelib: site_packages.clone(),
+            platlib: site_packages,
+            scripts: target.get_venv_bin_dir(&root),
+            data: root.clone(),
+            include: include
+                .join("site")
+                .join(format!("python{}.{}", interpreter.major, interpreter.minor)),
+            python: target.get_venv_python(&root),
+            windows: target.is_windows(),
+            root,
+        })
+    }
+
+    /// The directories that are never removed, even if uninstalling leaves them empty
+    fn is_protected(&self, dir: &Path) -> bool {
+        !dir.starts_with(&self.root)
+            || [
+                &self.root,
+                &self.purelib,
+                &self.platlib,
+                &self.scripts,
+                &self.data,
+                &self.include,
+            ]
+            .iter()
+            .any(|protected| dir == protected.as_path())
+    }
+}
+
+/// A distribution installed in site-packages, as recorded in its `.dist-info` directory
+#[derive(Debug, Clone)]
+pub struct InstalledDistribution {
+    /// The name as in the `.dist-info` directory, e.g. `my_project`
+    pub name: String,
+    /// The version as in the `.dist-info` directory
+    pub version: String,
+    /// The absolute path of the `.dist-info` directory
+    pub dist_info: PathBuf,
+    /// The absolute paths of all files listed in RECORD
+    pub files: Vec<PathBuf>,
+}
+
+impl InstalledDistribution {
+    /// Finds the installed versions of a distribution in site-packages, comparing the names in
+    /// their normalized form
+    pub fn find(site_packages: &Path, name: &str) -> Result<Vec<Self>> {
+        if !site_packages.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut installed = Vec::new();
+        for entry in fs::read_dir(site_packages)? {
+            let dist_info = entry?.path();
+            let Some(stem) = dist_info
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(".dist-info"))
+            else {
+                continue;
+            };
+            let Some((dist_name, version)) = stem.split_once('-') else {
+                continue;
+            };
+            if !dist_info.is_dir() || normalize_name(dist_name) != normalize_name(name) {
+                continue;
+            }
+            let record_file = dist_info.join("RECORD");
+            if !record_file.is_file() {
+                bail!(
+                    "Can't uninstall {dist_name} {version} since {} is missing, \
+                    it was probably not installed by pip or maturin",
+                    record_file.display()
+                );
+            }
+            let files = parse_record(&fs::read_to_string(&record_file)?)
+                .with_context(|| format!("Failed to parse {}", record_file.display()))?
+                .into_iter()
+                .map(|entry| normalize_path(&site_packages.join(entry.path)))
+                .collect();
+            installed.push(Self {
+                name: dist_name.to_string(),
+                version: version.to_string(),
+                dist_info,
+                files,
+            });
+        }
+        Ok(installed)
+    }
+
+    /// Whether all files listed in RECORD still exist with the recorded hashes and sizes, i.e.
+    /// nobody modified the installed distribution
+    pub fn is_unmodified(&self) -> Result<bool> {
+        let Some(site_packages) = self.dist_info.parent() else {
+            return Ok(false);
+        };
+        let record_file = self.dist_info.join("RECORD");
+        let Ok(record) = fs::read_to_string(&record_file) else {
+            return Ok(false);
+        };
+        for entry in parse_record(&record)? {
+            let Some(hash) = entry.hash else {
+                continue;
+            };
+            let file = normalize_path(&site_packages.join(&entry.path));
+            let Ok(contents) = fs::read(&file) else {
+                return Ok(false);
+            };
+            let actual = format!(
+                "sha256={}",
+                URL_SAFE_NO_PAD.encode(Sha256::digest(&contents))
+            );
+            if actual != hash || entry.size.is_some_and(|size| size != contents.len() as u64) {
+                debug!("{} was modified since it was installed", file.display());
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// The recorded files that exist inside of the environment, which is what
+    /// [InstalledDistribution::uninstall] removes besides bytecode caches and empty directories
+    pub fn existing_files(&self, scheme: &InstallScheme) -> Vec<&Path> {
+        self.files
+            .iter()
+            .filter(|file| file.starts_with(&scheme.root) && file.exists())
+            .map(PathBuf::as_path)
+            .collect()
+    }
+
+    /// Removes the files listed in RECORD together with their bytecode caches, the `.dist-info`
+    /// directory and all directories that became empty
+    ///
+    /// Files outside of the environment are left alone.
+    pub fn uninstall(&self, scheme: &InstallScheme) -> Result<()> {
+        debug!(
+            "Uninstalling {} {} from {}",
+            self.name,
+            self.version,
+            self.dist_info.display()
+        );
+        let mut dirs = BTreeSet::new();
+        for file in &self.files {
+            if !file.starts_with(&scheme.root) {
+                eprintln!(
+                    "⚠️  Warning: Not removing {} since it is outside of {}",
+                    file.display(),
+                    scheme.root.display()
+                );
+                continue;
+            }
+            if file.extension().is_some_and(|ext| ext == "py") {
+                remove_bytecode(file, &mut dirs)?;
+            }
+            remove_file_if_exists(file)?;
+            if let Some(parent) = file.parent() {
+                dirs.insert(parent.to_path_buf());
+            }
+        }
+        if self.dist_info.is_dir() {
+            fs::remove_dir_all(&self.dist_info)?;
+        }
+        // Children sort after their parents, so we go from the deepest directories upwards
+        for dir in dirs.iter().rev() {
+            let mut dir = dir.as_path();
+            while !scheme.is_protected(dir) && is_empty_dir(dir)? {
+                fs::remove_dir(dir)?;
+                let Some(parent) = dir.parent() else { break };
+                dir = parent;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Installs a wheel, replacing all installed versions of the same distribution
+///
+/// Besides the files of the wheel, this generates the launchers for the console and gui scripts
+/// on unix and writes INSTALLER, REQUESTED and the optional `direct_url.json` to the `.dist-info`
+/// directory, followed by a RECORD listing everything that was installed. Returns the path of
+/// the installed `.dist-info` directory.
+pub fn install_wheel(
+    wheel_path: &Path,
+    scheme: &InstallScheme,
+    direct_url: Option<&str>,
+) -> Result<PathBuf> {
+    let mut archive = zip::ZipArchive::new(File::open(wheel_path)?)
+        .with_context(|| format!("Failed to read {}", wheel_path.display()))?;
+    let dist_info_dirs: BTreeSet<&str> = archive
+        .file_names()
+        .filter_map(|name| name.split_once('/'))
+        .map(|(dir, _)| dir)
+        .filter(|dir| dir.ends_with(".dist-info"))
+        .collect();
+    let dist_info_dir = match dist_info_dirs.into_iter().collect::<Vec<_>>().as_slice() {
+        [dist_info_dir] => dist_info_dir.to_string(),
+        dirs => bail!(
+            "Expected {} to contain exactly one .dist-info directory, found {}",
+            wheel_path.display(),
+            dirs.len()
+        ),
+    };
+    let stem = dist_info_dir.strip_suffix(".dist-info").unwrap();
+    let Some((name, _version)) = stem.split_once('-') else {
+        bail!("Invalid .dist-info directory name {dist_info_dir}")
+    };
+    let data_dir = format!("{stem}.data");
+
+    let wheel_file = WheelFile::parse(&read_entry(
+        &mut archive,
+        &format!("{dist_info_dir}/WHEEL"),
+    )?)?;
+    if wheel_file.wheel_version.split('.').next() != Some("1") {
+        bail!(
+            "Unsupported Wheel-Version {} in {}",
+            wheel_file.wheel_version,
+            wheel_path.display()
+        );
+    }
+    let root = if wheel_file.root_is_purelib {
+        &scheme.purelib
+    } else {
+        &scheme.platlib
+    };
+
+    let entry_points_txt = format!("{dist_info_dir}/entry_points.txt");
+    let mut scripts = Vec::new();
+    if archive.file_names().any(|file| file == entry_points_txt) {
+        let mut entry_points = parse_entry_points(&read_entry(&mut archive, &entry_points_txt)?)?;
+        for section in ["console_scripts", "gui_scripts"] {
+            scripts.extend(entry_points.shift_remove(section).into_iter().flatten());
+        }
+    }
+    if scheme.windows {
+        if let Some((script_name, _)) = scripts.first() {
+            bail!(
+                "Can't generate the .exe launcher for the `{script_name}` entry point of {} \
+                on Windows, install it with pip or uv instead",
+                wheel_path.display()
+            );
+        }
+    }
+
+    for installed in InstalledDistribution::find(root, name)? {
+        installed.uninstall(scheme)?;
+    }
+
+    debug!(
+        "Installing {} into {}",
+        wheel_path.display(),
+        root.display()
+    );
+    let mut writer = PathWriter::from_path(root);
+    let skipped =
+        ["RECORD", "RECORD.jws", "RECORD.p7s"].map(|file| format!("{dist_info_dir}/{file}"));
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        if file.is_dir() {
+            continue;
+        }
+        let Some(path) = file.enclosed_name().map(Path::to_path_buf) else {
+            bail!(
+                "{} contains the unsafe path {}",
+                wheel_path.display(),
+                file.name()
+            );
+        };
+        if skipped.contains(&file.name().to_string()) {
+            continue;
+        }
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        let mut permissions = file.unix_mode().unwrap_or(0o644) & 0o777;
+
+        let target = match path.strip_prefix(&data_dir) {
+            Ok(data_path) => {
+                let mut components = data_path.components();
+                let key = components.next().and_then(|key| key.as_os_str().to_str());
+                let base = match key {
+                    Some("purelib") => scheme.purelib.clone(),
+                    Some("platlib") => scheme.platlib.clone(),
+                    Some("scripts") => {
+                        if bytes.starts_with(b"#!python") {
+                            bytes = rewrite_shebang(&bytes, &scheme.python);
+                        }
+                        permissions |= 0o755;
+                        scheme.scripts.clone()
+                    }
+                    Some("data") => scheme.data.clone(),
+                    Some("headers") => scheme.include.join(name),
+                    _ => bail!(
+                        "Invalid entry {} in {}, the .data directory may only contain \
+                        purelib, platlib, scripts, data and headers",
+                        file.name(),
+                        wheel_path.display()
+                    ),
+                };
+                relative_path(&base.join(components.as_path()), root)
+            }
+            Err(_) => path,
+        };
+        writer.add_bytes_with_permissions(target, &bytes, permissions)?;
+    }
+
+    for (script_name, entry_point) in &scripts {
+        add_launcher(&mut writer, scheme, root, script_name, entry_point)?;
+    }
+
+    let dist_info = Path::new(&dist_info_dir);
+    writer.add_bytes(
+        dist_info.join("INSTALLER"),
+        format!("{INSTALLER}\n").as_bytes(),
+    )?;
+    writer.add_bytes(dist_info.join("REQUESTED"), b"")?;
+    if let Some(direct_url) = direct_url {
+        writer.add_bytes(dist_info.join("direct_url.json"), direct_url.as_bytes())?;
+    }
+    writer.write_record_to(dist_info)?;
+    Ok(root.join(dist_info))
+}
+
+/// Extracts the importable modules and packages of a wheel into a directory, so that the
+/// directory can be put on `PYTHONPATH` instead of installing the wheel
+///
+/// The `.dist-info` directory, the scripts, data and headers of the `.data` directory and
+/// `.pth` files are left out. Existing files are removed before they are replaced, so that
+/// processes that still have the old extension module loaded don't crash. Returns the sorted
+/// top level names that were written.
+pub fn extract_packages(wheel_path: &Path, dir: &Path) -> Result<Vec<String>> {
+    let mut archive = zip::ZipArchive::new(File::open(wheel_path)?)
+        .with_context(|| format!("Failed to read {}", wheel_path.display()))?;
+    let mut writer = PathWriter::from_path(dir);
+    let mut top_level = BTreeSet::new();
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        if file.is_dir() {
+            continue;
+        }
+        let Some(path) = file.enclosed_name().map(Path::to_path_buf) else {
+            bail!(
+                "{} contains the unsafe path {}",
+                wheel_path.display(),
+                file.name()
+            );
+        };
+        let mut components = path.components();
+        let Some(first) = components
+            .next()
+            .and_then(|first| first.as_os_str().to_str())
+        else {
+            continue;
+        };
+        let relative = if first.ends_with(".data") {
+            let key = components.next().and_then(|key| key.as_os_str().to_str());
+            if !matches!(key, Some("purelib" | "platlib")) {
+                continue;
+            }
+            components.as_path().to_path_buf()
+        } else if first.ends_with(".dist-info") {
+            continue;
+        } else {
+            path.clone()
+        };
+        if relative.components().count() == 1
+            && relative.extension().is_some_and(|ext| ext == "pth")
+        {
+            continue;
+        }
+        let Some(name) = relative.components().next() else {
+            continue;
+        };
+        top_level.insert(name.as_os_str().to_string_lossy().to_string());
+
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        let permissions = file.unix_mode().unwrap_or(0o644) & 0o777;
+        remove_file_if_exists(&dir.join(&relative))?;
+        writer.add_bytes_with_permissions(&relative, &bytes, permissions)?;
+    }
+    Ok(top_level.into_iter().collect())
+}
+
+fn read_entry(archive: &mut zip::ZipArchive<File>, name: &str) -> Result<String> {
+    let mut contents = String::new();
+    archive
+        .by_name(name)
+        .with_context(|| format!("The wheel is missing {name}"))?
+        .read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+/// The shebang pointing to the interpreter, falling back to a `/bin/sh` trampoline for paths
+/// that the kernel can't handle in a shebang, the same way pip does it
+fn shebang(python: &Path) -> String {
+    let python = python.to_string_lossy();
+    if python.len() > 127 || python.contains(' ') {
+        format!("#!/bin/sh\n'''exec' \"{python}\" \"$0\" \"$@\"\n' '''\n")
+    } else {
+        format!("#!{python}\n")
+    }
+}
+
+/// Replaces a `#!python` or `#!pythonw` placeholder with the actual interpreter
+fn rewrite_shebang(script: &[u8], python: &Path) -> Vec<u8> {
+    let rest = match script.iter().position(|&byte| byte == b'\n') {
+        Some(newline) => &script[newline + 1..],
+        None => &[],
+    };
+    let mut rewritten = shebang(python).into_bytes();
+    rewritten.extend_from_slice(rest);
+    rewritten
+}
+
+/// The python code of a launcher for an entry point in the `module:object.attr [extras]` format
+fn launcher_script(entry_point: &str) -> Result<String> {
+    let reference = entry_point.split('[').next().unwrap_or_default().trim();
+    let Some((module, object)) = reference.split_once(':') else {
+        bail!("Invalid entry point `{entry_point}`, expected `module:function`")
+    };
+    let (module, object) = (module.trim(), object.trim());
+    let import_name = object.split('.').next().unwrap_or(object);
+    Ok(format!(
+        r#"# -*- coding: utf-8 -*-
+import re
+import sys
+from {module} import {import_name}
+if __name__ == "__main__":
+    sys.argv[0] = re.sub(r"(-script\.pyw|\.exe)?$", "", sys.argv[0])
+    sys.exit({object}())
+"#
+    ))
+}
+
+/// Generates the launcher for a console or gui script, which is an executable python script
+fn add_launcher(
+    writer: &mut PathWriter,
+    scheme: &InstallScheme,
+    root: &Path,
+    script_name: &str,
+    entry_point: &str,
+) -> Result<()> {
+    if script_name.is_empty() || script_name.contains(['/', '\\']) {
+        bail!("Invalid script name `{script_name}`");
+    }
+    let launcher = shebang(&scheme.python) + &launcher_script(entry_point)?;
+    writer.add_bytes_with_permissions(
+        relative_path(&scheme.scripts.join(script_name), root),
+        launcher.as_bytes(),
+        0o755,
+    )?;
+    Ok(())
+}
+
+/// Replaces the `direct_url.json` of an installed distribution and its entry in RECORD
+///
+/// pip records the temporary wheel as the location of the distribution, while the maturin import
+/// hook needs the project directory
+pub fn set_direct_url(dist_info: &Path, direct_url: &str) -> Result<()> {
+    let dist_info_dir = dist_info
+        .file_name()
+        .and_then(|name| name.to_str())
+        .with_context(|| format!("Invalid .dist-info directory {}", dist_info.display()))?;
+    let path = format!("{dist_info_dir}/direct_url.json");
+    fs::write(dist_info.join("direct_url.json"), direct_url)?;
+
+    let record_file = dist_info.join("RECORD");
+    let mut record: Vec<String> = fs::read_to_string(&record_file)?
+        .lines()
+        .filter(|line| !line.is_empty() && line.split(',').next() != Some(path.as_str()))
+        .map(ToString::to_string)
+        .collect();
+    let hash = URL_SAFE_NO_PAD.encode(Sha256::digest(direct_url));
+    record.push(format!("{path},sha256={hash},{}", direct_url.len()));
+    fs::write(&record_file, record.join("\n") + "\n")?;
+    Ok(())
+}
+
+/// Normalizes a distribution name for comparisons as in PEP 503
+pub(crate) fn normalize_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for part in name.split(['-', '_', '.']).filter(|part| !part.is_empty()) {
+        if !normalized.is_empty() {
+            normalized.push('-');
+        }
+        normalized.push_str(&part.to_lowercase());
+    }
+    normalized
+}
+
+/// Resolves `.` and `..` in a path without touching the filesystem
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// The path of `path` relative to `base`, going up with `..` where needed
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path = normalize_path(path);
+    let base = normalize_path(base);
+    let common = path
+        .components()
+        .zip(base.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative = PathBuf::new();
+    for _ in base.components().skip(common) {
+        relative.push("..");
+    }
+    relative.extend(path.components().skip(common));
+    relative
+}
+
+fn remove_file_if_exists(file: &Path) -> Result<()> {
+    match fs::remove_file(file) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Removes the `__pycache__/{stem}.*.pyc` files that python created for a module
+fn remove_bytecode(file: &Path, dirs: &mut BTreeSet<PathBuf>) -> Result<()> {
+    let (Some(parent), Some(stem)) = (file.parent(), file.file_stem()) else {
+        return Ok(());
+    };
+    let pycache = parent.join("__pycache__");
+    if !pycache.is_dir() {
+        return Ok(());
+    }
+    let prefix = format!("{}.", stem.to_string_lossy());
+    for entry in fs::read_dir(&pycache)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if file_name.starts_with(&prefix) && file_name.ends_with(".pyc") {
+            remove_file_if_exists(&entry.path())?;
+        }
+    }
+    dirs.insert(pycache);
+    Ok(())
+}
+
+fn is_empty_dir(dir: &Path) -> Result<bool> {
+    Ok(dir.is_dir() && fs::read_dir(dir)?.next().is_none())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_writer::{RecordEntry, WheelWriter};
+    use crate::Metadata23;
+    use ignore::overrides::Override;
+    use tempfile::TempDir;
+
+    fn test_scheme(root: &Path) -> InstallScheme {
+        let site_packages = root.join("lib").join("python3.12").join("site-packages");
+        InstallScheme {
+            root: root.to_path_buf(),
+            purelib: site_packages.clone(),
+            platlib: site_packages,
+            scripts: root.join("bin"),
+            data: root.to_path_buf(),
+            include: root.join("include").join("site").join("python3.12"),
+            python: root.join("bin").join("python"),
+            windows: false,
+        }
+    }
+
+    fn build_test_wheel(wheel_dir: &Path, version: &str) -> Result<PathBuf> {
+        let mut metadata23 = Metadata23::new("my-project".to_string(), version.parse().unwrap());
+        metadata23
+            .scripts
+            .insert("my-cli".to_string(), "my_project.cli:main".to_string());
+        let mut writer = WheelWriter::new(
+            "py3-none-any",
+            wheel_dir,
+            &metadata23,
+            None,
+            &["py3-none-any".to_string()],
+            Override::empty(),
+            false,
+        )?;
+        writer.add_bytes("my_project/__init__.py", b"")?;
+        writer.add_bytes(format!("my_project/v{}.py", version.replace('.', "_")), b"")?;
+        let data_dir = format!("my_project-{version}.data");
+        writer.add_bytes_with_permissions(
+            format!("{data_dir}/scripts/hello"),
+            b"#!python\nprint('hello')\n",
+            0o644,
+        )?;
+        writer.add_bytes(
+            format!("{data_dir}/data/share/my_project/data.txt"),
+            b"data",
+        )?;
+        writer.add_bytes(format!("{data_dir}/headers/my_project.h"), b"")?;
+        Ok(writer.finish()?)
+    }
+
+    #[test]
+    fn install_and_replace_wheel() -> Result<()> {
+        let wheels = TempDir::new()?;
+        let venv = TempDir::new()?;
+        let scheme = test_scheme(&dunce::canonicalize(venv.path())?);
+        fs::create_dir_all(&scheme.purelib)?;
+        fs::create_dir_all(&scheme.scripts)?;
+
+        let wheel = build_test_wheel(wheels.path(), "1.0.0")?;
+        let direct_url = r#"{"dir_info": {"editable": true}, "url": "file:///src"}"#;
+        let dist_info = install_wheel(&wheel, &scheme, Some(direct_url))?;
+        assert_eq!(dist_info, scheme.purelib.join("my_project-1.0.0.dist-info"));
+        assert!(scheme.purelib.join("my_project/v1_0_0.py").is_file());
+        assert_eq!(
+            fs::read_to_string(scheme.scripts.join("hello"))?,
+            format!("#!{}\nprint('hello')\n", scheme.python.display())
+        );
+        let launcher = fs::read_to_string(scheme.scripts.join("my-cli"))?;
+        assert!(launcher.starts_with(&format!("#!{}\n", scheme.python.display())));
+        assert!(launcher.contains("from my_project.cli import main\n"));
+        assert!(launcher.contains("sys.exit(main())"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(scheme.scripts.join("my-cli"))?
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o111, 0o111);
+        }
+        assert!(scheme.root.join("share/my_project/data.txt").is_file());
+        assert!(scheme.include.join("my_project/my_project.h").is_file());
+        assert_eq!(
+            fs::read_to_string(dist_info.join("INSTALLER"))?,
+            "maturin\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dist_info.join("direct_url.json"))?,
+            direct_url
+        );
+
+        let record = parse_record(&fs::read_to_string(dist_info.join("RECORD"))?)?;
+        let paths: Vec<&str> = record.iter().map(|entry| entry.path.as_str()).collect();
+        for expected in [
+            "my_project/__init__.py",
+            "../../../bin/hello",
+            "../../../bin/my-cli",
+            "../../../share/my_project/data.txt",
+            "../../../include/site/python3.12/my_project/my_project.h",
+            "my_project-1.0.0.dist-info/METADATA",
+            "my_project-1.0.0.dist-info/INSTALLER",
+            "my_project-1.0.0.dist-info/direct_url.json",
+            "my_project-1.0.0.dist-info/RECORD",
+        ] {
+            assert!(paths.contains(&expected), "{expected} missing in {paths:?}");
+        }
+
+        let installed = InstalledDistribution::find(&scheme.purelib, "my-project")?;
+        assert!(installed[0].is_unmodified()?);
+        fs::write(scheme.root.join("share/my_project/data.txt"), "changed")?;
+        assert!(!installed[0].is_unmodified()?);
+
+        // Simulate python having compiled the module
+        fs::create_dir_all(scheme.purelib.join("my_project/__pycache__"))?;
+        fs::write(
+            scheme
+                .purelib
+                .join("my_project/__pycache__/v1_0_0.cpython-312.pyc"),
+            b"",
+        )?;
+
+        let wheel = build_test_wheel(wheels.path(), "2.0.0")?;
+        install_wheel(&wheel, &scheme, None)?;
+        assert!(!dist_info.exists());
+        assert!(!scheme.purelib.join("my_project/v1_0_0.py").exists());
+        assert!(!scheme.purelib.join("my_project/__pycache__").exists());
+        assert!(scheme.purelib.join("my_project/v2_0_0.py").is_file());
+        let installed = InstalledDistribution::find(&scheme.purelib, "My.Project")?;
+        assert_eq!(installed.len(), 1);
+        assert_eq!(installed[0].version, "2.0.0");
+
+        installed[0].uninstall(&scheme)?;
+        assert!(!scheme.purelib.join("my_project").exists());
+        assert!(!scheme.root.join("share").exists());
+        assert!(!scheme
+            .root
+            .join("include")
+            .join("site")
+            .join("python3.12")
+            .join("my_project")
+            .exists());
+        assert!(scheme.purelib.is_dir());
+        assert!(scheme.scripts.is_dir());
+        assert_eq!(fs::read_dir(&scheme.scripts)?.count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn install_windows_scheme() -> Result<()> {
+        let wheels = TempDir::new()?;
+        let venv = TempDir::new()?;
+        let root = dunce::canonicalize(venv.path())?;
+        let site_packages = root.join("Lib").join("site-packages");
+        let scheme = InstallScheme {
+            root: root.clone(),
+            purelib: site_packages.clone(),
+            platlib: site_packages,
+            scripts: root.join("Scripts"),
+            data: root.clone(),
+            include: root.join("Include").join("site").join("python3.12"),
+            python: root.join("Scripts").join("python.exe"),
+            windows: true,
+        };
+        fs::create_dir_all(&scheme.purelib)?;
+        fs::create_dir_all(&scheme.scripts)?;
+
+        // The test wheel has a console script, which needs an `.exe` launcher on Windows
+        let wheel = build_test_wheel(wheels.path(), "1.0.0")?;
+        let err = install_wheel(&wheel, &scheme, None).unwrap_err();
+        assert!(
+            err.to_string().contains("`my-cli`"),
+            "unexpected error: {err}"
+        );
+
+        assert_eq!(fs::read_dir(&scheme.purelib)?.count(), 0);
+
+        let metadata23 = Metadata23::new("my-project".to_string(), "1.0.0".parse().unwrap());
+        let mut writer = WheelWriter::new(
+            "py3-none-any",
+            wheels.path(),
+            &metadata23,
+            None,
+            &["py3-none-any".to_string()],
+            Override::empty(),
+            false,
+        )?;
+        writer.add_bytes("my_project/__init__.py", b"")?;
+        writer.add_bytes(
+            "my_project-1.0.0.data/scripts/hello",
+            b"#!python\nprint('hello')\n",
+        )?;
+        writer.add_bytes("my_project-1.0.0.data/headers/my_project.h", b"")?;
+        let wheel = writer.finish()?;
+        let dist_info = install_wheel(&wheel, &scheme, None)?;
+        assert!(scheme.purelib.join("my_project/__init__.py").is_file());
+        assert!(scheme.scripts.join("hello").is_file());
+        assert!(scheme.include.join("my_project/my_project.h").is_file());
+
+        let record = parse_record(&fs::read_to_string(dist_info.join("RECORD"))?)?;
+        let paths: Vec<&str> = record.iter().map(|entry| entry.path.as_str()).collect();
+        for expected in [
+            "my_project/__init__.py",
+            "../../Scripts/hello",
+            "../../Include/site/python3.12/my_project/my_project.h",
+            "my_project-1.0.0.dist-info/RECORD",
+        ] {
+            assert!(paths.contains(&expected), "{expected} missing in {paths:?}");
+        }
+
+        // What develop does after pip installed a wheel with entry points
+        let direct_url = r#"{"dir_info": {"editable": true}, "url": "file:///src"}"#;
+        set_direct_url(&dist_info, direct_url)?;
+        set_direct_url(&dist_info, direct_url)?;
+        assert_eq!(
+            fs::read_to_string(dist_info.join("direct_url.json"))?,
+            direct_url
+        );
+        let record = parse_record(&fs::read_to_string(dist_info.join("RECORD"))?)?;
+        let entries: Vec<&RecordEntry> = record
+            .iter()
+            .filter(|entry| entry.path == "my_project-1.0.0.dist-info/direct_url.json")
+            .collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].size, Some(direct_url.len() as u64));
+        Ok(())
+    }
+
+    #[test]
+    fn extract_only_the_packages() -> Result<()> {
+        let wheels = TempDir::new()?;
+        let dir = TempDir::new()?;
+        let wheel = build_test_wheel(wheels.path(), "1.0.0")?;
+        fs::create_dir_all(dir.path().join("my_project"))?;
+        fs::write(dir.path().join("my_project/v1_0_0.py"), "stale")?;
+        assert_eq!(extract_packages(&wheel, dir.path())?, vec!["my_project"]);
+        assert!(dir.path().join("my_project/__init__.py").is_file());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("my_project/v1_0_0.py"))?,
+            ""
+        );
+        let mut entries: Vec<_> = fs::read_dir(dir.path())?
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        entries.sort();
+        assert_eq!(entries, vec!["my_project"]);
+        Ok(())
+    }
+
+    #[test]
+    fn launcher_for_nested_attribute() -> Result<()> {
+        let script = launcher_script("my_project.cli:app.run [extra]")?;
+        assert!(script.contains("from my_project.cli import app\n"));
+        assert!(script.contains("sys.exit(app.run())"));
+        assert!(launcher_script("my_project").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn relative_paths() {
+        assert_eq!(
+            relative_path(
+                Path::new("/venv/bin/script"),
+                Path::new("/venv/lib/python3.12/site-packages")
+            ),
+            Path::new("../../../bin/script")
+        );
+        assert_eq!(
+            relative_path(
+                Path::new("/venv/lib/python3.12/site-packages/pkg/__init__.py"),
+                Path::new("/venv/lib/python3.12/site-packages")
+            ),
+            Path::new("pkg/__init__.py")
+        );
+        assert_eq!(
+            normalize_path(Path::new("/venv/lib/site-packages/../../bin/script")),
+            Path::new("/venv/bin/script")
+        );
+        assert_eq!(normalize_name("My.Project__name"), "my-project-name");
+    }
+}
@@ -23,37 +23,54 @@
 
 #![deny(missing_docs)]
 
-pub use crate::build_context::{BridgeModel, BuildContext, BuiltWheelMetadata};
-pub use crate::build_options::{BuildOptions, CargoOptions};
+pub use crate::build_context::{
+    BridgeModel, BuildContext, BuildReport, BuiltInterpreter, BuiltWheelMetadata, GraftedLibrary,
+};
+pub use crate::build_options::{BuildOptions, CargoOptions, MessageFormat};
 pub use crate::cargo_toml::CargoToml;
+pub use crate::check::{check_wheel, check_wheels, wheel_problems};
 pub use crate::compile::{compile, BuildArtifact};
 pub use crate::develop::{develop, DevelopOptions};
 #[cfg(feature = "schemars")]
 pub use crate::generate_json_schema::{generate_json_schema, GenerateJsonSchemaOptions, Mode};
-pub use crate::metadata::{Metadata23, WheelMetadata};
+pub use crate::inspect::{inspect, DistributionKind, InspectFormat, InspectedDistribution};
+pub use crate::metadata::{LicenseFile, Metadata23, WheelMetadata};
 pub use crate::module_writer::{
-    write_dist_info, ModuleWriter, PathWriter, SDistWriter, WheelWriter,
+    write_dist_info, ModuleWriter, PathWriter, RecordEntry, SDistWriter, WheelFile, WheelFilename,
+    WheelWriter,
 };
 #[cfg(feature = "scaffolding")]
 pub use crate::new_project::{init_project, new_project, GenerateProjectOptions};
 pub use crate::pyproject_toml::PyProjectToml;
 pub use crate::python_interpreter::PythonInterpreter;
+pub use crate::retag::{retag, retag_wheel, RetagOptions};
+pub use crate::sbom::SbomFormat;
 pub use crate::target::Target;
+pub use crate::uninstall::{uninstall, UninstallOptions};
 #[cfg(feature = "upload")]
 pub use crate::upload::{upload, upload_ui, PublishOpt, Registry, UploadError};
-pub use auditwheel::PlatformTag;
+pub use auditwheel::{
+    audit_wheels, AuditFormat, AuditWheelMode, ClaimedTag, LibraryAudit, LinkerMap, PlatformTag,
+    Policies, PolicyRejection, PolicyViolations, TooNewVersion, WheelAudit, POLICY_FILE_ENV,
+};
 
 mod auditwheel;
 mod build_context;
 mod build_options;
 mod cargo_toml;
+mod check;
 #[cfg(feature = "scaffolding")]
 /// Generate CI configuration
 pub mod ci;
 mod compile;
+mod conda;
 mod cross_compile;
 mod develop;
+mod develop_cache;
 mod generate_json_schema;
+mod inspect;
+mod install;
+mod lockfile;
 mod metadata;
 mod module_writer;
 #[cfg(feature = "scaffolding")]
@@ -61,7 +78,14 @@ mod new_project;
 mod project_layout;
 pub mod pyproject_toml;
 mod python_interpreter;
+mod retag;
+mod sbom;
 mod source_distribution;
+mod spdx;
 mod target;
+mod third_party_licenses;
+mod uninstall;
 #[cfg(feature = "upload")]
 mod upload;
+mod vcs_version;
+mod watch;
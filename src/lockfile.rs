@@ -0,0 +1,584 @@
+//! Reads the pinned dependencies of a project from a `pylock.toml` (PEP 751) or a `uv.lock`, so
+//! that `maturin develop --lockfile` installs exactly the locked versions
+use crate::install::normalize_name;
+use anyhow::{bail, Context, Result};
+use fs_err as fs;
+use pep508_rs::{MarkerEnvironment, MarkerTree};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+use url::Url;
+
+/// Prints the PEP 508 environment markers of the interpreter as JSON
+const MARKER_ENVIRONMENT_SCRIPT: &str = r#"
+import json, os, platform, sys
+
+def format_full_version(info):
+    version = "{0.major}.{0.minor}.{0.micro}".format(info)
+    if info.releaselevel != "final":
+        version += info.releaselevel[0] + str(info.serial)
+    return version
+
+print(json.dumps({
+    "implementation_name": sys.implementation.name,
+    "implementation_version": format_full_version(sys.implementation.version),
+    "os_name": os.name,
+    "platform_machine": platform.machine(),
+    "platform_python_implementation": platform.python_implementation(),
+    "platform_release": platform.release(),
+    "platform_system": platform.system(),
+    "platform_version": platform.version(),
+    "python_full_version": platform.python_version(),
+    "python_version": ".".join(platform.python_version_tuple()[:2]),
+    "sys_platform": sys.platform,
+}))
+"#;
+
+/// Queries the values of the environment markers from a python interpreter
+pub fn marker_environment(python: &Path) -> Result<MarkerEnvironment> {
+    let output = Command::new(python)
+        .args(["-c", MARKER_ENVIRONMENT_SCRIPT])
+        .output()
+        .with_context(|| format!("Failed to run {}", python.display()))?;
+    if !output.status.success() {
+        bail!(
+            "Failed to get the environment markers of {}: {}",
+            python.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    serde_json::from_slice(&output.stdout).context("Failed to parse the environment markers")
+}
+
+/// Whether a marker from a lockfile is true for the environment
+fn evaluate_marker(marker: &str, env: &MarkerEnvironment) -> Result<bool> {
+    let marker = MarkerTree::from_str(marker)
+        .map_err(|err| anyhow::anyhow!("{err}"))
+        .with_context(|| format!("Unsupported marker `{marker}`"))?;
+    Ok(marker.evaluate(env, &[]))
+}
+
+/// The pinned requirements of all packages that the project needs in the environment, excluding
+/// the project itself
+///
+/// The format is determined by the file name: `uv.lock` or `pylock.toml`, which may also be
+/// named `pylock.<name>.toml`. Extras and dependency groups are selected from a `uv.lock`, while
+/// a `pylock.toml` already is the set of packages to install.
+pub fn locked_requirements(
+    lockfile: &Path,
+    project: &str,
+    extras: &[String],
+    groups: &[String],
+    env: &MarkerEnvironment,
+) -> Result<Vec<String>> {
+    let lockfile = dunce::canonicalize(lockfile)
+        .with_context(|| format!("Failed to find lockfile {}", lockfile.display()))?;
+    let lock_dir = lockfile.parent().unwrap();
+    let file_name = lockfile
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let contents = fs::read_to_string(&lockfile)?;
+    if file_name == "uv.lock" {
+        let lock: UvLock = toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse {}", lockfile.display()))?;
+        lock.requirements(lock_dir, project, extras, groups, env)
+            .with_context(|| format!("Failed to read the locked packages from {file_name}"))
+    } else if file_name.starts_with("pylock.") && file_name.ends_with(".toml") {
+        let lock: PyLock = toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse {}", lockfile.display()))?;
+        if !extras.is_empty() || !groups.is_empty() {
+            eprintln!(
+                "⚠️  Warning: Extras and dependency groups are ignored with {file_name}, \
+                all packages of the lockfile that match the environment are installed"
+            );
+        }
+        lock.requirements(lock_dir, project, env)
+            .with_context(|| format!("Failed to read the locked packages from {file_name}"))
+    } else {
+        bail!(
+            "Unsupported lockfile {}, expected a `pylock.toml` or a `uv.lock`",
+            lockfile.display()
+        )
+    }
+}
+
+/// A requirement on a local directory or archive
+fn path_requirement(name: &str, lock_dir: &Path, path: &str) -> Result<String> {
+    let url = Url::from_file_path(lock_dir.join(path))
+        .map_err(|_| anyhow::anyhow!("Invalid path `{path}` for {name}"))?;
+    Ok(format!("{name} @ {url}"))
+}
+
+/// Appends the subdirectory of a direct reference
+fn with_subdirectory(requirement: String, subdirectory: Option<&str>) -> String {
+    match subdirectory {
+        Some(subdirectory) => format!("{requirement}#subdirectory={subdirectory}"),
+        None => requirement,
+    }
+}
+
+/// A `uv.lock`, which contains the resolution for all extras and dependency groups
+#[derive(Debug, Deserialize)]
+struct UvLock {
+    #[serde(default)]
+    package: Vec<UvPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct UvPackage {
+    name: String,
+    version: Option<String>,
+    source: UvSource,
+    /// The environments that this version was resolved for, if the resolution forked
+    #[serde(default)]
+    resolution_markers: Vec<String>,
+    #[serde(default)]
+    dependencies: Vec<UvDependency>,
+    #[serde(default)]
+    optional_dependencies: BTreeMap<String, Vec<UvDependency>>,
+    /// The dependency groups
+    #[serde(default)]
+    dev_dependencies: BTreeMap<String, Vec<UvDependency>>,
+}
+
+/// Where a locked package comes from if it's not from a registry
+#[derive(Debug, Deserialize)]
+struct UvSource {
+    git: Option<String>,
+    url: Option<String>,
+    path: Option<String>,
+    directory: Option<String>,
+    editable: Option<String>,
+    r#virtual: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UvDependency {
+    name: String,
+    /// Only set if the lockfile contains multiple versions of the package
+    version: Option<String>,
+    marker: Option<String>,
+    #[serde(default)]
+    extra: Vec<String>,
+}
+
+impl UvPackage {
+    /// The requirement that installs the locked version, `None` for virtual workspace members
+    fn requirement(&self, lock_dir: &Path) -> Result<Option<String>> {
+        let name = &self.name;
+        let source = &self.source;
+        let requirement = if let Some(git) = &source.git {
+            // `https://github.com/owner/repo?rev=main&subdirectory=python#<commit>`
+            let mut url = Url::parse(git).with_context(|| format!("Invalid git url `{git}`"))?;
+            let commit = url.fragment().map(ToString::to_string);
+            let subdirectory = url
+                .query_pairs()
+                .find(|(key, _)| key == "subdirectory")
+                .map(|(_, value)| value.to_string());
+            url.set_fragment(None);
+            url.set_query(None);
+            let requirement = match commit {
+                Some(commit) => format!("{name} @ git+{url}@{commit}"),
+                None => format!("{name} @ git+{url}"),
+            };
+            with_subdirectory(requirement, subdirectory.as_deref())
+        } else if let Some(url) = &source.url {
+            format!("{name} @ {url}")
+        } else if let Some(path) = source
+            .path
+            .as_ref()
+            .or(source.directory.as_ref())
+            .or(source.editable.as_ref())
+        {
+            path_requirement(name, lock_dir, path)?
+        } else if source.r#virtual.is_some() {
+            return Ok(None);
+        } else {
+            match &self.version {
+                Some(version) => format!("{name}=={version}"),
+                None => name.clone(),
+            }
+        };
+        Ok(Some(requirement))
+    }
+}
+
+impl UvLock {
+    /// Walks the dependency graph from the project, following the selected extras and groups
+    /// and the edges whose markers match the environment
+    fn requirements(
+        &self,
+        lock_dir: &Path,
+        project: &str,
+        extras: &[String],
+        groups: &[String],
+        env: &MarkerEnvironment,
+    ) -> Result<Vec<String>> {
+        let Some(root) = self
+            .package
+            .iter()
+            .position(|package| normalize_name(&package.name) == normalize_name(project))
+        else {
+            bail!("{project} is not part of the lockfile, is it up to date?");
+        };
+        let root_package = &self.package[root];
+        let mut edges: Vec<&UvDependency> = root_package.dependencies.iter().collect();
+        for extra in extras {
+            if let Some(dependencies) = find_normalized(&root_package.optional_dependencies, extra)
+            {
+                edges.extend(dependencies);
+            }
+        }
+        for group in groups {
+            let Some(dependencies) = find_normalized(&root_package.dev_dependencies, group) else {
+                bail!("There is no dependency group `{group}` in the lockfile");
+            };
+            edges.extend(dependencies);
+        }
+
+        // Each package is visited once without extras and once for each of its extras
+        let mut visited: BTreeSet<(usize, String)> = BTreeSet::new();
+        visited.insert((root, String::new()));
+        while let Some(edge) = edges.pop() {
+            if let Some(marker) = &edge.marker {
+                if !evaluate_marker(marker, env)? {
+                    continue;
+                }
+            }
+            let index = self.resolve(edge, env)?;
+            let package = &self.package[index];
+            if visited.insert((index, String::new())) {
+                edges.extend(&package.dependencies);
+            }
+            for extra in &edge.extra {
+                if visited.insert((index, normalize_name(extra))) {
+                    if let Some(dependencies) =
+                        find_normalized(&package.optional_dependencies, extra)
+                    {
+                        edges.extend(dependencies);
+                    }
+                }
+            }
+        }
+
+        let packages: BTreeSet<usize> = visited.into_iter().map(|(index, _)| index).collect();
+        let mut requirements = Vec::new();
+        for index in packages {
+            if index == root {
+                continue;
+            }
+            requirements.extend(self.package[index].requirement(lock_dir)?);
+        }
+        Ok(requirements)
+    }
+
+    /// Finds the package that a dependency edge points to, choosing the version that was resolved
+    /// for the environment if the resolution forked
+    fn resolve(&self, edge: &UvDependency, env: &MarkerEnvironment) -> Result<usize> {
+        let name = normalize_name(&edge.name);
+        let candidates: Vec<usize> = (0..self.package.len())
+            .filter(|&index| {
+                let package = &self.package[index];
+                normalize_name(&package.name) == name
+                    && (edge.version.is_none() || edge.version == package.version)
+            })
+            .collect();
+        if let [index] = candidates.as_slice() {
+            return Ok(*index);
+        }
+        for &index in &candidates {
+            let markers = &self.package[index].resolution_markers;
+            let mut matches = markers.is_empty();
+            for marker in markers {
+                matches |= evaluate_marker(marker, env)?;
+            }
+            if matches {
+                return Ok(index);
+            }
+        }
+        bail!(
+            "Found no locked version of {} for this environment",
+            edge.name
+        )
+    }
+}
+
+/// Looks up an extra or a group, whose names uv stores normalized
+fn find_normalized<'a, T>(map: &'a BTreeMap<String, T>, name: &str) -> Option<&'a T> {
+    let name = normalize_name(name);
+    map.iter()
+        .find(|(key, _)| normalize_name(key) == name)
+        .map(|(_, value)| value)
+}
+
+/// A `pylock.toml` as specified in PEP 751
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PyLock {
+    lock_version: String,
+    #[serde(default)]
+    packages: Vec<PyLockPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PyLockPackage {
+    name: String,
+    version: Option<String>,
+    marker: Option<String>,
+    vcs: Option<PyLockVcs>,
+    directory: Option<PyLockDirectory>,
+    archive: Option<PyLockArchive>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PyLockVcs {
+    r#type: String,
+    url: Option<String>,
+    path: Option<String>,
+    commit_id: String,
+    subdirectory: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PyLockDirectory {
+    path: String,
+    subdirectory: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PyLockArchive {
+    url: Option<String>,
+    path: Option<String>,
+    subdirectory: Option<String>,
+}
+
+impl PyLock {
+    /// The packages whose markers match the environment
+    fn requirements(
+        &self,
+        lock_dir: &Path,
+        project: &str,
+        env: &MarkerEnvironment,
+    ) -> Result<Vec<String>> {
+        if self.lock_version.split('.').next() != Some("1") {
+            bail!("Unsupported lock-version {}", self.lock_version);
+        }
+        let mut requirements = Vec::new();
+        for package in &self.packages {
+            let name = &package.name;
+            if normalize_name(name) == normalize_name(project) {
+                continue;
+            }
+            if let Some(marker) = &package.marker {
+                if !evaluate_marker(marker, env)? {
+                    continue;
+                }
+            }
+            let requirement = if let Some(vcs) = &package.vcs {
+                let url = match (&vcs.url, &vcs.path) {
+                    (Some(url), _) => url.clone(),
+                    (None, Some(path)) => Url::from_file_path(lock_dir.join(path))
+                        .map_err(|_| anyhow::anyhow!("Invalid path `{path}` for {name}"))?
+                        .to_string(),
+                    (None, None) => bail!("{name} has neither a vcs url nor a path"),
+                };
+                with_subdirectory(
+                    format!("{name} @ {}+{url}@{}", vcs.r#type, vcs.commit_id),
+                    vcs.subdirectory.as_deref(),
+                )
+            } else if let Some(directory) = &package.directory {
+                with_subdirectory(
+                    path_requirement(name, lock_dir, &directory.path)?,
+                    directory.subdirectory.as_deref(),
+                )
+            } else if let Some(archive) = &package.archive {
+                let requirement = match (&archive.url, &archive.path) {
+                    (Some(url), _) => format!("{name} @ {url}"),
+                    (None, Some(path)) => path_requirement(name, lock_dir, path)?,
+                    (None, None) => bail!("The archive of {name} has neither a url nor a path"),
+                };
+                with_subdirectory(requirement, archive.subdirectory.as_deref())
+            } else {
+                match &package.version {
+                    Some(version) => format!("{name}=={version}"),
+                    None => name.clone(),
+                }
+            };
+            requirements.push(requirement);
+        }
+        Ok(requirements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use pep508_rs::StringVersion;
+    use pretty_assertions::assert_eq;
+
+    fn linux_env() -> MarkerEnvironment {
+        MarkerEnvironment {
+            implementation_name: "cpython".to_string(),
+            implementation_version: StringVersion::from_str("3.12.1").unwrap(),
+            os_name: "posix".to_string(),
+            platform_machine: "x86_64".to_string(),
+            platform_python_implementation: "CPython".to_string(),
+            platform_release: "6.1.0".to_string(),
+            platform_system: "Linux".to_string(),
+            platform_version: "#1 SMP".to_string(),
+            python_full_version: StringVersion::from_str("3.12.1").unwrap(),
+            python_version: StringVersion::from_str("3.12").unwrap(),
+            sys_platform: "linux".to_string(),
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn uv_lock_follows_extras_groups_and_markers() {
+        let lock: UvLock = toml::from_str(indoc!(
+            r#"
+            version = 1
+            requires-python = ">=3.8"
+
+            [[package]]
+            name = "my-project"
+            version = "0.1.0"
+            source = { editable = "." }
+            dependencies = [
+                { name = "numpy", version = "1.24.4", source = { registry = "https://pypi.org/simple" }, marker = "python_full_version < '3.9'" },
+                { name = "numpy", version = "2.0.0", source = { registry = "https://pypi.org/simple" }, marker = "python_full_version >= '3.9'" },
+                { name = "colorama", marker = "sys_platform == 'win32'" },
+            ]
+
+            [package.optional-dependencies]
+            cli = [{ name = "click" }]
+
+            [package.dev-dependencies]
+            test = [{ name = "pytest" }]
+
+            [[package]]
+            name = "numpy"
+            version = "1.24.4"
+            source = { registry = "https://pypi.org/simple" }
+            resolution-markers = ["python_full_version < '3.9'"]
+
+            [[package]]
+            name = "numpy"
+            version = "2.0.0"
+            source = { registry = "https://pypi.org/simple" }
+            resolution-markers = ["python_full_version >= '3.9'"]
+
+            [[package]]
+            name = "colorama"
+            version = "0.4.6"
+            source = { registry = "https://pypi.org/simple" }
+
+            [[package]]
+            name = "click"
+            version = "8.1.7"
+            source = { registry = "https://pypi.org/simple" }
+            dependencies = [{ name = "colorama", marker = "platform_system == 'Windows'" }]
+
+            [[package]]
+            name = "pytest"
+            version = "8.0.0"
+            source = { registry = "https://pypi.org/simple" }
+            dependencies = [{ name = "pluggy", extra = ["dev"] }]
+
+            [[package]]
+            name = "pluggy"
+            version = "1.4.0"
+            source = { git = "https://github.com/pytest-dev/pluggy?rev=main#0123abc" }
+
+            [package.optional-dependencies]
+            dev = [{ name = "local-helper" }]
+
+            [[package]]
+            name = "local-helper"
+            version = "0.0.1"
+            source = { directory = "helper" }
+            "#
+        ))
+        .unwrap();
+        let lock_dir = Path::new("/project");
+        let env = linux_env();
+
+        assert_eq!(
+            lock.requirements(lock_dir, "My_Project", &[], &[], &env)
+                .unwrap(),
+            vec!["numpy==2.0.0"]
+        );
+        assert_eq!(
+            lock.requirements(
+                lock_dir,
+                "my-project",
+                &["cli".to_string()],
+                &["test".to_string()],
+                &env
+            )
+            .unwrap(),
+            vec![
+                "numpy==2.0.0",
+                "click==8.1.7",
+                "pytest==8.0.0",
+                "pluggy @ git+https://github.com/pytest-dev/pluggy@0123abc",
+                "local-helper @ file:///project/helper",
+            ]
+        );
+        assert_eq!(
+            lock.requirements(lock_dir, "my-project", &[], &["lint".to_string()], &env)
+                .unwrap_err()
+                .to_string(),
+            "There is no dependency group `lint` in the lockfile"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn pylock_filters_by_marker() {
+        let lock: PyLock = toml::from_str(indoc!(
+            r#"
+            lock-version = "1.0"
+            created-by = "uv"
+
+            [[packages]]
+            name = "attrs"
+            version = "25.1.0"
+            index = "https://pypi.org/simple"
+
+            [[packages]]
+            name = "colorama"
+            version = "0.4.6"
+            marker = "sys_platform == 'win32'"
+
+            [[packages]]
+            name = "my-project"
+            directory = { path = ".", editable = true }
+
+            [[packages]]
+            name = "pluggy"
+            vcs = { type = "git", url = "https://github.com/pytest-dev/pluggy", commit-id = "0123abc" }
+
+            [[packages]]
+            name = "helper"
+            archive = { path = "dist/helper-1.0.tar.gz", hashes = { sha256 = "00" } }
+            "#
+        ))
+        .unwrap();
+        assert_eq!(
+            lock.requirements(Path::new("/project"), "my_project", &linux_env())
+                .unwrap(),
+            vec![
+                "attrs==25.1.0",
+                "pluggy @ git+https://github.com/pytest-dev/pluggy@0123abc",
+                "helper @ file:///project/dist/helper-1.0.tar.gz",
+            ]
+        );
+    }
+}
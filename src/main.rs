@@ -10,12 +10,14 @@ use cargo_zigbuild::Zig;
 #[cfg(feature = "cli-completion")]
 use clap::CommandFactory;
 use clap::{Parser, Subcommand};
-#[cfg(feature = "scaffolding")]
-use maturin::{ci::GenerateCI, init_project, new_project, GenerateProjectOptions};
 use maturin::{
-    develop, write_dist_info, BridgeModel, BuildOptions, CargoOptions, DevelopOptions, PathWriter,
-    PlatformTag, PythonInterpreter, Target,
+    audit_wheels, check_wheel, check_wheels, develop, inspect, retag, uninstall, write_dist_info,
+    AuditFormat, BridgeModel, BuildOptions, BuildReport, CargoOptions, DevelopOptions,
+    InspectFormat, MessageFormat, PathWriter, PlatformTag, PythonInterpreter, RetagOptions, Target,
+    UninstallOptions,
 };
+#[cfg(feature = "scaffolding")]
+use maturin::{ci::GenerateCI, init_project, new_project, GenerateProjectOptions};
 #[cfg(feature = "schemars")]
 use maturin::{generate_json_schema, GenerateJsonSchemaOptions};
 #[cfg(feature = "upload")]
@@ -93,7 +95,64 @@ enum Opt {
         /// directory in the project's target directory
         #[arg(short, long)]
         out: Option<PathBuf>,
+        /// Make the source distribution byte-for-byte reproducible
+        #[arg(long)]
+        reproducible: bool,
+        /// Build number for the wheels built from the source distribution, which must start
+        /// with a digit
+        #[arg(long, value_name = "BUILD")]
+        build_tag: Option<String>,
+        /// Local version label appended to the version, e.g. `cpu` for `1.0+cpu`
+        #[arg(long, value_name = "LABEL")]
+        local_version: Option<String>,
+        /// The output format for the built source distribution
+        #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+        message_format: MessageFormat,
+    },
+    /// Show the metadata and the file list of built wheels and source distributions
+    #[command(name = "inspect")]
+    Inspect {
+        /// The wheels (.whl) and source distributions (.tar.gz) to inspect
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = InspectFormat::Table)]
+        format: InspectFormat,
+    },
+    /// Check which manylinux or musllinux tags the native libraries of wheels satisfy
+    ///
+    /// Fails if a wheel claims a platform tag that its libraries don't satisfy.
+    #[command(name = "audit")]
+    Audit {
+        /// The wheels (.whl) to audit
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = AuditFormat::Human)]
+        format: AuditFormat,
+        /// A linker map with a cross reference table, to show which crates use the symbols
+        /// that are too new for a policy
+        ///
+        /// Write one by building with `RUSTFLAGS="-C link-arg=-Wl,-Map=link.map,--cref"`.
+        #[arg(long, value_name = "FILE")]
+        linker_map: Option<PathBuf>,
+    },
+    /// Verify the RECORD hashes, the tags and the metadata of built wheels
+    #[command(name = "check")]
+    Check {
+        /// The wheels (.whl) to check
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
     },
+    /// Change the tags or the build number of built wheels without recompiling
+    #[command(name = "retag")]
+    Retag(RetagOptions),
+    /// Remove a package installed with `maturin develop` from the current virtualenv
+    ///
+    /// This removes all files listed in the RECORD of the installed package, including the `.pth`
+    /// file of editable installs, and with `--clean` the extension modules built in place
+    #[command(name = "uninstall")]
+    Uninstall(UninstallOptions),
     /// Create a new cargo project in an existing directory
     #[cfg(feature = "scaffolding")]
     #[command(name = "init")]
@@ -194,6 +253,35 @@ enum Pep517Command {
     },
 }
 
+/// Finds the virtualenvs named `.venv` or `.venv-*` in the current directory, e.g. one per python
+/// version
+fn find_all_venvs(target: &Target) -> Result<Vec<PathBuf>> {
+    let current_dir = env::current_dir().context("Failed to detect current directory ಠ_ಠ")?;
+    let mut venvs = Vec::new();
+    for entry in fs_err::read_dir(&current_dir)? {
+        let path = entry?.path();
+        let is_venv_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name == ".venv" || name.starts_with(".venv-"));
+        if is_venv_name
+            && path.join("pyvenv.cfg").is_file()
+            && target.get_venv_python(&path).is_file()
+        {
+            venvs.push(path);
+        }
+    }
+    if venvs.is_empty() {
+        bail!(
+            "Couldn't find any virtualenv named `.venv` or `.venv-*` in {}",
+            current_dir.display()
+        );
+    }
+    venvs.sort();
+    debug!("Found virtualenvs {:?}", venvs);
+    Ok(venvs)
+}
+
 fn detect_venv(target: &Target) -> Result<PathBuf> {
     match (env::var_os("VIRTUAL_ENV"), env::var_os("CONDA_PREFIX")) {
         (Some(dir), None) => return Ok(PathBuf::from(dir)),
@@ -269,7 +357,13 @@ fn pep517(subcommand: Pep517Command) -> Result<()> {
             };
 
             let mut writer = PathWriter::from_path(metadata_directory);
-            write_dist_info(&mut writer, &context.metadata23, &tags)?;
+            let metadata23 = context.wheel_metadata23()?;
+            write_dist_info(
+                &mut writer,
+                &metadata23,
+                context.build_tag.as_deref(),
+                &tags,
+            )?;
             println!("{}", context.metadata23.get_dist_info_dir().display());
         }
         Pep517Command::BuildWheel {
@@ -280,7 +374,7 @@ fn pep517(subcommand: Pep517Command) -> Result<()> {
             let build_context = build_options.into_build_context(true, strip, editable)?;
             let wheels = build_context.build_wheels()?;
             assert_eq!(wheels.len(), 1);
-            println!("{}", wheels[0].0.to_str().unwrap());
+            println!("{}", wheels[0].path.to_str().unwrap());
         }
         Pep517Command::WriteSDist {
             sdist_directory,
@@ -295,10 +389,10 @@ fn pep517(subcommand: Pep517Command) -> Result<()> {
                 ..Default::default()
             };
             let build_context = build_options.into_build_context(false, false, false)?;
-            let (path, _) = build_context
+            let sdist = build_context
                 .build_source_distribution()?
                 .context("Failed to build source distribution, pyproject.toml not found")?;
-            println!("{}", path.file_name().unwrap().to_str().unwrap());
+            println!("{}", sdist.path.file_name().unwrap().to_str().unwrap());
         }
     };
 
@@ -335,14 +429,23 @@ fn run() -> Result<()> {
             strip,
             sdist,
         } => {
+            let message_format = build.message_format;
             let build_context = build.into_build_context(release, strip, false)?;
+            let mut artifacts = Vec::new();
             if sdist {
-                build_context
-                    .build_source_distribution()?
-                    .context("Failed to build source distribution, pyproject.toml not found")?;
+                artifacts.push(
+                    build_context
+                        .build_source_distribution()?
+                        .context("Failed to build source distribution, pyproject.toml not found")?,
+                );
             }
             let wheels = build_context.build_wheels()?;
             assert!(!wheels.is_empty());
+            for wheel in &wheels {
+                check_wheel(&wheel.path)?;
+            }
+            artifacts.extend(wheels);
+            BuildReport { artifacts }.print(message_format)?;
         }
         #[cfg(feature = "upload")]
         Opt::Publish {
@@ -352,6 +455,7 @@ fn run() -> Result<()> {
             no_strip,
             no_sdist,
         } => {
+            let message_format = build.message_format;
             let build_context = build.into_build_context(!debug, !no_strip, false)?;
 
             if !build_context.release {
@@ -365,10 +469,20 @@ fn run() -> Result<()> {
                 }
             }
 
-            let items = wheels.into_iter().map(|wheel| wheel.0).collect::<Vec<_>>();
+            let items = wheels
+                .iter()
+                .map(|wheel| wheel.path.clone())
+                .collect::<Vec<_>>();
+            // Don't upload anything if one of the wheels is broken
+            for item in &items {
+                if item.extension().is_some_and(|ext| ext == "whl") {
+                    check_wheel(item)?;
+                }
+            }
             publish.non_interactive_on_ci();
 
-            upload_ui(&items, &publish)?
+            upload_ui(&items, &publish)?;
+            BuildReport { artifacts: wheels }.print(message_format)?;
         }
         Opt::ListPython { target } => {
             let found = if target.is_some() {
@@ -386,12 +500,28 @@ fn run() -> Result<()> {
         }
         Opt::Develop(develop_options) => {
             let target = Target::from_target_triple(develop_options.cargo_options.target.clone())?;
-            let venv_dir = detect_venv(&target)?;
-            develop(develop_options, &venv_dir)?;
+            let venv_dirs = if !develop_options.venvs.is_empty() {
+                develop_options.venvs.clone()
+            } else if develop_options.all_venvs {
+                find_all_venvs(&target)?
+            } else {
+                vec![detect_venv(&target)?]
+            };
+            develop(develop_options, &venv_dirs)?;
         }
-        Opt::SDist { manifest_path, out } => {
+        Opt::SDist {
+            manifest_path,
+            out,
+            reproducible,
+            build_tag,
+            local_version,
+            message_format,
+        } => {
             let build_options = BuildOptions {
                 out,
+                reproducible,
+                build_tag,
+                local_version,
                 cargo: CargoOptions {
                     manifest_path,
                     ..Default::default()
@@ -399,9 +529,28 @@ fn run() -> Result<()> {
                 ..Default::default()
             };
             let build_context = build_options.into_build_context(false, false, false)?;
-            build_context
+            let sdist = build_context
                 .build_source_distribution()?
                 .context("Failed to build source distribution, pyproject.toml not found")?;
+            BuildReport {
+                artifacts: vec![sdist],
+            }
+            .print(message_format)?;
+        }
+        Opt::Inspect { files, format } => inspect(&files, format)?,
+        Opt::Audit {
+            files,
+            format,
+            linker_map,
+        } => audit_wheels(&files, format, linker_map.as_deref())?,
+        Opt::Check { files } => check_wheels(&files)?,
+        Opt::Retag(options) => {
+            retag(&options)?;
+        }
+        Opt::Uninstall(options) => {
+            let target = Target::from_target_triple(None)?;
+            let venv_dir = detect_venv(&target)?;
+            uninstall(options, &venv_dir)?;
         }
         Opt::Pep517(subcommand) => pep517(subcommand)?,
         #[cfg(feature = "scaffolding")]
@@ -1,10 +1,11 @@
+use crate::spdx::LicenseExpression;
 use crate::PyProjectToml;
 use anyhow::{bail, format_err, Context, Result};
 use fs_err as fs;
 use indexmap::IndexMap;
-use pep440_rs::{Version, VersionSpecifiers};
+use pep440_rs::{LocalSegment, Version, VersionSpecifiers};
 use pep508_rs::{MarkerExpression, MarkerOperator, MarkerTree, MarkerValue, Requirement};
-use pyproject_toml::License;
+use pyproject_toml::{License, LicenseFiles};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -26,6 +27,26 @@ pub struct WheelMetadata {
     pub module_name: String,
 }
 
+/// A license file that is copied into the `.dist-info/licenses` directory of a wheel
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct LicenseFile {
+    /// The file on disk
+    pub source: PathBuf,
+    /// The location relative to `.dist-info/licenses`, which is also the `License-File` value
+    pub path: PathBuf,
+}
+
+impl LicenseFile {
+    /// A license file that is placed at the top level of `.dist-info/licenses`
+    pub fn new(source: PathBuf) -> Result<Self> {
+        let path = source
+            .file_name()
+            .with_context(|| format!("missing file name for license file {}", source.display()))?
+            .into();
+        Ok(Self { source, path })
+    }
+}
+
 /// Python Package Metadata 2.3 as specified in
 /// https://packaging.python.org/specifications/core-metadata/
 /// Maturin writes static metadata and does not support dynamic fields atm.
@@ -51,8 +72,10 @@ pub struct Metadata23 {
     pub maintainer: Option<String>,
     pub maintainer_email: Option<String>,
     pub license: Option<String>,
+    // https://peps.python.org/pep-0639/#add-license-expression-field
+    pub license_expression: Option<String>,
     // https://peps.python.org/pep-0639/#license-file-multiple-use
-    pub license_files: Vec<PathBuf>,
+    pub license_files: Vec<LicenseFile>,
     pub classifiers: Vec<String>,
     pub requires_dist: Vec<Requirement>,
     pub provides_dist: Vec<String>,
@@ -66,6 +89,17 @@ pub struct Metadata23 {
     pub entry_points: IndexMap<String, IndexMap<String, String>>,
 }
 
+/// Parses and normalizes a PEP 440 local version label such as `cpu` or `cu121.avx2`
+pub(crate) fn parse_local_version(label: &str) -> Result<Vec<LocalSegment>> {
+    let version = Version::from_str(&format!("0+{label}")).map_err(|err| {
+        format_err!(
+            "Invalid local version label `{label}`, it may only contain letters, digits and \
+            `.`, `-` or `_` as separators: {err}"
+        )
+    })?;
+    Ok(version.local().to_vec())
+}
+
 impl Metadata23 {
     /// Initializes with name, version and otherwise the defaults
     pub fn new(name: String, version: Version) -> Self {
@@ -86,6 +120,7 @@ impl Metadata23 {
             maintainer: None,
             maintainer_email: None,
             license: None,
+            license_expression: None,
             license_files: vec![],
             classifiers: vec![],
             requires_dist: vec![],
@@ -124,6 +159,69 @@ fn path_to_content_type(path: &Path) -> String {
 }
 
 impl Metadata23 {
+    /// Adds the license files matching the glob patterns relative to the pyproject.toml, keeping
+    /// their relative path in `.dist-info/licenses`
+    ///
+    /// Patterns from `project.license-files` must match at least one file, while the default
+    /// patterns may match nothing.
+    fn add_license_files(
+        &mut self,
+        pyproject_dir: &Path,
+        patterns: &[String],
+        explicit: bool,
+    ) -> Result<()> {
+        let escaped_pyproject_dir = glob::Pattern::escape(pyproject_dir.to_str().unwrap());
+        for pattern in patterns {
+            // https://peps.python.org/pep-0639/#add-license-files-key
+            if Path::new(pattern).is_absolute()
+                || pattern.starts_with('/')
+                || pattern.contains('\\')
+                || pattern.split('/').any(|part| part == "..")
+            {
+                bail!("Invalid `project.license-files` pattern `{pattern}`: license files must be relative paths inside the project directory, using `/` as separator");
+            }
+            let mut matched = false;
+            let full_pattern = Path::new(&escaped_pyproject_dir).join(pattern);
+            for license_path in glob::glob(&full_pattern.to_string_lossy())
+                .with_context(|| format!("Invalid `project.license-files` pattern `{pattern}`"))?
+                .filter_map(Result::ok)
+            {
+                if !license_path.is_file() {
+                    continue;
+                }
+                matched = true;
+                // if the pyproject.toml specified the license file,
+                // then we won't list it as automatically included
+                if self
+                    .license_files
+                    .iter()
+                    .any(|license_file| license_file.source == license_path)
+                {
+                    continue;
+                }
+                let path = license_path
+                    .strip_prefix(pyproject_dir)
+                    .with_context(|| {
+                        format!(
+                            "License file {} is outside of {}",
+                            license_path.display(),
+                            pyproject_dir.display()
+                        )
+                    })?
+                    .to_path_buf();
+                eprintln!("📦 Including license file \"{}\"", license_path.display());
+                self.license_files.push(LicenseFile {
+                    source: license_path,
+                    path,
+                });
+            }
+            if explicit && !matched {
+                bail!("`project.license-files` pattern `{pattern}` doesn't match any files");
+            }
+        }
+        Ok(())
+    }
+
     /// Merge metadata with pyproject.toml, where pyproject.toml takes precedence
     ///
     /// pyproject_dir must be the directory containing pyproject.toml
@@ -184,42 +282,51 @@ impl Metadata23 {
 
             if let Some(license) = &project.license {
                 match license {
-                    // TODO: switch to License-Expression core metadata, see https://peps.python.org/pep-0639/#add-license-expression-field
-                    License::String(license_expr) => self.license = Some(license_expr.clone()),
-                    License::Table { file, text } => match (file, text) {
-                        (Some(_), Some(_)) => {
-                            bail!("file and text fields of 'project.license' are mutually-exclusive, only one of them should be specified");
+                    License::String(license_expr) => {
+                        let expression = LicenseExpression::parse(license_expr)
+                            .context("`project.license` in pyproject.toml must be a valid SPDX license expression")?;
+                        self.license_expression = Some(expression.to_string());
+                        self.license = None;
+                    }
+                    License::Table { file, text } => {
+                        // The legacy table can't be combined with a license expression, so
+                        // the expression from Cargo.toml becomes a free-form license again
+                        if let Some(license_expression) = self.license_expression.take() {
+                            self.license = Some(license_expression);
                         }
-                        (Some(license_path), None) => {
-                            let license_path = pyproject_dir.join(license_path);
-                            self.license_files.push(license_path);
+                        match (file, text) {
+                            (Some(_), Some(_)) => {
+                                bail!("file and text fields of 'project.license' are mutually-exclusive, only one of them should be specified");
+                            }
+                            (Some(license_path), None) => {
+                                let license_path = pyproject_dir.join(license_path);
+                                self.license_files.push(LicenseFile::new(license_path)?);
+                            }
+                            (None, Some(license_text)) => self.license = Some(license_text.clone()),
+                            (None, None) => {}
                         }
-                        (None, Some(license_text)) => self.license = Some(license_text.clone()),
-                        (None, None) => {}
-                    },
+                    }
                 }
             }
 
-            // Until PEP 639 is approved with metadata 2.3, we can assume a
-            // dynamic license-files (also awaiting full 2.2 metadata support)
-            // We're already emitting the License-Files metadata without issue.
-            // license-files.globs = ["LICEN[CS]E*", "COPYING*", "NOTICE*", "AUTHORS*"]
-            let license_include_targets = ["LICEN[CS]E*", "COPYING*", "NOTICE*", "AUTHORS*"];
-            let escaped_manifest_string = glob::Pattern::escape(pyproject_dir.to_str().unwrap());
-            let escaped_manifest_path = Path::new(&escaped_manifest_string);
-            for pattern in license_include_targets.iter() {
-                for license_path in
-                    glob::glob(&escaped_manifest_path.join(pattern).to_string_lossy())?
-                        .filter_map(Result::ok)
-                {
-                    if !license_path.is_file() {
-                        continue;
-                    }
-                    // if the pyproject.toml specified the license file,
-                    // then we won't list it as automatically included
-                    if !self.license_files.contains(&license_path) {
-                        eprintln!("📦 Including license file \"{}\"", license_path.display());
-                        self.license_files.push(license_path);
+            match &project.license_files {
+                Some(LicenseFiles::Globs(globs)) => self.add_license_files(
+                    pyproject_dir,
+                    globs.as_deref().unwrap_or_default(),
+                    true,
+                )?,
+                Some(LicenseFiles::Paths(paths)) => {
+                    let patterns: Vec<String> = paths
+                        .iter()
+                        .flatten()
+                        .map(|path| glob::Pattern::escape(path))
+                        .collect();
+                    self.add_license_files(pyproject_dir, &patterns, true)?
+                }
+                None => {
+                    let default_globs = LicenseFiles::default();
+                    if let LicenseFiles::Globs(Some(globs)) = &default_globs {
+                        self.add_license_files(pyproject_dir, globs, false)?;
                     }
                 }
             }
@@ -282,6 +389,28 @@ impl Metadata23 {
                 self.classifiers = classifiers.clone();
             }
 
+            // https://peps.python.org/pep-0639/#deprecate-license-classifiers
+            if let Some(license_expression) = &self.license_expression {
+                let license_classifiers: Vec<&str> = self
+                    .classifiers
+                    .iter()
+                    .filter(|classifier| classifier.starts_with("License ::"))
+                    .map(String::as_str)
+                    .collect();
+                if !license_classifiers.is_empty() {
+                    if matches!(project.license, Some(License::String(_))) {
+                        bail!(
+                            "The license classifiers {} can't be combined with the license expression `{}` in `project.license`, please remove them",
+                            license_classifiers.join(", "),
+                            license_expression
+                        );
+                    }
+                    // Keep projects that declare their license with classifiers and in
+                    // Cargo.toml working, using the pre PEP 639 `License` field
+                    self.license = self.license_expression.take();
+                }
+            }
+
             if let Some(urls) = &project.urls {
                 self.project_url = urls.clone();
             }
@@ -386,8 +515,21 @@ impl Metadata23 {
         if let Some(repository) = package.repository.as_ref() {
             project_url.insert("Source Code".to_string(), repository.clone());
         }
+        // Cargo still accepts the deprecated `MIT/Apache-2.0` syntax
+        let (license, license_expression) = match &package.license {
+            Some(license) => match LicenseExpression::parse(&license.replace('/', " OR ")) {
+                Ok(expression) => (None, Some(expression.to_string())),
+                Err(err) => {
+                    eprintln!(
+                        "⚠️  Warning: Using the license from Cargo.toml as free-form `License` instead of `License-Expression`: {err:#}"
+                    );
+                    (Some(license.clone()), None)
+                }
+            },
+            None => (None, None),
+        };
         let license_files = if let Some(license_file) = package.license_file.as_ref() {
-            vec![manifest_path.as_ref().join(license_file)]
+            vec![LicenseFile::new(manifest_path.as_ref().join(license_file))?]
         } else {
             Vec::new()
         };
@@ -421,7 +563,8 @@ impl Metadata23 {
                 Some(authors)
             },
             author_email,
-            license: package.license.clone(),
+            license,
+            license_expression,
             license_files,
             project_url,
             ..Metadata23::new(name, version)
@@ -429,12 +572,22 @@ impl Metadata23 {
         Ok(metadata)
     }
 
+    /// The version of the written metadata, which is only raised to 2.4 when one of the fields
+    /// added in 2.4, `License-Expression` and `License-File`, is used
+    pub fn get_metadata_version(&self) -> &str {
+        if self.license_expression.is_some() || !self.license_files.is_empty() {
+            "2.4"
+        } else {
+            &self.metadata_version
+        }
+    }
+
     /// Formats the metadata into a list where keys with multiple values
     /// become multiple single-valued key-value pairs. This format is needed for the pypi
     /// uploader and for the METADATA file inside wheels
     pub fn to_vec(&self) -> Vec<(String, String)> {
         let mut fields = vec![
-            ("Metadata-Version", self.metadata_version.clone()),
+            ("Metadata-Version", self.get_metadata_version().to_string()),
             ("Name", self.name.clone()),
             ("Version", self.version.to_string()),
         ];
@@ -464,7 +617,7 @@ impl Metadata23 {
         let license_files: Vec<String> = self
             .license_files
             .iter()
-            .map(|path| path.file_name().unwrap().to_str().unwrap().to_string())
+            .map(|license_file| license_file.path.to_string_lossy().replace('\\', "/"))
             .collect();
         add_vec("License-File", &license_files);
 
@@ -483,6 +636,7 @@ impl Metadata23 {
         add_option("Maintainer", &self.maintainer);
         add_option("Maintainer-email", &self.maintainer_email);
         add_option("License", &self.license.as_deref().map(fold_header));
+        add_option("License-Expression", &self.license_expression);
         add_option(
             "Requires-Python",
             &self
@@ -536,6 +690,118 @@ impl Metadata23 {
         Ok(out)
     }
 
+    /// Parses the contents of a METADATA or PKG-INFO file, the inverse of
+    /// [Metadata23::to_file_contents]
+    ///
+    /// Scripts and entry points aren't part of the core metadata, they are read from
+    /// `entry_points.txt` separately.
+    pub fn from_file_contents(contents: &str) -> Result<Metadata23> {
+        let (headers, body) = match contents.split_once("\n\n") {
+            Some((headers, body)) => (headers, Some(body)),
+            None => (contents, None),
+        };
+
+        // Unfold continuation lines, see `fold_header`
+        let mut fields: Vec<(String, String)> = Vec::new();
+        for line in headers.lines() {
+            if line.starts_with([' ', '\t']) {
+                let (_, value) = fields
+                    .last_mut()
+                    .with_context(|| format!("Unexpected continuation line: {line:?}"))?;
+                value.push('\n');
+                value.push_str(&line[1..]);
+            } else if let Some((key, value)) = line.split_once(':') {
+                fields.push((key.trim().to_ascii_lowercase(), value.trim().to_string()));
+            } else if !line.trim().is_empty() {
+                bail!("Invalid metadata line: {line:?}");
+            }
+        }
+
+        let get_field = |name: &str| {
+            fields
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+        };
+        let metadata_version = get_field("metadata-version").context("Missing Metadata-Version")?;
+        let name = get_field("name").context("Missing Name")?;
+        let version = get_field("version").context("Missing Version")?;
+        let version = Version::from_str(&version)
+            .map_err(|err| format_err!("Invalid Version {version:?}: {err}"))?;
+
+        let mut metadata = Metadata23 {
+            metadata_version,
+            ..Metadata23::new(name, version)
+        };
+        for (key, value) in fields {
+            match key.as_str() {
+                "metadata-version" | "name" | "version" => {}
+                "platform" => metadata.platform.push(value),
+                "supported-platform" => metadata.supported_platform.push(value),
+                "summary" => metadata.summary = Some(value),
+                "description" => metadata.description = Some(value),
+                "description-content-type" => metadata.description_content_type = Some(value),
+                "keywords" => metadata.keywords = Some(value),
+                "home-page" => metadata.home_page = Some(value),
+                "download-url" => metadata.download_url = Some(value),
+                "author" => metadata.author = Some(value),
+                "author-email" => metadata.author_email = Some(value),
+                "maintainer" => metadata.maintainer = Some(value),
+                "maintainer-email" => metadata.maintainer_email = Some(value),
+                "license" => metadata.license = Some(value),
+                "license-expression" => metadata.license_expression = Some(value),
+                "license-file" => metadata.license_files.push(LicenseFile {
+                    source: PathBuf::from(&value),
+                    path: PathBuf::from(value),
+                }),
+                "classifier" => metadata.classifiers.push(value),
+                "requires-dist" => metadata.requires_dist.push(
+                    Requirement::from_str(&value)
+                        .with_context(|| format!("Invalid Requires-Dist {value:?}"))?,
+                ),
+                "provides-dist" => metadata.provides_dist.push(value),
+                "obsoletes-dist" => metadata.obsoletes_dist.push(value),
+                "requires-python" => {
+                    metadata.requires_python =
+                        Some(VersionSpecifiers::from_str(&value).map_err(|err| {
+                            format_err!("Invalid Requires-Python {value:?}: {err}")
+                        })?)
+                }
+                "requires-external" => metadata.requires_external.push(value),
+                "project-url" => {
+                    let (label, url) = value
+                        .split_once(',')
+                        .with_context(|| format!("Invalid Project-URL {value:?}"))?;
+                    metadata
+                        .project_url
+                        .insert(label.trim().to_string(), url.trim().to_string());
+                }
+                "provides-extra" => metadata.provides_extra.push(value),
+                // Fields we don't model, e.g. `Dynamic`
+                _ => {}
+            }
+        }
+
+        if let Some(body) = body {
+            // `to_file_contents` terminates the body with a newline
+            let body = body.strip_suffix('\n').unwrap_or(body);
+            if !body.is_empty() {
+                metadata.description = Some(body.to_string());
+            }
+        }
+
+        Ok(metadata)
+    }
+
+    /// Appends a local version label such as `cpu` to the version, after any local version the
+    /// version already has, e.g. `1.0+cpu` or `1.1.dev3+g1a2b3c4.cpu`
+    pub fn add_local_version(&mut self, label: &str) -> Result<()> {
+        let mut local = self.version.local().to_vec();
+        local.extend(parse_local_version(label)?);
+        self.version = self.version.clone().with_local(local);
+        Ok(())
+    }
+
     /// Returns the distribution name according to PEP 427, Section "Escaping
     /// and Unicode"
     pub fn get_distribution_escaped(&self) -> String {
@@ -698,6 +964,257 @@ mod test {
         assert_metadata_from_cargo_toml(readme, cargo_toml, expected);
     }
 
+    #[test]
+    fn test_metadata_from_cargo_toml_license_expression() {
+        let cargo_toml = indoc!(
+            r#"
+            [package]
+            name = "info-project"
+            version = "0.1.0"
+            license = "MIT/Apache-2.0"
+
+            [lib]
+            crate-type = ["cdylib"]
+            name = "pyo3_pure"
+        "#
+        );
+
+        let expected = expect![[r#"
+            Metadata-Version: 2.4
+            Name: info-project
+            Version: 0.1.0
+            License-Expression: MIT OR Apache-2.0
+            Description-Content-Type: text/markdown; charset=UTF-8; variant=GFM
+
+            # Readme
+
+        "#]];
+
+        assert_metadata_from_cargo_toml("# Readme\n", cargo_toml, expected);
+    }
+
+    fn merge_pyproject_toml_in(
+        project_dir: &Path,
+        pyproject: &str,
+    ) -> Result<Metadata23, anyhow::Error> {
+        fs::write(project_dir.join("pyproject.toml"), pyproject).unwrap();
+        let pyproject_toml = PyProjectToml::new(project_dir.join("pyproject.toml")).unwrap();
+        let mut metadata = Metadata23::new("spam".to_string(), Version::new([1]));
+        metadata.license_expression = Some("MIT".to_string());
+        metadata.merge_pyproject_toml(project_dir, &pyproject_toml)?;
+        Ok(metadata)
+    }
+
+    #[test]
+    fn test_merge_pyproject_toml_pep639() {
+        let project_dir = tempfile::tempdir().unwrap();
+        let project_dir = project_dir.path();
+        fs::create_dir(project_dir.join("licenses")).unwrap();
+        fs::write(project_dir.join("LICENSE"), "").unwrap();
+        fs::write(project_dir.join("licenses").join("vendored.txt"), "").unwrap();
+        fs::write(project_dir.join("licenses").join("other.md"), "").unwrap();
+
+        let metadata = merge_pyproject_toml_in(
+            project_dir,
+            indoc!(
+                r#"
+                [build-system]
+                requires = ["maturin"]
+                build-backend = "maturin"
+
+                [project]
+                name = "spam"
+                license = "apache-2.0 or mit"
+                license-files = ["LICEN[CS]E*", "licenses/*.txt"]
+                "#
+            ),
+        )
+        .unwrap();
+        assert_eq!(
+            metadata.license_expression.as_deref(),
+            Some("Apache-2.0 OR MIT")
+        );
+        assert_eq!(metadata.license, None);
+        assert_eq!(
+            metadata
+                .license_files
+                .iter()
+                .map(|license_file| license_file.path.clone())
+                .collect::<Vec<_>>(),
+            [
+                PathBuf::from("LICENSE"),
+                Path::new("licenses").join("vendored.txt")
+            ]
+        );
+        let contents = metadata.to_file_contents().unwrap();
+        assert!(contents.starts_with("Metadata-Version: 2.4\n"));
+        assert!(contents.contains("License-File: licenses/vendored.txt\n"));
+
+        let err = merge_pyproject_toml_in(
+            project_dir,
+            indoc!(
+                r#"
+                [build-system]
+                requires = ["maturin"]
+                build-backend = "maturin"
+
+                [project]
+                name = "spam"
+                license = "MIT OR Apache"
+                "#
+            ),
+        )
+        .unwrap_err();
+        assert!(format!("{err:#}").contains("unknown license `Apache`"));
+
+        let err = merge_pyproject_toml_in(
+            project_dir,
+            indoc!(
+                r#"
+                [build-system]
+                requires = ["maturin"]
+                build-backend = "maturin"
+
+                [project]
+                name = "spam"
+                license-files = ["COPYING*"]
+                "#
+            ),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`project.license-files` pattern `COPYING*` doesn't match any files"
+        );
+
+        let err = merge_pyproject_toml_in(
+            project_dir,
+            indoc!(
+                r#"
+                [build-system]
+                requires = ["maturin"]
+                build-backend = "maturin"
+
+                [project]
+                name = "spam"
+                license-files = ["../LICENSE"]
+                "#
+            ),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("must be relative paths"));
+    }
+
+    #[test]
+    fn test_merge_pyproject_toml_license_classifiers() {
+        let project_dir = tempfile::tempdir().unwrap();
+        let project_dir = project_dir.path();
+
+        // The license expression from Cargo.toml falls back to `License`
+        let metadata = merge_pyproject_toml_in(
+            project_dir,
+            indoc!(
+                r#"
+                [build-system]
+                requires = ["maturin"]
+                build-backend = "maturin"
+
+                [project]
+                name = "spam"
+                classifiers = ["License :: OSI Approved :: MIT License"]
+                "#
+            ),
+        )
+        .unwrap();
+        assert_eq!(metadata.license.as_deref(), Some("MIT"));
+        assert_eq!(metadata.license_expression, None);
+        assert!(metadata
+            .to_file_contents()
+            .unwrap()
+            .starts_with("Metadata-Version: 2.3\n"));
+
+        let err = merge_pyproject_toml_in(
+            project_dir,
+            indoc!(
+                r#"
+                [build-system]
+                requires = ["maturin"]
+                build-backend = "maturin"
+
+                [project]
+                name = "spam"
+                license = "MIT"
+                classifiers = ["License :: OSI Approved :: MIT License"]
+                "#
+            ),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("can't be combined"));
+    }
+
+    #[test]
+    fn test_metadata_from_file_contents_roundtrip() {
+        let manifest_dir = PathBuf::from("test-crates").join("pyo3-pure");
+        let cargo_metadata = MetadataCommand::new()
+            .manifest_path(manifest_dir.join("Cargo.toml"))
+            .exec()
+            .unwrap();
+        let mut metadata = Metadata23::from_cargo_toml(&manifest_dir, &cargo_metadata).unwrap();
+        let pyproject_toml = PyProjectToml::new(manifest_dir.join("pyproject.toml")).unwrap();
+        metadata
+            .merge_pyproject_toml(&manifest_dir, &pyproject_toml)
+            .unwrap();
+        metadata.project_url.insert(
+            "Bug Tracker".to_string(),
+            "https://example.org/issues".to_string(),
+        );
+
+        let parsed = Metadata23::from_file_contents(&metadata.to_file_contents().unwrap()).unwrap();
+        assert_eq!(parsed.name, metadata.name);
+        assert_eq!(parsed.version, metadata.version);
+        assert_eq!(parsed.summary, metadata.summary);
+        assert_eq!(parsed.description, metadata.description);
+        assert_eq!(parsed.classifiers, metadata.classifiers);
+        assert_eq!(parsed.requires_dist, metadata.requires_dist);
+        assert_eq!(parsed.provides_extra, metadata.provides_extra);
+        assert_eq!(parsed.project_url, metadata.project_url);
+        assert_eq!(parsed.license, metadata.license);
+        assert_eq!(parsed.license_expression, metadata.license_expression);
+        assert_eq!(
+            parsed
+                .license_files
+                .iter()
+                .map(|license_file| license_file.path.clone())
+                .collect::<Vec<_>>(),
+            vec![PathBuf::from("LICENSE")],
+            "License-File only contains the file name"
+        );
+    }
+
+    #[test]
+    fn test_metadata_from_file_contents_folded() {
+        let contents = indoc!(
+            "
+            Metadata-Version: 2.3
+            Name: folded
+            Version: 1.0.0
+            License: first line
+            \tsecond line
+            Requires-Python: >=3.8
+            "
+        );
+        let metadata = Metadata23::from_file_contents(contents).unwrap();
+        assert_eq!(metadata.license.as_deref(), Some("first line\nsecond line"));
+        assert_eq!(
+            metadata.requires_python.unwrap().to_string(),
+            ">=3.8".to_string()
+        );
+        assert!(metadata.description.is_none());
+
+        let err = Metadata23::from_file_contents("Name: no-version\n").unwrap_err();
+        assert_eq!(err.to_string(), "Missing Metadata-Version");
+    }
+
     #[test]
     fn test_path_to_content_type() {
         for (filename, expected) in &[
@@ -762,7 +1279,8 @@ mod test {
         assert_eq!(metadata.license.as_ref().unwrap(), "MIT");
 
         let license_file = &metadata.license_files[0];
-        assert_eq!(license_file.file_name().unwrap(), "LICENSE");
+        assert_eq!(license_file.source.file_name().unwrap(), "LICENSE");
+        assert_eq!(license_file.path, PathBuf::from("LICENSE"));
 
         let content = metadata.to_file_contents().unwrap();
         let pkginfo: Result<python_pkginfo::Metadata, _> = content.parse();
@@ -825,12 +1343,24 @@ mod test {
         assert_eq!(4, metadata.license_files.len());
 
         // Verify pyproject.toml license = {file = ...} worked
-        assert_eq!(metadata.license_files[0], manifest_dir.join("LICENCE.txt"));
+        assert_eq!(
+            metadata.license_files[0].source,
+            manifest_dir.join("LICENCE.txt")
+        );
 
         // Verify the default licenses were included
-        assert_eq!(metadata.license_files[1], manifest_dir.join("LICENSE"));
-        assert_eq!(metadata.license_files[2], manifest_dir.join("NOTICE.md"));
-        assert_eq!(metadata.license_files[3], manifest_dir.join("AUTHORS.txt"));
+        assert_eq!(
+            metadata.license_files[1].source,
+            manifest_dir.join("LICENSE")
+        );
+        assert_eq!(
+            metadata.license_files[2].source,
+            manifest_dir.join("NOTICE.md")
+        );
+        assert_eq!(
+            metadata.license_files[3].source,
+            manifest_dir.join("AUTHORS.txt")
+        );
     }
 
     #[test]
@@ -871,4 +1401,23 @@ mod test {
             assert_eq!(result, expected);
         }
     }
+
+    #[test]
+    fn test_add_local_version() {
+        let mut metadata = Metadata23::new("foo".to_string(), Version::from_str("1.0").unwrap());
+        metadata.add_local_version("CPU").unwrap();
+        assert_eq!(metadata.version.to_string(), "1.0+cpu");
+        assert_eq!(metadata.get_version_escaped(), "1.0+cpu");
+
+        let mut metadata = Metadata23::new(
+            "foo".to_string(),
+            Version::from_str("1.1.dev3+g1a2b3c4").unwrap(),
+        );
+        metadata.add_local_version("cu121-avx2").unwrap();
+        assert_eq!(metadata.version.to_string(), "1.1.dev3+g1a2b3c4.cu121.avx2");
+
+        for label in ["", "cpu+cuda", "cpu..cuda", "-cpu", "cpu/cuda"] {
+            assert!(metadata.add_local_version(label).is_err(), "{label}");
+        }
+    }
 }
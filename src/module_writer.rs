@@ -8,15 +8,16 @@ use anyhow::{anyhow, bail, Context, Result};
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine;
 use flate2::write::GzEncoder;
-use flate2::Compression;
+use flate2::{Compression, GzBuilder};
 use fs_err as fs;
 use fs_err::File;
 use ignore::overrides::Override;
 use ignore::WalkBuilder;
 use indexmap::IndexMap;
 use normpath::PathExt as _;
+use serde::Serialize;
 use sha2::{Digest, Sha256};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
 use std::ffi::OsStr;
 use std::fmt::Write as _;
@@ -127,30 +128,28 @@ impl PathWriter {
 
     /// Writes the RECORD file after everything else has been written
     pub fn write_record(self, metadata23: &Metadata23) -> Result<()> {
-        let record_file = self
-            .base_path
-            .join(metadata23.get_dist_info_dir())
-            .join("RECORD");
-        let mut buffer = File::create(&record_file).context(format!(
-            "Failed to create a file at {}",
-            record_file.display()
-        ))?;
+        self.write_record_to(metadata23.get_dist_info_dir())
+    }
 
+    /// Writes the RECORD file into the given `.dist-info` directory relative to the base path,
+    /// listing all files written by this writer
+    pub fn write_record_to(self, dist_info_dir: impl AsRef<Path>) -> Result<()> {
+        let relative_record_file = dist_info_dir.as_ref().join("RECORD");
+        let record_file = self.base_path.join(&relative_record_file);
+        let mut buffer = String::new();
         for (filename, hash, len) in self.record {
-            buffer
-                .write_all(format!("{filename},sha256={hash},{len}\n").as_bytes())
-                .context(format!(
-                    "Failed to write to file at {}",
-                    record_file.display()
-                ))?;
+            writeln!(buffer, "{filename},sha256={hash},{len}")?;
         }
         // Write the record for the RECORD file itself
-        buffer
-            .write_all(format!("{},,\n", record_file.display()).as_bytes())
-            .context(format!(
-                "Failed to write to file at {}",
-                record_file.display()
-            ))?;
+        writeln!(
+            buffer,
+            "{},,",
+            relative_record_file.to_str().unwrap().replace('\\', "/")
+        )?;
+        fs::write(&record_file, buffer).context(format!(
+            "Failed to write to file at {}",
+            record_file.display()
+        ))?;
 
         Ok(())
     }
@@ -171,6 +170,9 @@ impl ModuleWriter for PathWriter {
         _permissions: u32,
     ) -> Result<()> {
         let path = self.base_path.join(&target);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
 
         // We only need to set the executable bit on unix
         let mut file = {
@@ -195,7 +197,7 @@ impl ModuleWriter for PathWriter {
 
         let hash = URL_SAFE_NO_PAD.encode(Sha256::digest(bytes));
         self.record.push((
-            target.as_ref().to_str().unwrap().to_owned(),
+            target.as_ref().to_str().unwrap().replace('\\', "/"),
             hash,
             bytes.len(),
         ));
@@ -204,6 +206,29 @@ impl ModuleWriter for PathWriter {
     }
 }
 
+/// The earliest timestamp a zip file can represent, 1980-01-01T00:00:00Z
+const ZIP_EPOCH: i64 = 315532800;
+
+/// Returns the value of the SOURCE_DATE_EPOCH environment variable, or in reproducible mode
+/// 1980-01-01 if it is unset
+pub(crate) fn source_date_epoch(reproducible: bool) -> Result<i64> {
+    match env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => Ok(epoch.parse()?),
+        Err(_) if reproducible => Ok(ZIP_EPOCH),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Reduces unix permissions to either `rw-r--r--` or `rwxr-xr-x`, so that the archive doesn't
+/// depend on the umask or the filesystem of the machine it was built on
+fn normalize_permissions(permissions: u32) -> u32 {
+    if permissions & 0o111 != 0 {
+        0o755
+    } else {
+        0o644
+    }
+}
+
 /// A glorified zip builder, mostly useful for writing the record file of a wheel
 pub struct WheelWriter {
     zip: ZipWriter<File>,
@@ -211,6 +236,10 @@ pub struct WheelWriter {
     record_file: PathBuf,
     wheel_path: PathBuf,
     excludes: Override,
+    files: HashSet<String>,
+    reproducible: bool,
+    /// In reproducible mode, files are collected here and written sorted in [WheelWriter::finish]
+    pending: Vec<(String, Vec<u8>, u32)>,
 }
 
 impl ModuleWriter for WheelWriter {
@@ -230,50 +259,42 @@ impl ModuleWriter for WheelWriter {
         }
         // The zip standard mandates using unix style paths
         let target = target.to_str().unwrap().replace('\\', "/");
-
-        // Unlike users which can use the develop subcommand, the tests have to go through
-        // packing a zip which pip than has to unpack. This makes this 2-3 times faster
-        let compression_method = if cfg!(feature = "faster-tests") {
-            zip::CompressionMethod::Stored
-        } else {
-            zip::CompressionMethod::Deflated
-        };
-
-        let mut options = zip::write::FileOptions::default()
-            .unix_permissions(permissions)
-            .compression_method(compression_method);
-        let mtime = self.mtime().ok();
-        if let Some(mtime) = mtime {
-            options = options.last_modified_time(mtime);
+        if !self.files.insert(target.clone()) {
+            // Ignore duplicate files
+            return Ok(());
         }
 
-        self.zip.start_file(target.clone(), options)?;
-        self.zip.write_all(bytes)?;
-
-        let hash = URL_SAFE_NO_PAD.encode(Sha256::digest(bytes));
-        self.record.push((target, hash, bytes.len()));
-
-        Ok(())
+        if self.reproducible {
+            self.pending
+                .push((target, bytes.to_vec(), normalize_permissions(permissions)));
+            return Ok(());
+        }
+        self.write_entry(target, bytes, permissions)
     }
 }
 
 impl WheelWriter {
     /// Create a new wheel file which can be subsequently expanded
     ///
-    /// Adds the .dist-info directory and the METADATA file in it
+    /// Adds the .dist-info directory and the METADATA file in it. The optional `build_tag` is
+    /// added to the filename and the WHEEL file. With `reproducible`, the entries are sorted,
+    /// their permissions are normalized and their timestamps are fixed, so that building the
+    /// same sources twice gives byte-identical wheels
     pub fn new(
         tag: &str,
         wheel_dir: &Path,
         metadata23: &Metadata23,
+        build_tag: Option<&str>,
         tags: &[String],
         excludes: Override,
+        reproducible: bool,
     ) -> Result<WheelWriter> {
-        let wheel_path = wheel_dir.join(format!(
-            "{}-{}-{}.whl",
-            metadata23.get_distribution_escaped(),
-            metadata23.get_version_escaped(),
-            tag
-        ));
+        let distribution = metadata23.get_distribution_escaped();
+        let version = metadata23.get_version_escaped();
+        let wheel_path = wheel_dir.join(match build_tag {
+            Some(build_tag) => format!("{distribution}-{version}-{build_tag}-{tag}.whl"),
+            None => format!("{distribution}-{version}-{tag}.whl"),
+        });
 
         let file = File::create(&wheel_path)?;
 
@@ -283,13 +304,43 @@ impl WheelWriter {
             record_file: metadata23.get_dist_info_dir().join("RECORD"),
             wheel_path,
             excludes,
+            files: HashSet::new(),
+            reproducible,
+            pending: Vec::new(),
         };
 
-        write_dist_info(&mut builder, metadata23, tags)?;
+        write_dist_info(&mut builder, metadata23, build_tag, tags)?;
 
         Ok(builder)
     }
 
+    /// Writes a single file to the zip and adds it to the record
+    fn write_entry(&mut self, target: String, bytes: &[u8], permissions: u32) -> Result<()> {
+        // Unlike users which can use the develop subcommand, the tests have to go through
+        // packing a zip which pip than has to unpack. This makes this 2-3 times faster
+        let compression_method = if cfg!(feature = "faster-tests") {
+            zip::CompressionMethod::Stored
+        } else {
+            zip::CompressionMethod::Deflated
+        };
+
+        let mut options = zip::write::FileOptions::default()
+            .unix_permissions(permissions)
+            .compression_method(compression_method);
+        let mtime = self.mtime().ok();
+        if let Some(mtime) = mtime {
+            options = options.last_modified_time(mtime);
+        }
+
+        self.zip.start_file(target.clone(), options)?;
+        self.zip.write_all(bytes)?;
+
+        let hash = URL_SAFE_NO_PAD.encode(Sha256::digest(bytes));
+        self.record.push((target, hash, bytes.len()));
+
+        Ok(())
+    }
+
     /// Add a pth file to wheel root for editable installs
     pub fn add_pth(
         &mut self,
@@ -319,6 +370,42 @@ impl WheelWriter {
         Ok(())
     }
 
+    /// Add an import hook for editable installs that maps exactly the python packages of the
+    /// project to the source tree, instead of adding the whole python source directory to
+    /// `sys.path` like [WheelWriter::add_pth]
+    ///
+    /// `extension` is the file name of the extension module that was copied next to the python
+    /// sources, which the hook maps explicitly so that it's always the one from this build.
+    pub fn add_editable_finder(
+        &mut self,
+        project_layout: &ProjectLayout,
+        metadata23: &Metadata23,
+        extension: Option<&str>,
+    ) -> Result<()> {
+        let mapping = editable_mapping(project_layout, extension)?;
+        if mapping.is_empty() {
+            return Ok(());
+        }
+        let module = format!(
+            "_maturin_editable_{}",
+            metadata23.get_distribution_escaped().replace('.', "_")
+        );
+        let source = format!(
+            "\"\"\"Editable install of {} by maturin, see PEP 660\"\"\"\n\n\
+            # The top level packages and the compiled extension of the project\n\
+            MAPPING = {}\n\n{EDITABLE_FINDER}",
+            metadata23.name,
+            serde_json::to_string_pretty(&mapping)?,
+        );
+        debug!("Adding {module}.py and {module}.pth");
+        self.add_bytes(format!("{module}.py"), source.as_bytes())?;
+        self.add_bytes(
+            format!("{module}.pth"),
+            format!("import {module}; {module}.install()\n").as_bytes(),
+        )?;
+        Ok(())
+    }
+
     /// Returns `true` if the given path should be excluded
     fn exclude(&self, path: impl AsRef<Path>) -> bool {
         self.excludes.matched(path.as_ref(), false).is_whitelist()
@@ -327,13 +414,8 @@ impl WheelWriter {
     /// Returns a DateTime representing the value SOURCE_DATE_EPOCH environment variable
     /// Note that the earliest timestamp a zip file can represent is 1980-01-01
     fn mtime(&self) -> Result<DateTime> {
-        let epoch: i64 = env::var("SOURCE_DATE_EPOCH")?.parse()?;
+        let epoch = source_date_epoch(self.reproducible)?.max(ZIP_EPOCH);
         let dt = time::OffsetDateTime::from_unix_timestamp(epoch)?;
-        let min_dt = time::Date::from_calendar_date(1980, time::Month::January, 1)
-            .unwrap()
-            .midnight()
-            .assume_offset(time::UtcOffset::UTC);
-        let dt = dt.max(min_dt);
 
         let dt = DateTime::try_from(dt).map_err(|_| anyhow!("Failed to build zip DateTime"))?;
         Ok(dt)
@@ -341,6 +423,20 @@ impl WheelWriter {
 
     /// Creates the record file and finishes the zip
     pub fn finish(mut self) -> Result<PathBuf, io::Error> {
+        if self.reproducible {
+            let dist_info_dir = self.record_file.parent().unwrap().to_str().unwrap();
+            let dist_info_dir = format!("{}/", dist_info_dir.replace('\\', "/"));
+            let mut pending = std::mem::take(&mut self.pending);
+            // Sorted by path, but with the .dist-info directory last as recommended by PEP 427
+            pending.sort_by(|(a, _, _), (b, _, _)| {
+                (a.starts_with(&dist_info_dir), a).cmp(&(b.starts_with(&dist_info_dir), b))
+            });
+            for (target, bytes, permissions) in pending {
+                self.write_entry(target, &bytes, permissions)
+                    .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            }
+        }
+
         let compression_method = if cfg!(feature = "faster-tests") {
             zip::CompressionMethod::Stored
         } else {
@@ -375,6 +471,9 @@ pub struct SDistWriter {
     path: PathBuf,
     files: HashSet<PathBuf>,
     excludes: Override,
+    reproducible: bool,
+    /// In reproducible mode, files are collected here and written sorted in [SDistWriter::finish]
+    pending: Vec<(PathBuf, Vec<u8>, u32)>,
 }
 
 impl ModuleWriter for SDistWriter {
@@ -398,17 +497,25 @@ impl ModuleWriter for SDistWriter {
             return Ok(());
         }
 
-        let mut header = tar::Header::new_gnu();
-        header.set_size(bytes.len() as u64);
-        header.set_mode(permissions);
-        header.set_cksum();
-        self.tar
-            .append_data(&mut header, target, bytes)
-            .context(format!(
-                "Failed to add {} bytes to sdist as {}",
-                bytes.len(),
-                target.display()
-            ))?;
+        if self.reproducible {
+            self.pending.push((
+                target.to_path_buf(),
+                bytes.to_vec(),
+                normalize_permissions(permissions),
+            ));
+        } else {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(bytes.len() as u64);
+            header.set_mode(permissions);
+            header.set_cksum();
+            self.tar
+                .append_data(&mut header, target, bytes)
+                .context(format!(
+                    "Failed to add {} bytes to sdist as {}",
+                    bytes.len(),
+                    target.display()
+                ))?;
+        }
         self.files.insert(target.to_path_buf());
         Ok(())
     }
@@ -425,6 +532,16 @@ impl ModuleWriter for SDistWriter {
         }
         debug!("Adding {} from {}", target.display(), source.display());
 
+        if self.reproducible {
+            // Don't copy the owner and the timestamps from the file system
+            #[cfg(unix)]
+            let mode = source.metadata()?.permissions().mode();
+            #[cfg(not(unix))]
+            let mode = 0o644;
+            let bytes = fs::read(source)?;
+            return self.add_bytes_with_permissions(target, &bytes, mode);
+        }
+
         self.tar
             .append_path_with_name(source, target)
             .context(format!(
@@ -439,10 +556,14 @@ impl ModuleWriter for SDistWriter {
 
 impl SDistWriter {
     /// Create a source distribution .tar.gz which can be subsequently expanded
+    ///
+    /// With `reproducible`, the entries are sorted and their owner, permissions and timestamps
+    /// are normalized, so that building the same sources twice gives byte-identical archives
     pub fn new(
         wheel_dir: impl AsRef<Path>,
         metadata23: &Metadata23,
         excludes: Override,
+        reproducible: bool,
     ) -> Result<Self, io::Error> {
         let path = wheel_dir
             .as_ref()
@@ -454,7 +575,10 @@ impl SDistWriter {
             ))
             .into_path_buf();
 
-        let enc = GzEncoder::new(Vec::new(), Compression::default());
+        // No file name and a zero timestamp in the gzip header, so it doesn't change between builds
+        let enc = GzBuilder::new()
+            .mtime(0)
+            .write(Vec::new(), Compression::default());
         let tar = tar::Builder::new(enc);
 
         Ok(Self {
@@ -462,6 +586,8 @@ impl SDistWriter {
             path,
             files: HashSet::new(),
             excludes,
+            reproducible,
+            pending: Vec::new(),
         })
     }
 
@@ -471,14 +597,33 @@ impl SDistWriter {
     }
 
     /// Finished the .tar.gz archive
-    pub fn finish(self) -> Result<PathBuf, io::Error> {
+    pub fn finish(mut self) -> Result<PathBuf, io::Error> {
+        if self.reproducible {
+            let mtime = source_date_epoch(true)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+            let mut pending = std::mem::take(&mut self.pending);
+            pending.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+            for (target, bytes, permissions) in pending {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(bytes.len() as u64);
+                header.set_mode(permissions);
+                header.set_mtime(mtime.max(0) as u64);
+                header.set_uid(0);
+                header.set_gid(0);
+                header.set_username("")?;
+                header.set_groupname("")?;
+                header.set_cksum();
+                self.tar
+                    .append_data(&mut header, target, bytes.as_slice())?;
+            }
+        }
         let archive = self.tar.into_inner()?;
         fs::write(&self.path, archive.finish()?)?;
         Ok(self.path)
     }
 }
 
-fn wheel_file(tags: &[String]) -> Result<String> {
+fn wheel_file(build_tag: Option<&str>, tags: &[String]) -> Result<String> {
     let mut wheel_file = format!(
         "Wheel-Version: 1.0
 Generator: {name} ({version})
@@ -488,6 +633,10 @@ Root-Is-Purelib: false
         version = env!("CARGO_PKG_VERSION"),
     );
 
+    if let Some(build_tag) = build_tag {
+        writeln!(wheel_file, "Build: {build_tag}")?;
+    }
+
     for tag in tags {
         writeln!(wheel_file, "Tag: {tag}")?;
     }
@@ -495,6 +644,242 @@ Root-Is-Purelib: false
     Ok(wheel_file)
 }
 
+/// The parsed contents of the WHEEL file written by [wheel_file]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WheelFile {
+    /// Version of the wheel specification, e.g. `1.0`
+    pub wheel_version: String,
+    /// Name and version of the tool that generated the wheel
+    pub generator: Option<String>,
+    /// Whether the wheel should be installed into purelib
+    pub root_is_purelib: bool,
+    /// Optional build number
+    pub build: Option<String>,
+    /// The expanded compatibility tags, e.g. `cp38-abi3-manylinux_2_17_x86_64`
+    pub tags: Vec<String>,
+}
+
+impl WheelFile {
+    /// Parses the WHEEL file of a `.dist-info` directory
+    pub fn parse(contents: &str) -> Result<Self> {
+        let mut wheel_version = None;
+        let mut generator = None;
+        let mut root_is_purelib = None;
+        let mut build = None;
+        let mut tags = Vec::new();
+        for line in contents.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once(':')
+                .with_context(|| format!("Invalid line in WHEEL file: {line:?}"))?;
+            let value = value.trim().to_string();
+            match key.trim() {
+                "Wheel-Version" => wheel_version = Some(value),
+                "Generator" => generator = Some(value),
+                "Root-Is-Purelib" => root_is_purelib = Some(value == "true"),
+                "Build" => build = Some(value),
+                "Tag" => tags.push(value),
+                _ => {}
+            }
+        }
+        Ok(Self {
+            wheel_version: wheel_version.context("WHEEL file is missing Wheel-Version")?,
+            generator,
+            root_is_purelib: root_is_purelib.context("WHEEL file is missing Root-Is-Purelib")?,
+            build,
+            tags,
+        })
+    }
+}
+
+/// The components of a wheel filename, `{distribution}-{version}(-{build tag})?-{python tag}-{abi tag}-{platform tag}.whl`
+///
+/// See https://packaging.python.org/en/latest/specifications/binary-distribution-format/#file-name-convention
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WheelFilename {
+    /// The escaped distribution name
+    pub distribution: String,
+    /// The escaped version
+    pub version: String,
+    /// Optional build number
+    pub build_tag: Option<String>,
+    /// Python tag(s), e.g. `cp38` or `py2.py3`
+    pub python_tag: String,
+    /// ABI tag(s), e.g. `abi3`
+    pub abi_tag: String,
+    /// Platform tag(s), e.g. `manylinux_2_17_x86_64.manylinux2014_x86_64`
+    pub platform_tag: String,
+}
+
+impl WheelFilename {
+    /// Parses the file name of a wheel, without any leading directories
+    pub fn parse(filename: &str) -> Result<Self> {
+        let stem = filename
+            .strip_suffix(".whl")
+            .with_context(|| format!("{filename} doesn't end with .whl"))?;
+        let parts: Vec<&str> = stem.split('-').collect();
+        let (distribution, version, build_tag, python_tag, abi_tag, platform_tag) =
+            match parts.as_slice() {
+                [distribution, version, python, abi, platform] => {
+                    (distribution, version, None, python, abi, platform)
+                }
+                [distribution, version, build, python, abi, platform] => {
+                    (distribution, version, Some(build), python, abi, platform)
+                }
+                _ => bail!(
+                    "Invalid wheel filename {filename}, expected \
+                    {{distribution}}-{{version}}(-{{build tag}})?-{{python tag}}-{{abi tag}}-{{platform tag}}.whl"
+                ),
+            };
+        if let Some(build_tag) = build_tag {
+            validate_build_tag(build_tag).with_context(|| format!("Invalid {filename}"))?;
+        }
+        Ok(Self {
+            distribution: distribution.to_string(),
+            version: version.to_string(),
+            build_tag: build_tag.map(|tag| tag.to_string()),
+            python_tag: python_tag.to_string(),
+            abi_tag: abi_tag.to_string(),
+            platform_tag: platform_tag.to_string(),
+        })
+    }
+
+    /// The compressed tag set, e.g. `cp38-abi3-manylinux_2_17_x86_64.manylinux2014_x86_64`
+    pub fn tag(&self) -> String {
+        format!("{}-{}-{}", self.python_tag, self.abi_tag, self.platform_tag)
+    }
+
+    /// Expands the compressed tag set into the individual tags
+    pub fn expanded_tags(&self) -> Vec<String> {
+        expand_tag(&self.tag())
+    }
+}
+
+impl std::fmt::Display for WheelFilename {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.distribution, self.version)?;
+        if let Some(build_tag) = &self.build_tag {
+            write!(f, "-{build_tag}")?;
+        }
+        write!(f, "-{}.whl", self.tag())
+    }
+}
+
+/// Checks that a build tag starts with a digit and only contains alphanumeric characters, `_`
+/// and `.`, so that it can be put into a wheel filename
+///
+/// https://packaging.python.org/en/latest/specifications/binary-distribution-format/#file-name-convention
+pub fn validate_build_tag(build_tag: &str) -> Result<()> {
+    if !build_tag.starts_with(|c: char| c.is_ascii_digit()) {
+        bail!("Invalid build tag `{build_tag}`, it must start with a digit");
+    }
+    if !build_tag
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+    {
+        bail!("Invalid build tag `{build_tag}`, it may only contain letters, digits, `_` and `.`");
+    }
+    Ok(())
+}
+
+/// Expands a compressed tag set such as `py2.py3-none-any` into `py2-none-any` and `py3-none-any`
+pub fn expand_tag(tag: &str) -> Vec<String> {
+    let mut parts = tag.splitn(3, '-');
+    let (Some(python), Some(abi), Some(platform)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return vec![tag.to_string()];
+    };
+    let mut tags = Vec::new();
+    for python in python.split('.') {
+        for abi in abi.split('.') {
+            for platform in platform.split('.') {
+                tags.push(format!("{python}-{abi}-{platform}"));
+            }
+        }
+    }
+    tags
+}
+
+/// A single line of a RECORD file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RecordEntry {
+    /// Path relative to the wheel root, always with forward slashes
+    pub path: String,
+    /// The hash in `<algorithm>=<urlsafe base64 digest>` format, empty for RECORD itself
+    pub hash: Option<String>,
+    /// File size in bytes, empty for RECORD itself
+    pub size: Option<u64>,
+}
+
+/// Parses a RECORD file as written by [WheelWriter::finish]
+///
+/// RECORD is a csv file, so paths containing a comma are quoted
+pub fn parse_record(contents: &str) -> Result<Vec<RecordEntry>> {
+    let mut entries = Vec::new();
+    for line in contents.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        // The hash and the size never contain commas, so we can split from the right
+        let mut parts = line.rsplitn(3, ',');
+        let (size, hash, path) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(size), Some(hash), Some(path)) => (size, hash, path),
+            _ => bail!("Invalid line in RECORD file: {line:?}"),
+        };
+        let path = match path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) {
+            Some(quoted) => quoted.replace("\"\"", "\""),
+            None => path.to_string(),
+        };
+        let size = if size.is_empty() {
+            None
+        } else {
+            Some(
+                size.parse()
+                    .with_context(|| format!("Invalid size in RECORD line: {line:?}"))?,
+            )
+        };
+        entries.push(RecordEntry {
+            path,
+            hash: (!hash.is_empty()).then(|| hash.to_string()),
+            size,
+        });
+    }
+    Ok(entries)
+}
+
+/// Parses an `entry_points.txt` as written by [write_dist_info] into its sections
+///
+/// https://packaging.python.org/specifications/entry-points/
+pub fn parse_entry_points(contents: &str) -> Result<IndexMap<String, IndexMap<String, String>>> {
+    let mut entry_points: IndexMap<String, IndexMap<String, String>> = IndexMap::new();
+    let mut section = None;
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = Some(name.trim().to_string());
+            entry_points.entry(name.trim().to_string()).or_default();
+            continue;
+        }
+        let (name, value) = line
+            .split_once('=')
+            .with_context(|| format!("Invalid line in entry_points.txt: {line:?}"))?;
+        let section = section
+            .as_ref()
+            .with_context(|| format!("Entry point outside of a section: {line:?}"))?;
+        entry_points
+            .get_mut(section)
+            .unwrap()
+            .insert(name.trim().to_string(), value.trim().to_string());
+    }
+    Ok(entry_points)
+}
+
 /// https://packaging.python.org/specifications/entry-points/
 fn entry_points_txt(
     entry_type: &str,
@@ -508,7 +893,7 @@ fn entry_points_txt(
 }
 
 /// Glue code that exposes `lib`.
-fn cffi_init_file(extension_name: &str) -> String {
+pub(crate) fn cffi_init_file(extension_name: &str) -> String {
     format!(
         r#"__all__ = ["lib", "ffi"]
 
@@ -718,23 +1103,7 @@ pub fn write_bindings_module(
     pyproject_toml: Option<&PyProjectToml>,
 ) -> Result<()> {
     let ext_name = &project_layout.extension_name;
-    let so_filename = if is_abi3 {
-        if target.is_unix() {
-            format!("{ext_name}.abi3.so")
-        } else {
-            match python_interpreter {
-                Some(python_interpreter) if python_interpreter.is_windows_debug() => {
-                    format!("{ext_name}_d.pyd")
-                }
-                // Apparently there is no tag for abi3 on windows
-                _ => format!("{ext_name}.pyd"),
-            }
-        }
-    } else {
-        let python_interpreter =
-            python_interpreter.expect("A python interpreter is required for non-abi3 build");
-        python_interpreter.get_library_name(ext_name)
-    };
+    let so_filename = bindings_module_filename(ext_name, python_interpreter, is_abi3, target);
 
     if !editable {
         write_python_part(writer, project_layout, pyproject_toml)
@@ -788,6 +1157,92 @@ if hasattr({ext_name}, "__all__"):
     Ok(())
 }
 
+/// The file name of a pyo3 or rust-cpython extension module, e.g.
+/// `ext.cpython-312-x86_64-linux-gnu.so` or `ext.abi3.so`
+pub fn bindings_module_filename(
+    ext_name: &str,
+    python_interpreter: Option<&PythonInterpreter>,
+    is_abi3: bool,
+    target: &Target,
+) -> String {
+    if is_abi3 {
+        if target.is_unix() {
+            format!("{ext_name}.abi3.so")
+        } else {
+            match python_interpreter {
+                Some(python_interpreter) if python_interpreter.is_windows_debug() => {
+                    format!("{ext_name}_d.pyd")
+                }
+                // Apparently there is no tag for abi3 on windows
+                _ => format!("{ext_name}.pyd"),
+            }
+        }
+    } else {
+        let python_interpreter =
+            python_interpreter.expect("A python interpreter is required for non-abi3 build");
+        python_interpreter.get_library_name(ext_name)
+    }
+}
+
+/// The import hook of [WheelWriter::add_editable_finder], which gets the `MAPPING` prepended
+const EDITABLE_FINDER: &str = include_str!("editable_finder.py");
+
+/// Maps the fully qualified module names that an editable install provides to their location
+/// in the source tree: the python module, the additional python packages and the in-place
+/// extension module
+fn editable_mapping(
+    project_layout: &ProjectLayout,
+    extension: Option<&str>,
+) -> Result<BTreeMap<String, String>> {
+    let mut mapping = BTreeMap::new();
+    let mut add = |name: String, path: &Path| -> Result<()> {
+        // The extension module may not have been copied yet, so we only normalize the parent
+        let (Some(parent), Some(file_name)) = (path.parent(), path.file_name()) else {
+            bail!("Invalid path `{}` for {name}", path.display());
+        };
+        let path = parent
+            .normalize()
+            .with_context(|| format!("failed to normalize path `{}`", parent.display()))?
+            .into_path_buf()
+            .join(file_name);
+        let path = path.to_str().with_context(|| {
+            format!(
+                "Editable installs require unicode paths, but `{}` isn't",
+                path.display()
+            )
+        })?;
+        mapping.insert(name, path.to_string());
+        Ok(())
+    };
+    if let Some(python_module) = &project_layout.python_module {
+        if let Some(name) = python_module.file_name().and_then(|name| name.to_str()) {
+            add(name.to_string(), python_module)?;
+        }
+        if let Some(extension) = extension {
+            let relative = project_layout
+                .rust_module
+                .strip_prefix(python_module.parent().unwrap())
+                .unwrap();
+            let mut parts: Vec<_> = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy().to_string())
+                .collect();
+            parts.push(project_layout.extension_name.clone());
+            add(parts.join("."), &project_layout.rust_module.join(extension))?;
+        }
+    }
+    for package in &project_layout.python_packages {
+        let package_dir = project_layout.python_dir.join(package);
+        let module_file = project_layout.python_dir.join(format!("{package}.py"));
+        if package_dir.is_dir() {
+            add(package.clone(), &package_dir)?;
+        } else if module_file.is_file() {
+            add(package.clone(), &module_file)?;
+        }
+    }
+    Ok(mapping)
+}
+
 /// Creates the cffi module with the shared library, the cffi declarations and the cffi loader
 #[allow(clippy::too_many_arguments)]
 pub fn write_cffi_module(
@@ -1253,6 +1708,7 @@ pub fn write_python_part(
 pub fn write_dist_info(
     writer: &mut impl ModuleWriter,
     metadata23: &Metadata23,
+    build_tag: Option<&str>,
     tags: &[String],
 ) -> Result<()> {
     let dist_info_dir = metadata23.get_dist_info_dir();
@@ -1264,7 +1720,10 @@ pub fn write_dist_info(
         metadata23.to_file_contents()?.as_bytes(),
     )?;
 
-    writer.add_bytes(&dist_info_dir.join("WHEEL"), wheel_file(tags)?.as_bytes())?;
+    writer.add_bytes(
+        &dist_info_dir.join("WHEEL"),
+        wheel_file(build_tag, tags)?.as_bytes(),
+    )?;
 
     let mut entry_points = String::new();
     if !metadata23.scripts.is_empty() {
@@ -1283,14 +1742,16 @@ pub fn write_dist_info(
         )?;
     }
 
+    // https://peps.python.org/pep-0639/#add-license-file-field
     if !metadata23.license_files.is_empty() {
-        let license_files_dir = dist_info_dir.join("license_files");
-        writer.add_directory(&license_files_dir)?;
-        for path in &metadata23.license_files {
-            let filename = path.file_name().with_context(|| {
-                format!("missing file name for license file {}", path.display())
-            })?;
-            writer.add_file(license_files_dir.join(filename), path)?;
+        let licenses_dir = dist_info_dir.join("licenses");
+        writer.add_directory(&licenses_dir)?;
+        for license_file in &metadata23.license_files {
+            let target = licenses_dir.join(&license_file.path);
+            if let Some(parent) = target.parent().filter(|parent| *parent != licenses_dir) {
+                writer.add_directory(parent)?;
+            }
+            writer.add_file(target, &license_file.source)?;
         }
     }
 
@@ -1372,7 +1833,7 @@ mod tests {
 
         // No excludes
         let tmp_dir = TempDir::new()?;
-        let mut writer = SDistWriter::new(&tmp_dir, &metadata, Override::empty())?;
+        let mut writer = SDistWriter::new(&tmp_dir, &metadata, Override::empty(), false)?;
         assert!(writer.files.is_empty());
         writer.add_bytes_with_permissions("test", &[], perm)?;
         assert_eq!(writer.files.len(), 1);
@@ -1384,7 +1845,7 @@ mod tests {
         let mut excludes = OverrideBuilder::new(&tmp_dir);
         excludes.add("test*")?;
         excludes.add("!test2")?;
-        let mut writer = SDistWriter::new(&tmp_dir, &metadata, excludes.build()?)?;
+        let mut writer = SDistWriter::new(&tmp_dir, &metadata, excludes.build()?, false)?;
         writer.add_bytes_with_permissions("test1", &[], perm)?;
         writer.add_bytes_with_permissions("test3", &[], perm)?;
         assert!(writer.files.is_empty());
@@ -1397,4 +1858,98 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn editable_mapping_only_contains_the_packages() -> Result<()> {
+        let tmp_dir = TempDir::new()?;
+        let python_dir = tmp_dir.path().join("python");
+        let python_module = python_dir.join("my_project");
+        fs::create_dir_all(python_module.join("_native"))?;
+        fs::create_dir_all(python_dir.join("tests"))?;
+        fs::write(python_module.join("__init__.py"), "")?;
+        fs::write(python_dir.join("helpers.py"), "")?;
+        fs::write(python_dir.join("conftest.py"), "")?;
+        let project_layout = ProjectLayout {
+            python_dir: python_dir.clone(),
+            python_module: Some(python_module.clone()),
+            python_packages: vec!["helpers".to_string(), "missing".to_string()],
+            rust_module: python_module.join("_native"),
+            extension_name: "_lib".to_string(),
+            data: None,
+        };
+        let mapping = editable_mapping(&project_layout, Some("_lib.abi3.so"))?;
+        let path = |path: PathBuf| path.normalize().unwrap().into_path_buf();
+        assert_eq!(
+            mapping,
+            BTreeMap::from([
+                (
+                    "helpers".to_string(),
+                    path(python_dir.join("helpers.py")).display().to_string()
+                ),
+                (
+                    "my_project".to_string(),
+                    path(python_module.clone()).display().to_string()
+                ),
+                (
+                    "my_project._native._lib".to_string(),
+                    path(python_module.join("_native"))
+                        .join("_lib.abi3.so")
+                        .display()
+                        .to_string()
+                ),
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn wheel_file_roundtrip() -> Result<()> {
+        let tags = vec![
+            "cp38-abi3-manylinux_2_17_x86_64".to_string(),
+            "cp38-abi3-manylinux2014_x86_64".to_string(),
+        ];
+        let parsed = WheelFile::parse(&wheel_file(None, &tags)?)?;
+        assert_eq!(parsed.wheel_version, "1.0");
+        assert!(!parsed.root_is_purelib);
+        assert_eq!(parsed.build, None);
+        assert_eq!(parsed.tags, tags);
+        let parsed = WheelFile::parse(&wheel_file(Some("1"), &tags)?)?;
+        assert_eq!(parsed.build.as_deref(), Some("1"));
+        assert_eq!(parsed.tags, tags);
+        assert!(WheelFile::parse("Tag: py3-none-any\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn record_parsing() -> Result<()> {
+        let record = indoc::indoc!(
+            r#"
+            dummy/__init__.py,sha256=47DEQpj8HBSa-_TImW-5JCeuQeRkm5NMpJWZG3hSuFU,0
+            "dummy/with,comma.py",sha256=47DEQpj8HBSa-_TImW-5JCeuQeRkm5NMpJWZG3hSuFU,0
+            dummy-1.0.dist-info/RECORD,,
+            "#
+        );
+        let entries = parse_record(record)?;
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].path, "dummy/with,comma.py");
+        assert_eq!(entries[1].size, Some(0));
+        assert_eq!(
+            entries[2],
+            RecordEntry {
+                path: "dummy-1.0.dist-info/RECORD".to_string(),
+                hash: None,
+                size: None,
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn entry_points_roundtrip() -> Result<()> {
+        let mut scripts = IndexMap::new();
+        scripts.insert("get_42".to_string(), "pyo3_pure:get_42".to_string());
+        let parsed = parse_entry_points(&entry_points_txt("console_scripts", &scripts))?;
+        assert_eq!(parsed["console_scripts"], scripts);
+        Ok(())
+    }
 }
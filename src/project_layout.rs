@@ -1,4 +1,6 @@
 use crate::build_options::{extract_cargo_metadata_args, CargoOptions};
+use crate::pyproject_toml::VersionSource;
+use crate::vcs_version::version_from_vcs;
 use crate::{CargoToml, Metadata23, PyProjectToml};
 use anyhow::{bail, format_err, Context, Result};
 use cargo_metadata::{Metadata, MetadataCommand};
@@ -112,6 +114,20 @@ impl ProjectResolver {
         if let Some(pyproject) = pyproject {
             let pyproject_dir = pyproject_file.parent().unwrap();
             metadata23.merge_pyproject_toml(pyproject_dir, pyproject)?;
+            if pyproject.version_source() == VersionSource::Vcs {
+                if let Some(project) = &pyproject.project {
+                    let dynamic = project.dynamic.as_deref().unwrap_or_default();
+                    if project.version.is_some() || !dynamic.iter().any(|field| field == "version")
+                    {
+                        bail!(
+                            "`tool.maturin.version.source = \"vcs\"` requires `version` to be \
+                            listed in `project.dynamic` instead of being set in `project.version`"
+                        );
+                    }
+                }
+                metadata23.version = version_from_vcs(pyproject_dir)?;
+                eprintln!("🏷️  Using version {} from git", metadata23.version);
+            }
         }
 
         let crate_name = &cargo_toml.package.name;
@@ -1,10 +1,12 @@
 //! A pyproject.toml as specified in PEP 517
 
-use crate::PlatformTag;
-use anyhow::{Context, Result};
+use crate::install::normalize_name;
+use crate::{AuditWheelMode, SbomFormat};
+use anyhow::{bail, Context, Result};
 use fs_err as fs;
+use indexmap::IndexMap;
 use pep440_rs::Version;
-use pep508_rs::VersionOrUrl;
+use pep508_rs::{Requirement, VersionOrUrl};
 use pyproject_toml::{BuildSystem, Project};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -111,6 +113,12 @@ pub struct TargetConfig {
     /// macOS deployment target version
     #[serde(alias = "macosx-deployment-target")]
     pub macos_deployment_target: Option<String>,
+    /// What to do when the wheel isn't manylinux or musllinux compliant, overrides
+    /// `[tool.maturin.auditwheel]`
+    pub auditwheel: Option<AuditWheelMode>,
+    /// Shared libraries that aren't copied into the wheel, overrides
+    /// `[tool.maturin.auditwheel-exclude]`
+    pub auditwheel_exclude: Option<Vec<String>>,
 }
 
 /// Source distribution generator
@@ -125,6 +133,67 @@ pub enum SdistGenerator {
     Git,
 }
 
+/// How editable installs make the python sources importable
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum EditableMode {
+    /// A `.pth` file that adds the whole python source directory to `sys.path`
+    #[default]
+    Pth,
+    /// An import hook that maps exactly the python packages of the project and the compiled
+    /// extension to the source tree
+    Finder,
+}
+
+/// What happens when a third-party crate fails the license check
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum LicenseViolation {
+    /// Abort the build
+    #[default]
+    Fail,
+    /// Print a warning and continue
+    Warn,
+}
+
+/// Bundling of the licenses of the third-party crates compiled into the wheel
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ThirdPartyLicenses {
+    /// SPDX license identifiers that are not allowed, e.g. `GPL-3.0-only`
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// Whether a crate without a license file or with a denied license fails the build
+    #[serde(default)]
+    pub on_violation: LicenseViolation,
+}
+
+/// Where the version of the package comes from
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum VersionSource {
+    /// `project.version` in pyproject.toml or `package.version` in Cargo.toml
+    #[default]
+    Cargo,
+    /// The nearest git tag, the number of commits since the tag and the dirty state of the
+    /// working tree, as with setuptools_scm
+    Vcs,
+}
+
+/// The `[tool.maturin.version]` section of a pyproject.toml
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct VersionConfig {
+    /// Where the version comes from
+    #[serde(default)]
+    pub source: VersionSource,
+}
+
 /// The `[tool.maturin]` section of a pyproject.toml
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "kebab-case")]
@@ -141,16 +210,40 @@ pub struct ToolMaturin {
     pub bindings: Option<String>,
     /// Platform compatibility
     #[serde(alias = "manylinux")]
-    pub compatibility: Option<PlatformTag>,
+    pub compatibility: Option<String>,
     /// Skip audit wheel
     #[serde(default)]
     pub skip_auditwheel: bool,
+    /// What to do when the wheel isn't manylinux or musllinux compliant
+    pub auditwheel: Option<AuditWheelMode>,
+    /// Shared libraries that aren't copied into the wheel although they aren't allowed by the
+    /// policy, as glob patterns of their sonames, e.g. `libcuda.so.*`
+    pub auditwheel_exclude: Option<Vec<String>>,
+    /// JSON file with additional or overriding manylinux/musllinux policies, in the format of
+    /// auditwheel's `manylinux-policy.json`
+    pub policy_file: Option<PathBuf>,
     /// Strip the final binary
     #[serde(default)]
     pub strip: bool,
+    /// Make the wheels and the source distribution byte-for-byte reproducible
+    #[serde(default)]
+    pub reproducible: bool,
+    /// Embed a software bill of materials of the compiled crates in this format
+    pub sbom: Option<SbomFormat>,
+    /// Bundle the license files of the third-party crates in `.dist-info/licenses/`
+    pub third_party_licenses: Option<ThirdPartyLicenses>,
+    /// Where the version of the package comes from
+    pub version: Option<VersionConfig>,
+    /// Build number added to the wheel filenames and the WHEEL files
+    pub build_tag: Option<String>,
+    /// Local version label appended to the version, e.g. `cpu` for `1.0+cpu`
+    pub local_version: Option<String>,
     /// Source distribution generator
     #[serde(default)]
     pub sdist_generator: SdistGenerator,
+    /// How editable installs make the python sources importable
+    #[serde(default)]
+    pub editable_mode: EditableMode,
     /// The directory with python module, contains `<module_name>/__init__.py`
     pub python_source: Option<PathBuf>,
     /// Python packages to include
@@ -185,6 +278,20 @@ pub struct ToolMaturin {
     pub rustc_args: Option<Vec<String>>,
 }
 
+/// An entry of a dependency group as specified in PEP 735
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum DependencyGroupEntry {
+    /// A PEP 508 requirement
+    Requirement(String),
+    /// Includes all requirements of another dependency group
+    IncludeGroup {
+        /// The name of the included group
+        #[serde(rename = "include-group")]
+        include_group: String,
+    },
+}
+
 /// A pyproject.toml as specified in PEP 517
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "kebab-case")]
@@ -193,6 +300,9 @@ pub struct PyProjectToml {
     pub build_system: BuildSystem,
     /// Project metadata
     pub project: Option<Project>,
+    /// PEP 735: Named lists of requirements such as test and lint tools, which are not part of
+    /// the package metadata
+    pub dependency_groups: Option<IndexMap<String, Vec<DependencyGroupEntry>>>,
     /// PEP 518: The `[tool]` table is where any tool related to your Python project, not just build
     /// tools, can have users specify configuration data as long as they use a sub-table within
     /// `[tool]`, e.g. the flit tool would store its configuration in `[tool.flit]`.
@@ -209,15 +319,34 @@ impl PyProjectToml {
     pub fn new(pyproject_file: impl AsRef<Path>) -> Result<PyProjectToml> {
         let path = pyproject_file.as_ref();
         let contents = fs::read_to_string(path)?;
-        let pyproject = toml::from_str(&contents).with_context(|| {
-            format!(
-                "pyproject.toml at {} is invalid",
-                pyproject_file.as_ref().display()
-            )
-        })?;
+        let pyproject = toml::from_str(&contents)
+            .or_else(|err| Self::from_license_files_list(&contents).ok_or(err))
+            .with_context(|| {
+                format!(
+                    "pyproject.toml at {} is invalid",
+                    pyproject_file.as_ref().display()
+                )
+            })?;
         Ok(pyproject)
     }
 
+    /// PEP 639 settled on a list of globs for `project.license-files`, while the pyproject-toml
+    /// crate only knows the earlier draft with `license-files.globs`, so we rewrite the former
+    /// into the latter
+    fn from_license_files_list(contents: &str) -> Option<PyProjectToml> {
+        let mut pyproject: toml::Table = toml::from_str(contents).ok()?;
+        let project = pyproject.get_mut("project")?.as_table_mut()?;
+        let globs = project.get("license-files")?.as_array()?.clone();
+        project.insert(
+            "license-files".to_string(),
+            toml::Value::Table(toml::Table::from_iter([(
+                "globs".to_string(),
+                toml::Value::Array(globs),
+            )])),
+        );
+        toml::Value::Table(pyproject).try_into().ok()
+    }
+
     /// Returns the value of `[project.name]` in pyproject.toml
     pub fn project_name(&self) -> Option<&str> {
         self.project.as_ref().map(|project| project.name.as_str())
@@ -250,8 +379,8 @@ impl PyProjectToml {
     }
 
     /// Returns the value of `[tool.maturin.compatibility]` in pyproject.toml
-    pub fn compatibility(&self) -> Option<PlatformTag> {
-        self.maturin()?.compatibility
+    pub fn compatibility(&self) -> Option<&str> {
+        self.maturin()?.compatibility.as_deref()
     }
 
     /// Returns the value of `[tool.maturin.skip-auditwheel]` in pyproject.toml
@@ -261,6 +390,25 @@ impl PyProjectToml {
             .unwrap_or_default()
     }
 
+    /// Returns the value of `[tool.maturin.auditwheel]` in pyproject.toml, or `skip` if
+    /// `[tool.maturin.skip-auditwheel]` is set
+    pub fn auditwheel(&self) -> Option<AuditWheelMode> {
+        let maturin = self.maturin()?;
+        maturin
+            .auditwheel
+            .or(maturin.skip_auditwheel.then_some(AuditWheelMode::Skip))
+    }
+
+    /// Returns the value of `[tool.maturin.auditwheel-exclude]` in pyproject.toml
+    pub fn auditwheel_exclude(&self) -> Option<&[String]> {
+        self.maturin()?.auditwheel_exclude.as_deref()
+    }
+
+    /// Returns the value of `[tool.maturin.policy-file]` in pyproject.toml
+    pub fn policy_file(&self) -> Option<&Path> {
+        self.maturin()?.policy_file.as_deref()
+    }
+
     /// Returns the value of `[tool.maturin.strip]` in pyproject.toml
     pub fn strip(&self) -> bool {
         self.maturin()
@@ -268,6 +416,44 @@ impl PyProjectToml {
             .unwrap_or_default()
     }
 
+    /// Returns the value of `[tool.maturin.reproducible]` in pyproject.toml
+    pub fn reproducible(&self) -> bool {
+        self.maturin()
+            .map(|maturin| maturin.reproducible)
+            .unwrap_or_default()
+    }
+
+    /// Returns the value of `[tool.maturin.sbom]` in pyproject.toml
+    pub fn sbom(&self) -> Option<SbomFormat> {
+        self.maturin().and_then(|maturin| maturin.sbom)
+    }
+
+    /// Returns the value of `[tool.maturin.build-tag]` in pyproject.toml
+    pub fn build_tag(&self) -> Option<&str> {
+        self.maturin()
+            .and_then(|maturin| maturin.build_tag.as_deref())
+    }
+
+    /// Returns the value of `[tool.maturin.local-version]` in pyproject.toml
+    pub fn local_version(&self) -> Option<&str> {
+        self.maturin()
+            .and_then(|maturin| maturin.local_version.as_deref())
+    }
+
+    /// Returns the value of `[tool.maturin.third-party-licenses]` in pyproject.toml
+    pub fn third_party_licenses(&self) -> Option<&ThirdPartyLicenses> {
+        self.maturin()
+            .and_then(|maturin| maturin.third_party_licenses.as_ref())
+    }
+
+    /// Returns the value of `[tool.maturin.version.source]` in pyproject.toml
+    pub fn version_source(&self) -> VersionSource {
+        self.maturin()
+            .and_then(|maturin| maturin.version.as_ref())
+            .map(|version| version.source)
+            .unwrap_or_default()
+    }
+
     /// Returns the value of `[tool.maturin.sdist-generator]` in pyproject.toml
     pub fn sdist_generator(&self) -> SdistGenerator {
         self.maturin()
@@ -275,6 +461,13 @@ impl PyProjectToml {
             .unwrap_or_default()
     }
 
+    /// Returns the value of `[tool.maturin.editable-mode]` in pyproject.toml
+    pub fn editable_mode(&self) -> EditableMode {
+        self.maturin()
+            .map(|maturin| maturin.editable_mode)
+            .unwrap_or_default()
+    }
+
     /// Returns the value of `[tool.maturin.python-source]` in pyproject.toml
     pub fn python_source(&self) -> Option<&Path> {
         self.maturin()
@@ -308,6 +501,63 @@ impl PyProjectToml {
         self.maturin()?.manifest_path.as_deref()
     }
 
+    /// Returns the requirements of a `[dependency-groups]` entry, resolving `include-group`
+    ///
+    /// Group names are compared in their normalized form and cycles are an error, see PEP 735.
+    pub fn dependency_group(&self, name: &str) -> Result<Vec<String>> {
+        let groups = self.dependency_groups.as_ref();
+        let mut seen = IndexMap::new();
+        for group in groups.into_iter().flat_map(|groups| groups.keys()) {
+            if let Some(previous) = seen.insert(normalize_name(group), group) {
+                bail!("Dependency groups `{previous}` and `{group}` have the same normalized name");
+            }
+        }
+        let mut requirements = Vec::new();
+        self.resolve_dependency_group(name, &mut Vec::new(), &mut requirements)?;
+        Ok(requirements)
+    }
+
+    fn resolve_dependency_group(
+        &self,
+        name: &str,
+        parents: &mut Vec<String>,
+        requirements: &mut Vec<String>,
+    ) -> Result<()> {
+        let normalized = normalize_name(name);
+        if parents.contains(&normalized) {
+            bail!(
+                "Dependency group `{name}` includes itself through {}",
+                parents.join(" -> ")
+            );
+        }
+        let Some(entries) = self.dependency_groups.iter().find_map(|groups| {
+            groups
+                .iter()
+                .find(|(group, _)| normalize_name(group) == normalized)
+                .map(|(_, entries)| entries)
+        }) else {
+            bail!("There is no dependency group `{name}` in pyproject.toml");
+        };
+        parents.push(normalized);
+        for entry in entries {
+            match entry {
+                DependencyGroupEntry::Requirement(requirement) => {
+                    Requirement::from_str(requirement).with_context(|| {
+                        format!("Invalid requirement `{requirement}` in dependency group `{name}`")
+                    })?;
+                    if !requirements.contains(requirement) {
+                        requirements.push(requirement.clone());
+                    }
+                }
+                DependencyGroupEntry::IncludeGroup { include_group } => {
+                    self.resolve_dependency_group(include_group, parents, requirements)?
+                }
+            }
+        }
+        parents.pop();
+        Ok(())
+    }
+
     /// Warn about `build-system.requires` mismatching expectations.
     ///
     /// Having a pyproject.toml without a version constraint is a bad idea
@@ -380,7 +630,7 @@ impl PyProjectToml {
 mod tests {
     use crate::{
         pyproject_toml::{Format, Formats, GlobPattern, ToolMaturin},
-        PyProjectToml,
+        AuditWheelMode, PyProjectToml,
     };
     use expect_test::expect;
     use fs_err as fs;
@@ -450,6 +700,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_auditwheel() {
+        let tmp_dir = TempDir::new().unwrap();
+        let pyproject_file = tmp_dir.path().join("pyproject.toml");
+
+        fs::write(
+            &pyproject_file,
+            r#"[build-system]
+            requires = ["maturin"]
+            build-backend = "maturin"
+
+            [tool.maturin]
+            skip-auditwheel = true
+            auditwheel-exclude = ["libcuda.so.*"]
+            policy-file = "policies.json"
+
+            [tool.maturin.target."x86_64-unknown-linux-gnu"]
+            auditwheel = "check"
+            auditwheel-exclude = ["libnvidia-*"]
+            "#,
+        )
+        .unwrap();
+        let pyproject = PyProjectToml::new(pyproject_file).unwrap();
+        assert_eq!(pyproject.auditwheel(), Some(AuditWheelMode::Skip));
+        assert_eq!(
+            pyproject.auditwheel_exclude(),
+            Some(["libcuda.so.*".to_string()].as_slice())
+        );
+        assert_eq!(pyproject.policy_file(), Some(Path::new("policies.json")));
+        let target_config = pyproject.target_config("x86_64-unknown-linux-gnu").unwrap();
+        assert_eq!(target_config.auditwheel, Some(AuditWheelMode::Check));
+        assert_eq!(
+            target_config.auditwheel_exclude,
+            Some(vec!["libnvidia-*".to_string()])
+        );
+    }
+
     #[test]
     fn test_warn_missing_maturin_version() {
         let with_constraint = PyProjectToml::new("test-crates/pyo3-pure/pyproject.toml").unwrap();
@@ -583,4 +870,38 @@ mod tests {
         "#]];
         expected.assert_eq(&inner_error.to_string());
     }
+
+    #[test]
+    fn test_dependency_groups() {
+        let pyproject: PyProjectToml = toml::from_str(indoc!(
+            r#"
+            [build-system]
+            requires = ["maturin"]
+            build-backend = "maturin"
+
+            [dependency-groups]
+            Test = ["pytest>=8", "coverage"]
+            typing = ["mypy; python_version >= '3.9'"]
+            dev = [{include-group = "test"}, {include-group = "typing"}, "coverage"]
+            cycle-a = [{include-group = "cycle_b"}]
+            cycle-b = [{include-group = "Cycle.A"}]
+            "#
+        ))
+        .unwrap();
+        assert_eq!(
+            pyproject.dependency_group("dev").unwrap(),
+            vec!["pytest>=8", "coverage", "mypy; python_version >= '3.9'"]
+        );
+        assert_eq!(
+            pyproject
+                .dependency_group("cycle-a")
+                .unwrap_err()
+                .to_string(),
+            "Dependency group `Cycle.A` includes itself through cycle-a -> cycle-b"
+        );
+        assert_eq!(
+            pyproject.dependency_group("lint").unwrap_err().to_string(),
+            "There is no dependency group `lint` in pyproject.toml"
+        );
+    }
 }
@@ -0,0 +1,459 @@
+//! Implementation of `maturin retag`, which changes the tags and the build number of built wheels
+//! without recompiling them
+use crate::auditwheel::{audit_elf, PlatformTag, Policies};
+use crate::check::check_wheel;
+use crate::inspect::InspectedDistribution;
+use crate::module_writer::{validate_build_tag, WheelFilename};
+use crate::target::Arch;
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use fs_err::File;
+use goblin::elf::Elf;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zip::{ZipArchive, ZipWriter};
+
+/// `manylinux_x_y_arch`, `musllinux_x_y_arch` or an alias such as `manylinux2014_arch`,
+/// including the aliases of policies loaded from policy files
+static PORTABLE_LINUX_TAG: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?P<tag>(?:many|musl)linux(?:_\d+_\d+|\d+))_(?P<arch>.+)$").unwrap()
+});
+
+/// Change the tags or the build number of built wheels
+#[derive(Debug, Clone, clap::Parser)]
+pub struct RetagOptions {
+    /// The wheels (.whl) to retag
+    #[arg(value_name = "FILE", required = true)]
+    pub files: Vec<PathBuf>,
+    /// The new platform tags, e.g. `manylinux_2_28_x86_64`. manylinux and musllinux tags are
+    /// only applied if all binaries in the wheel comply with the policy of the tag
+    #[arg(long = "platform-tag", value_name = "TAG")]
+    pub platform_tags: Vec<String>,
+    /// The new python tag, e.g. `cp38` or `py2.py3`
+    #[arg(long, value_name = "TAG")]
+    pub python_tag: Option<String>,
+    /// The new abi tag, e.g. `abi3`
+    #[arg(long, value_name = "TAG")]
+    pub abi_tag: Option<String>,
+    /// The new build number, which must start with a digit
+    #[arg(long, value_name = "BUILD")]
+    pub build_tag: Option<String>,
+    /// The directory to store the retagged wheels in. Defaults to the directory of each wheel
+    #[arg(short, long)]
+    pub out: Option<PathBuf>,
+}
+
+/// Checks that a compressed tag set component such as `py2.py3` or `manylinux_2_17_x86_64`
+/// only contains valid characters
+fn validate_tag(kind: &str, tag: &str) -> Result<()> {
+    if tag.is_empty()
+        || tag.split('.').any(|part| {
+            part.is_empty() || !part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+    {
+        bail!("Invalid {kind} tag `{tag}`");
+    }
+    Ok(())
+}
+
+/// Maps the architecture of a manylinux/musllinux platform tag to the rust architecture
+fn arch_from_platform_tag(arch: &str) -> Option<Arch> {
+    match arch {
+        "x86_64" => Some(Arch::X86_64),
+        "i686" => Some(Arch::X86),
+        "aarch64" => Some(Arch::Aarch64),
+        "armv7l" => Some(Arch::Armv7L),
+        "ppc64le" => Some(Arch::Powerpc64Le),
+        "ppc64" => Some(Arch::Powerpc64),
+        "s390x" => Some(Arch::S390X),
+        _ => None,
+    }
+}
+
+/// Parses a manylinux/musllinux platform tag such as `manylinux_2_28_x86_64`, returning `None`
+/// for all other platform tags
+fn parse_portable_linux_tag(tag: &str, policies: &Policies) -> Result<Option<(PlatformTag, Arch)>> {
+    let Some(captures) = PORTABLE_LINUX_TAG.captures(tag) else {
+        return Ok(None);
+    };
+    let platform_tag = policies.parse_platform_tag(&captures["tag"])?;
+    let arch = arch_from_platform_tag(&captures["arch"])
+        .with_context(|| format!("Unsupported architecture in platform tag `{tag}`"))?;
+    Ok(Some((platform_tag, arch)))
+}
+
+/// Runs the auditwheel policy check of a manylinux/musllinux tag against all elf files in the
+/// wheel
+///
+/// Libraries that are bundled in the wheel, e.g. by a previous auditwheel repair, are allowed
+/// to be linked in addition to those in the policy.
+fn audit_wheel_files(wheel_path: &Path, platform_tag: &str, policies: &Policies) -> Result<()> {
+    let Some((policy_tag, arch)) = parse_portable_linux_tag(platform_tag, policies)? else {
+        return Ok(());
+    };
+    let mut archive = ZipArchive::new(File::open(wheel_path)?)?;
+    let mut elf_files = Vec::new();
+    for idx in 0..archive.len() {
+        let mut file = archive.by_index(idx)?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+        if contents.starts_with(b"\x7fELF") {
+            elf_files.push((file.name().to_string(), contents));
+        }
+    }
+    let bundled: HashSet<&str> = elf_files
+        .iter()
+        .filter_map(|(name, _)| name.rsplit('/').next())
+        .collect();
+
+    for (name, contents) in &elf_files {
+        let elf = Elf::parse(contents).with_context(|| format!("Failed to parse {name}"))?;
+        // Executables in the scripts directory may embed python
+        let allow_linking_libpython = name.contains(".data/scripts/");
+        let (policy, should_repair) = audit_elf(
+            &elf,
+            arch,
+            Some(policy_tag),
+            allow_linking_libpython,
+            policies,
+        )
+        .with_context(|| format!("{name} doesn't comply with {platform_tag}"))?;
+        if should_repair {
+            let mut unbundled: Vec<&str> = elf
+                .libraries
+                .iter()
+                .copied()
+                .filter(|lib| {
+                    !policy.lib_whitelist.contains(*lib)
+                        && !bundled.contains(lib)
+                        && !lib.starts_with("ld-linux")
+                        && !lib.starts_with("ld64.so")
+                        && (!allow_linking_libpython || !lib.starts_with("libpython"))
+                })
+                .collect();
+            unbundled.sort_unstable();
+            if !unbundled.is_empty() {
+                bail!(
+                    "{name} doesn't comply with {platform_tag} because it links {}, which are \
+                    neither allowed by the policy nor bundled in the wheel",
+                    unbundled.join(", ")
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the `Tag:` and `Build:` lines of a WHEEL file, keeping all other lines
+fn rewrite_wheel_file(contents: &str, tags: &[String], build_tag: Option<&str>) -> String {
+    let mut wheel_file = String::new();
+    for line in contents.lines() {
+        if !line.starts_with("Tag:") && !line.starts_with("Build:") && !line.is_empty() {
+            wheel_file.push_str(line);
+            wheel_file.push('\n');
+        }
+    }
+    if let Some(build_tag) = build_tag {
+        wheel_file.push_str(&format!("Build: {build_tag}\n"));
+    }
+    for tag in tags {
+        wheel_file.push_str(&format!("Tag: {tag}\n"));
+    }
+    wheel_file
+}
+
+/// Writes a copy of the wheel with a new WHEEL file and the RECORD updated for it
+fn write_retagged_wheel(
+    source: &Path,
+    target: &Path,
+    distribution: &InspectedDistribution,
+    wheel_file: &str,
+) -> Result<()> {
+    let dist_info_dir = distribution.metadata.get_dist_info_dir();
+    let dist_info_dir = dist_info_dir.to_str().unwrap();
+    let wheel_path = format!("{dist_info_dir}/WHEEL");
+    let record_path = format!("{dist_info_dir}/RECORD");
+
+    let out_dir = target.parent().unwrap();
+    let tmp_file = tempfile::NamedTempFile::new_in(out_dir)?;
+    let mut archive = ZipArchive::new(File::open(source)?)?;
+    let mut writer = ZipWriter::new(tmp_file.as_file());
+    let mut record_options = None;
+    for idx in 0..archive.len() {
+        let file = archive.by_index_raw(idx)?;
+        let options = zip::write::FileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .last_modified_time(file.last_modified())
+            .unix_permissions(file.unix_mode().unwrap_or(0o644));
+        if file.name() == wheel_path {
+            writer.start_file(file.name(), options)?;
+            writer.write_all(wheel_file.as_bytes())?;
+        } else if file.name() == record_path {
+            // RECORD must be written last because it contains the hash of WHEEL
+            record_options = Some(options);
+        } else {
+            writer.raw_copy_file(file)?;
+        }
+    }
+
+    let wheel_hash = format!(
+        "sha256={}",
+        URL_SAFE_NO_PAD.encode(Sha256::digest(wheel_file.as_bytes()))
+    );
+    let mut record = String::new();
+    for entry in &distribution.record {
+        if entry.path == wheel_path {
+            record.push_str(&format!(
+                "{},{wheel_hash},{}\n",
+                entry.path,
+                wheel_file.len()
+            ));
+        } else {
+            record.push_str(&format!(
+                "{},{},{}\n",
+                entry.path,
+                entry.hash.as_deref().unwrap_or_default(),
+                entry.size.map(|size| size.to_string()).unwrap_or_default()
+            ));
+        }
+    }
+    writer.start_file(&record_path, record_options.unwrap_or_default())?;
+    writer.write_all(record.as_bytes())?;
+    writer.finish()?;
+    drop(writer);
+
+    tmp_file
+        .persist(target)
+        .with_context(|| format!("Failed to write {}", target.display()))?;
+    Ok(())
+}
+
+/// Retags a single wheel and returns the path of the retagged wheel
+///
+/// manylinux and musllinux tags are checked against the `policies`.
+pub fn retag_wheel(
+    wheel_path: &Path,
+    options: &RetagOptions,
+    policies: &Policies,
+) -> Result<PathBuf> {
+    let filename = wheel_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .with_context(|| format!("Invalid file name {}", wheel_path.display()))?;
+    let mut wheel_filename = WheelFilename::parse(filename)?;
+    if let Some(python_tag) = &options.python_tag {
+        wheel_filename.python_tag = python_tag.clone();
+    }
+    if let Some(abi_tag) = &options.abi_tag {
+        wheel_filename.abi_tag = abi_tag.clone();
+    }
+    if !options.platform_tags.is_empty() {
+        wheel_filename.platform_tag = options.platform_tags.join(".");
+    }
+    if let Some(build_tag) = &options.build_tag {
+        wheel_filename.build_tag = Some(build_tag.clone());
+    }
+
+    let distribution = InspectedDistribution::open(wheel_path)?;
+    let wheel = distribution
+        .wheel
+        .as_ref()
+        .context("The wheel is missing the WHEEL file")?;
+    let tags = wheel_filename.expanded_tags();
+    let old_platform_tags: HashSet<&str> = wheel
+        .tags
+        .iter()
+        .filter_map(|tag| tag.splitn(3, '-').nth(2))
+        .collect();
+    // Tags the wheel already has were checked when building it
+    for platform_tag in wheel_filename.platform_tag.split('.') {
+        if !old_platform_tags.contains(platform_tag) {
+            audit_wheel_files(wheel_path, platform_tag, policies)?;
+        }
+    }
+
+    let mut archive = ZipArchive::new(File::open(wheel_path)?)?;
+    let mut old_wheel_file = String::new();
+    archive
+        .by_name(&format!(
+            "{}/WHEEL",
+            distribution.metadata.get_dist_info_dir().display()
+        ))?
+        .read_to_string(&mut old_wheel_file)?;
+    let wheel_file =
+        rewrite_wheel_file(&old_wheel_file, &tags, wheel_filename.build_tag.as_deref());
+
+    let out_dir = match &options.out {
+        Some(out) => {
+            fs_err::create_dir_all(out)?;
+            out.clone()
+        }
+        None => wheel_path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .to_path_buf(),
+    };
+    let target = out_dir.join(wheel_filename.to_string());
+    write_retagged_wheel(wheel_path, &target, &distribution, &wheel_file)?;
+    check_wheel(&target)?;
+    Ok(target)
+}
+
+/// Implementation of `maturin retag`
+pub fn retag(options: &RetagOptions) -> Result<Vec<PathBuf>> {
+    if options.platform_tags.is_empty()
+        && options.python_tag.is_none()
+        && options.abi_tag.is_none()
+        && options.build_tag.is_none()
+    {
+        bail!("Nothing to do, pass `--platform-tag`, `--python-tag`, `--abi-tag` or `--build-tag`");
+    }
+    let policies = Policies::from_env()?;
+    for platform_tag in &options.platform_tags {
+        validate_tag("platform", platform_tag)?;
+        parse_portable_linux_tag(platform_tag, &policies)?;
+    }
+    if let Some(python_tag) = &options.python_tag {
+        validate_tag("python", python_tag)?;
+    }
+    if let Some(abi_tag) = &options.abi_tag {
+        validate_tag("abi", abi_tag)?;
+    }
+    if let Some(build_tag) = &options.build_tag {
+        validate_build_tag(build_tag)?;
+    }
+
+    let mut retagged = Vec::new();
+    for file in &options.files {
+        let target = retag_wheel(file, options, &policies)
+            .with_context(|| format!("Failed to retag {}", file.display()))?;
+        eprintln!("🏷️  Retagged {} as {}", file.display(), target.display());
+        retagged.push(target);
+    }
+    Ok(retagged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metadata23, ModuleWriter, WheelWriter};
+    use clap::Parser;
+    use ignore::overrides::Override;
+    use pep440_rs::Version;
+
+    fn build_wheel(dir: &Path) -> Result<PathBuf> {
+        let metadata = Metadata23::new("retag-me".to_string(), Version::new([0, 1]));
+        let tag = "py3-none-linux_x86_64";
+        let mut writer = WheelWriter::new(
+            tag,
+            dir,
+            &metadata,
+            None,
+            &[tag.to_string()],
+            Override::empty(),
+            false,
+        )?;
+        writer.add_bytes("retag_me/__init__.py", b"print('hello')\n")?;
+        Ok(writer.finish()?)
+    }
+
+    #[test]
+    fn test_parse_portable_linux_tag() -> Result<()> {
+        assert_eq!(
+            parse_portable_linux_tag("manylinux_2_28_x86_64", &Policies::default())?,
+            Some((PlatformTag::Manylinux { x: 2, y: 28 }, Arch::X86_64))
+        );
+        assert_eq!(
+            parse_portable_linux_tag("manylinux2014_aarch64", &Policies::default())?,
+            Some((PlatformTag::manylinux2014(), Arch::Aarch64))
+        );
+        assert_eq!(
+            parse_portable_linux_tag("musllinux_1_2_armv7l", &Policies::default())?,
+            Some((PlatformTag::Musllinux { x: 1, y: 2 }, Arch::Armv7L))
+        );
+        assert_eq!(
+            parse_portable_linux_tag("linux_x86_64", &Policies::default())?,
+            None
+        );
+        assert_eq!(
+            parse_portable_linux_tag("macosx_11_0_arm64", &Policies::default())?,
+            None
+        );
+        assert!(parse_portable_linux_tag("manylinux_2_28_sparc", &Policies::default()).is_err());
+        assert!(parse_portable_linux_tag("manylinux2099_x86_64", &Policies::default()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rewrite_wheel_file() {
+        let wheel_file = "Wheel-Version: 1.0\nGenerator: maturin (1.5.1)\nRoot-Is-Purelib: false\nTag: py3-none-linux_x86_64\n";
+        assert_eq!(
+            rewrite_wheel_file(
+                wheel_file,
+                &[
+                    "py3-none-manylinux_2_17_x86_64".to_string(),
+                    "py3-none-manylinux2014_x86_64".to_string()
+                ],
+                Some("1")
+            ),
+            "Wheel-Version: 1.0\nGenerator: maturin (1.5.1)\nRoot-Is-Purelib: false\nBuild: 1\n\
+            Tag: py3-none-manylinux_2_17_x86_64\nTag: py3-none-manylinux2014_x86_64\n"
+        );
+    }
+
+    #[test]
+    fn test_retag() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let wheel = build_wheel(tmp_dir.path())?;
+        let options = RetagOptions::try_parse_from([
+            "retag",
+            wheel.to_str().unwrap(),
+            "--platform-tag",
+            "manylinux_2_17_x86_64",
+            "--platform-tag",
+            "manylinux2014_x86_64",
+            "--build-tag",
+            "2",
+        ])?;
+        let retagged = retag(&options)?;
+        assert_eq!(
+            retagged[0].file_name().unwrap(),
+            "retag_me-0.1-2-py3-none-manylinux_2_17_x86_64.manylinux2014_x86_64.whl"
+        );
+        let distribution = InspectedDistribution::open(&retagged[0])?;
+        let wheel_file = distribution.wheel.unwrap();
+        assert_eq!(wheel_file.build.as_deref(), Some("2"));
+        assert_eq!(
+            wheel_file.tags,
+            [
+                "py3-none-manylinux_2_17_x86_64",
+                "py3-none-manylinux2014_x86_64"
+            ]
+        );
+        // The original wheel is kept
+        assert!(wheel.is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn test_retag_invalid() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let wheel = build_wheel(tmp_dir.path())?;
+        let wheel = wheel.to_str().unwrap();
+        for args in [
+            vec!["retag", wheel],
+            vec!["retag", wheel, "--build-tag", "a1"],
+            vec!["retag", wheel, "--python-tag", "py3-none"],
+            vec!["retag", wheel, "--platform-tag", "manylinux_2_17_mips"],
+        ] {
+            assert!(retag(&RetagOptions::try_parse_from(&args)?).is_err());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,488 @@
+//! Software bill of materials for the contents of a wheel, stored in `.dist-info/sboms/`
+//! as specified by PEP 770
+use crate::build_context::GraftedLibrary;
+use crate::build_options::extract_cargo_metadata_args;
+use crate::module_writer::source_date_epoch;
+use crate::{BuildContext, Metadata23, ModuleWriter};
+use anyhow::{Context, Result};
+use cargo_metadata::{DependencyKind, Metadata, MetadataCommand, Package, PackageId};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::env;
+use std::path::PathBuf;
+use tracing::debug;
+
+/// The SBOM document format
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum SbomFormat {
+    /// CycloneDX 1.5 JSON
+    #[serde(rename = "cyclonedx", alias = "cyclonedx-json")]
+    #[value(name = "cyclonedx")]
+    CycloneDx,
+    /// SPDX 2.3 JSON
+    #[serde(alias = "spdx-json")]
+    Spdx,
+}
+
+impl SbomFormat {
+    /// The file extension conventionally used for the format
+    fn extension(&self) -> &'static str {
+        match self {
+            SbomFormat::CycloneDx => "cdx.json",
+            SbomFormat::Spdx => "spdx.json",
+        }
+    }
+}
+
+/// A rust crate or a shared library that ended up in the wheel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Component {
+    /// Unique reference within the document, the purl for crates
+    id: String,
+    name: String,
+    version: Option<String>,
+    description: Option<String>,
+    /// SPDX license expression from Cargo.toml
+    license: Option<String>,
+    purl: Option<String>,
+    /// Hex encoded sha256, only known for shared libraries
+    sha256: Option<String>,
+    /// The location inside the wheel, only for shared libraries
+    path: Option<PathBuf>,
+    /// The ids of the crates this one depends on
+    dependencies: Vec<String>,
+}
+
+impl Component {
+    fn from_package(package: &Package) -> Self {
+        let purl = format!("pkg:cargo/{}@{}", package.name, package.version);
+        Self {
+            id: purl.clone(),
+            name: package.name.clone(),
+            version: Some(package.version.to_string()),
+            description: package.description.clone(),
+            // Cargo still accepts the deprecated `MIT/Apache-2.0` syntax
+            license: package
+                .license
+                .as_ref()
+                .map(|license| license.replace('/', " OR ")),
+            purl: Some(purl),
+            sha256: None,
+            path: None,
+            dependencies: Vec::new(),
+        }
+    }
+
+    fn from_grafted_library(library: &GraftedLibrary) -> Result<Self> {
+        let sha256 = format!("{:x}", Sha256::digest(fs_err::read(&library.source)?));
+        Ok(Self {
+            id: format!("file:{}", library.path.display()),
+            name: library.name.clone(),
+            version: None,
+            description: None,
+            license: None,
+            purl: None,
+            sha256: Some(sha256),
+            path: Some(library.path.clone()),
+            dependencies: Vec::new(),
+        })
+    }
+}
+
+/// A crate that is compiled into the wheel, with the crates it directly depends on
+#[derive(Debug, Clone)]
+pub(crate) struct CompiledCrate {
+    pub(crate) package: Package,
+    pub(crate) dependencies: Vec<PackageId>,
+}
+
+/// Returns the crate that is built and all crates that are compiled into it for the target,
+/// the former being the first element
+///
+/// The crates come from `cargo metadata --filter-platform`, with the same features as the build,
+/// and only normal dependencies are followed. Proc macros and build dependencies only run on the
+/// host and are therefore not part of the wheel. `cargo metadata` only runs once per build
+/// context, all wheels of a build contain the same crates.
+pub(crate) fn compiled_crates(context: &BuildContext) -> Result<&[CompiledCrate]> {
+    context
+        .compiled_crates
+        .get_or_try_init(|| resolve_compiled_crates(context))
+        .map(Vec::as_slice)
+}
+
+fn resolve_compiled_crates(context: &BuildContext) -> Result<Vec<CompiledCrate>> {
+    let mut args = extract_cargo_metadata_args(&context.cargo_options)?;
+    let triples = if context.universal2 {
+        vec!["x86_64-apple-darwin", "aarch64-apple-darwin"]
+    } else {
+        vec![context.target.target_triple()]
+    };
+    for triple in triples {
+        args.push("--filter-platform".to_string());
+        args.push(triple.to_string());
+    }
+    let metadata: Metadata = MetadataCommand::new()
+        .cargo_path("cargo")
+        .manifest_path(&context.manifest_path)
+        .other_options(args)
+        .exec()
+        .context("Failed to resolve the compiled crates with cargo metadata")?;
+
+    let resolve = metadata
+        .resolve
+        .as_ref()
+        .context("cargo metadata didn't return a dependency graph")?;
+    let root = resolve
+        .root
+        .as_ref()
+        .context("cargo metadata didn't return a root package")?;
+    let packages: HashMap<&PackageId, &Package> =
+        metadata.packages.iter().map(|pkg| (&pkg.id, pkg)).collect();
+    let nodes: HashMap<&PackageId, _> = resolve.nodes.iter().map(|node| (&node.id, node)).collect();
+
+    let mut crates = Vec::new();
+    let mut seen = BTreeSet::from([root]);
+    let mut queue = VecDeque::from([root]);
+    while let Some(id) = queue.pop_front() {
+        let mut dependencies = Vec::new();
+        for dep in &nodes[id].deps {
+            if !dep
+                .dep_kinds
+                .iter()
+                .any(|info| info.kind == DependencyKind::Normal)
+            {
+                continue;
+            }
+            let is_proc_macro = packages[&dep.pkg]
+                .targets
+                .iter()
+                .any(|target| target.kind.iter().any(|kind| kind == "proc-macro"));
+            if is_proc_macro {
+                continue;
+            }
+            dependencies.push(dep.pkg.clone());
+            if seen.insert(&dep.pkg) {
+                queue.push_back(&dep.pkg);
+            }
+        }
+        crates.push(CompiledCrate {
+            package: packages[id].clone(),
+            dependencies,
+        });
+    }
+    // Keep the built crate first and sort the rest, so the output doesn't depend on the
+    // order of the traversal
+    crates[1..].sort_by(|a, b| {
+        (&a.package.name, &a.package.version).cmp(&(&b.package.name, &b.package.version))
+    });
+    debug!("Found {} compiled crates", crates.len());
+    Ok(crates)
+}
+
+/// Formats the timestamp of the document, taken from `SOURCE_DATE_EPOCH` if set so that
+/// reproducible builds stay reproducible
+fn timestamp(reproducible: bool) -> Result<String> {
+    let now = if reproducible || env::var_os("SOURCE_DATE_EPOCH").is_some() {
+        let epoch = source_date_epoch(reproducible)
+            .context("SOURCE_DATE_EPOCH must be a unix timestamp")?;
+        time::OffsetDateTime::from_unix_timestamp(epoch)?
+    } else {
+        time::OffsetDateTime::now_utc()
+    };
+    Ok(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        now.year(),
+        u8::from(now.month()),
+        now.day(),
+        now.hour(),
+        now.minute(),
+        now.second()
+    ))
+}
+
+fn cyclonedx(root: &Component, components: &[Component], timestamp: &str) -> Value {
+    let component = |component: &Component| {
+        let mut value = json!({
+            "type": "library",
+            "bom-ref": component.id,
+            "name": component.name,
+        });
+        if let Some(version) = &component.version {
+            value["version"] = json!(version);
+        }
+        if let Some(description) = &component.description {
+            value["description"] = json!(description);
+        }
+        if let Some(license) = &component.license {
+            value["licenses"] = json!([{ "expression": license }]);
+        }
+        if let Some(purl) = &component.purl {
+            value["purl"] = json!(purl);
+        }
+        if let Some(sha256) = &component.sha256 {
+            value["hashes"] = json!([{ "alg": "SHA-256", "content": sha256 }]);
+        }
+        if let Some(path) = &component.path {
+            value["properties"] = json!([{ "name": "maturin:path", "value": path }]);
+        }
+        value
+    };
+    let dependencies: Vec<Value> = [root]
+        .into_iter()
+        .chain(components)
+        .filter(|component| component.purl.is_some())
+        .map(|component| json!({ "ref": component.id, "dependsOn": component.dependencies }))
+        .collect();
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "metadata": {
+            "timestamp": timestamp,
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                }]
+            },
+            "component": component(root),
+        },
+        "components": components.iter().map(component).collect::<Vec<_>>(),
+        "dependencies": dependencies,
+    })
+}
+
+fn spdx(
+    metadata23: &Metadata23,
+    root: &Component,
+    components: &[Component],
+    timestamp: &str,
+) -> Value {
+    let all: Vec<&Component> = [root].into_iter().chain(components).collect();
+    let spdx_ids: HashMap<&str, String> = all
+        .iter()
+        .enumerate()
+        .map(|(idx, component)| (component.id.as_str(), format!("SPDXRef-Package-{idx}")))
+        .collect();
+    let packages: Vec<Value> = all
+        .iter()
+        .map(|component| {
+            let mut value = json!({
+                "name": component.name,
+                "SPDXID": spdx_ids[component.id.as_str()],
+                "downloadLocation": "NOASSERTION",
+                "filesAnalyzed": false,
+                "licenseConcluded": "NOASSERTION",
+                "licenseDeclared": component.license.as_deref().unwrap_or("NOASSERTION"),
+                "copyrightText": "NOASSERTION",
+            });
+            if let Some(version) = &component.version {
+                value["versionInfo"] = json!(version);
+            }
+            if let Some(description) = &component.description {
+                value["description"] = json!(description);
+            }
+            if let Some(purl) = &component.purl {
+                value["externalRefs"] = json!([{
+                    "referenceCategory": "PACKAGE-MANAGER",
+                    "referenceType": "purl",
+                    "referenceLocator": purl,
+                }]);
+            }
+            if let Some(sha256) = &component.sha256 {
+                value["checksums"] = json!([{ "algorithm": "SHA256", "checksumValue": sha256 }]);
+            }
+            if let Some(path) = &component.path {
+                value["packageFileName"] = json!(path);
+            }
+            value
+        })
+        .collect();
+
+    let root_id = &spdx_ids[root.id.as_str()];
+    let mut relationships = vec![json!({
+        "spdxElementId": "SPDXRef-DOCUMENT",
+        "relationshipType": "DESCRIBES",
+        "relatedSpdxElement": root_id,
+    })];
+    for component in &all {
+        for dependency in &component.dependencies {
+            relationships.push(json!({
+                "spdxElementId": spdx_ids[component.id.as_str()],
+                "relationshipType": "DEPENDS_ON",
+                "relatedSpdxElement": spdx_ids[dependency.as_str()],
+            }));
+        }
+    }
+    for component in components.iter().filter(|c| c.purl.is_none()) {
+        relationships.push(json!({
+            "spdxElementId": root_id,
+            "relationshipType": "CONTAINS",
+            "relatedSpdxElement": spdx_ids[component.id.as_str()],
+        }));
+    }
+
+    // The namespace must be unique per document, so we derive it from the contents
+    let name = format!(
+        "{}-{}",
+        metadata23.get_distribution_escaped(),
+        metadata23.get_version_escaped()
+    );
+    let content_hash = format!(
+        "{:x}",
+        Sha256::digest(serde_json::to_vec(&packages).unwrap_or_default())
+    );
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": name,
+        "documentNamespace": format!("https://spdx.org/spdxdocs/{name}-{content_hash}"),
+        "creationInfo": {
+            "created": timestamp,
+            "creators": [format!("Tool: {}-{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))],
+        },
+        "packages": packages,
+        "relationships": relationships,
+    })
+}
+
+/// Generates the SBOM for a wheel and writes it to `.dist-info/sboms/`
+///
+/// Lists the crates compiled into the wheel and the external shared libraries that were grafted
+/// into it
+pub fn write_sbom(
+    writer: &mut impl ModuleWriter,
+    context: &BuildContext,
+    metadata23: &Metadata23,
+    format: SbomFormat,
+    grafted_libraries: &[GraftedLibrary],
+) -> Result<()> {
+    let crates = compiled_crates(context)?;
+    let purls: HashMap<&PackageId, String> = crates
+        .iter()
+        .map(|compiled| {
+            (
+                &compiled.package.id,
+                Component::from_package(&compiled.package).id,
+            )
+        })
+        .collect();
+    let mut components = crates.iter().map(|compiled| {
+        let mut component = Component::from_package(&compiled.package);
+        component.dependencies = compiled
+            .dependencies
+            .iter()
+            .map(|id| purls[id].clone())
+            .collect();
+        component
+    });
+    let root = components
+        .next()
+        .context("The SBOM is missing the built crate")?;
+    let mut components: Vec<Component> = components.collect();
+    for library in grafted_libraries {
+        components.push(Component::from_grafted_library(library)?);
+    }
+
+    let timestamp = timestamp(context.reproducible)?;
+    let document = match format {
+        SbomFormat::CycloneDx => cyclonedx(&root, &components, &timestamp),
+        SbomFormat::Spdx => spdx(metadata23, &root, &components, &timestamp),
+    };
+    let target = metadata23.get_dist_info_dir().join("sboms").join(format!(
+        "{}.{}",
+        context.crate_name,
+        format.extension()
+    ));
+    writer.add_bytes(target, serde_json::to_string_pretty(&document)?.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pep440_rs::Version;
+
+    fn components() -> (Component, Vec<Component>) {
+        let mut root = Component {
+            id: "pkg:cargo/my-project@0.1.0".to_string(),
+            name: "my-project".to_string(),
+            version: Some("0.1.0".to_string()),
+            description: None,
+            license: Some("MIT".to_string()),
+            purl: Some("pkg:cargo/my-project@0.1.0".to_string()),
+            sha256: None,
+            path: None,
+            dependencies: Vec::new(),
+        };
+        let dependency = Component {
+            id: "pkg:cargo/libc@0.2.153".to_string(),
+            name: "libc".to_string(),
+            version: Some("0.2.153".to_string()),
+            description: Some("Raw FFI bindings".to_string()),
+            license: Some("MIT OR Apache-2.0".to_string()),
+            purl: Some("pkg:cargo/libc@0.2.153".to_string()),
+            sha256: None,
+            path: None,
+            dependencies: Vec::new(),
+        };
+        root.dependencies.push(dependency.id.clone());
+        let library = Component::from_grafted_library(&GraftedLibrary {
+            name: "libfoo.so.1".to_string(),
+            source: PathBuf::from(file!()),
+            path: PathBuf::from("my_project.libs/libfoo-12345678.so.1"),
+        })
+        .unwrap();
+        (root, vec![dependency, library])
+    }
+
+    #[test]
+    fn cyclonedx_document() {
+        let (root, components) = components();
+        let document = cyclonedx(&root, &components, "1980-01-01T00:00:00Z");
+        assert_eq!(document["bomFormat"], "CycloneDX");
+        assert_eq!(document["metadata"]["component"]["name"], "my-project");
+        assert_eq!(document["components"][0]["purl"], "pkg:cargo/libc@0.2.153");
+        assert_eq!(
+            document["components"][0]["licenses"][0]["expression"],
+            "MIT OR Apache-2.0"
+        );
+        assert_eq!(document["components"][1]["name"], "libfoo.so.1");
+        assert_eq!(document["components"][1]["hashes"][0]["alg"], "SHA-256");
+        assert_eq!(
+            document["dependencies"][0],
+            json!({ "ref": "pkg:cargo/my-project@0.1.0", "dependsOn": ["pkg:cargo/libc@0.2.153"] })
+        );
+    }
+
+    #[test]
+    fn spdx_document() {
+        let (root, components) = components();
+        let metadata23 = Metadata23::new("my-project".to_string(), Version::new([0, 1, 0]));
+        let document = spdx(&metadata23, &root, &components, "1980-01-01T00:00:00Z");
+        assert_eq!(document["spdxVersion"], "SPDX-2.3");
+        assert_eq!(document["packages"].as_array().unwrap().len(), 3);
+        assert_eq!(
+            document["packages"][1]["licenseDeclared"],
+            "MIT OR Apache-2.0"
+        );
+        assert_eq!(document["packages"][2]["licenseDeclared"], "NOASSERTION");
+        let relationships: Vec<_> = document["relationships"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["relationshipType"].as_str().unwrap())
+            .collect();
+        assert_eq!(relationships, ["DESCRIBES", "DEPENDS_ON", "CONTAINS"]);
+        assert!(document["documentNamespace"]
+            .as_str()
+            .unwrap()
+            .starts_with("https://spdx.org/spdxdocs/my_project-0.1.0-"));
+    }
+}
@@ -1,5 +1,7 @@
+use crate::metadata::parse_local_version;
 use crate::module_writer::{add_data, ModuleWriter};
-use crate::pyproject_toml::SdistGenerator;
+use crate::pyproject_toml::{SdistGenerator, VersionSource};
+use crate::vcs_version::pep440_to_semver;
 use crate::{pyproject_toml::Format, BuildContext, PyProjectToml, SDistWriter};
 use anyhow::{bail, Context, Result};
 use cargo_metadata::{Metadata, MetadataCommand, PackageId};
@@ -7,6 +9,7 @@ use fs_err as fs;
 use ignore::overrides::Override;
 use normpath::PathExt as _;
 use path_slash::PathExt as _;
+use pep440_rs::Version;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -21,15 +24,48 @@ use tracing::debug;
 /// foo = { path = "path/to/foo" }
 /// ```
 #[derive(Debug, Clone)]
-struct PathDependency {
+pub(crate) struct PathDependency {
     /// `Cargo.toml` path of the path dependency
-    manifest_path: PathBuf,
+    pub(crate) manifest_path: PathBuf,
     /// workspace root of the path dependency
     workspace_root: PathBuf,
     /// readme path of the path dependency
     readme: Option<PathBuf>,
 }
 
+/// The version determined by `tool.maturin.version.source = "vcs"`, which is written into the
+/// Cargo.toml and pyproject.toml of the source distribution because there's no git repository
+/// when building from the source distribution
+#[derive(Debug, Clone)]
+struct VcsVersion {
+    /// The version for pyproject.toml
+    pep440: Version,
+    /// The version for Cargo.toml and Cargo.lock
+    semver: semver::Version,
+}
+
+/// Settings that can't be determined anymore when building from the source distribution and are
+/// therefore written into its pyproject.toml
+#[derive(Debug, Clone, Default)]
+struct SdistOverrides {
+    /// The version from git, which is also written to Cargo.toml and Cargo.lock
+    vcs_version: Option<VcsVersion>,
+    /// `--build-tag`, if it differs from `tool.maturin.build-tag`
+    build_tag: Option<String>,
+    /// `--local-version`, if it differs from `tool.maturin.local-version`
+    local_version: Option<String>,
+}
+
+impl SdistOverrides {
+    fn is_empty(&self) -> bool {
+        self.vcs_version.is_none() && self.build_tag.is_none() && self.local_version.is_none()
+    }
+
+    fn semver(&self) -> Option<&semver::Version> {
+        self.vcs_version.as_ref().map(|version| &version.semver)
+    }
+}
+
 fn parse_toml_file(path: &Path, kind: &str) -> Result<toml_edit::Document> {
     let text = fs::read_to_string(path)?;
     let document = text.parse::<toml_edit::Document>().context(format!(
@@ -40,18 +76,38 @@ fn parse_toml_file(path: &Path, kind: &str) -> Result<toml_edit::Document> {
     Ok(document)
 }
 
+/// Sets `package.version` in a Cargo.toml, replacing `version.workspace = true`
+fn set_cargo_toml_version(
+    document: &mut toml_edit::Document,
+    manifest_path: &Path,
+    version: &semver::Version,
+) -> Result<()> {
+    let package = document
+        .get_mut("package")
+        .and_then(|x| x.as_table_like_mut())
+        .with_context(|| format!("`[package]` must be a table in {}", manifest_path.display()))?;
+    package.insert("version", toml_edit::value(version.to_string()));
+    Ok(())
+}
+
 /// Rewrite Cargo.toml to only retain path dependencies that are actually used
+/// and to set the version from git, if any
 ///
 /// We only want to add path dependencies that are actually used
 /// to reduce the size of the source distribution.
 fn rewrite_cargo_toml(
     manifest_path: impl AsRef<Path>,
     known_path_deps: &HashMap<String, PathDependency>,
+    version: Option<&semver::Version>,
 ) -> Result<String> {
     let manifest_path = manifest_path.as_ref();
     debug!("Rewriting Cargo.toml at {}", manifest_path.display());
     let mut document = parse_toml_file(manifest_path, "Cargo.toml")?;
 
+    if let Some(version) = version {
+        set_cargo_toml_version(&mut document, manifest_path, version)?;
+    }
+
     // Update workspace members
     if let Some(workspace) = document.get_mut("workspace").and_then(|x| x.as_table_mut()) {
         if let Some(members) = workspace.get_mut("members").and_then(|x| x.as_array()) {
@@ -103,13 +159,61 @@ fn rewrite_cargo_toml(
     Ok(document.to_string())
 }
 
+/// Sets the version of the root crate in Cargo.lock, so that `--locked` builds from the source
+/// distribution work after the version in Cargo.toml was changed
+fn rewrite_cargo_lock(
+    cargo_lock_path: &Path,
+    package: &cargo_metadata::Package,
+    version: &semver::Version,
+) -> Result<String> {
+    let mut document = parse_toml_file(cargo_lock_path, "Cargo.lock")?;
+    if let Some(packages) = document
+        .get_mut("package")
+        .and_then(|x| x.as_array_of_tables_mut())
+    {
+        for locked in packages.iter_mut() {
+            // Path dependencies and workspace members don't have a source
+            if locked.get("name").and_then(|x| x.as_str()) == Some(package.name.as_str())
+                && locked.get("version").and_then(|x| x.as_str())
+                    == Some(package.version.to_string().as_str())
+                && !locked.contains_key("source")
+            {
+                locked.insert("version", toml_edit::value(version.to_string()));
+            }
+        }
+    }
+    Ok(document.to_string())
+}
+
 /// When `pyproject.toml` is inside the Cargo workspace root,
 /// we need to update `tool.maturin.manifest-path` in `pyproject.toml`.
+///
+/// With a version from git, `project.version` is set to it and the version is removed from
+/// `project.dynamic` and `tool.maturin.version`. The build tag and the local version label from
+/// the command line are written to `tool.maturin`.
 fn rewrite_pyproject_toml(
     pyproject_toml_path: &Path,
-    relative_manifest_path: &Path,
+    relative_manifest_path: Option<&Path>,
+    overrides: &SdistOverrides,
 ) -> Result<String> {
     let mut data = parse_toml_file(pyproject_toml_path, "pyproject.toml")?;
+    let version = overrides
+        .vcs_version
+        .as_ref()
+        .map(|version| &version.pep440);
+    // Without a `[project]` table, the version is taken from the rewritten Cargo.toml
+    if let (Some(version), Some(project)) = (
+        version,
+        data.get_mut("project").and_then(|x| x.as_table_like_mut()),
+    ) {
+        project.insert("version", toml_edit::value(version.to_string()));
+        if let Some(dynamic) = project.get_mut("dynamic").and_then(|x| x.as_array_mut()) {
+            dynamic.retain(|field| field.as_str() != Some("version"));
+            if dynamic.is_empty() {
+                project.remove("dynamic");
+            }
+        }
+    }
     let tool = data
         .entry("tool")
         .or_insert_with(|| toml_edit::Item::Table(toml_edit::Table::new()))
@@ -131,11 +235,22 @@ fn rewrite_pyproject_toml(
             )
         })?;
 
-    maturin.remove("manifest-path");
-    maturin.insert(
-        "manifest-path",
-        toml_edit::value(relative_manifest_path.to_str().unwrap()),
-    );
+    if let Some(relative_manifest_path) = relative_manifest_path {
+        maturin.remove("manifest-path");
+        maturin.insert(
+            "manifest-path",
+            toml_edit::value(relative_manifest_path.to_str().unwrap()),
+        );
+    }
+    if version.is_some() {
+        maturin.remove("version");
+    }
+    if let Some(build_tag) = &overrides.build_tag {
+        maturin.insert("build-tag", toml_edit::value(build_tag));
+    }
+    if let Some(local_version) = &overrides.local_version {
+        maturin.insert("local-version", toml_edit::value(local_version));
+    }
 
     Ok(data.to_string())
 }
@@ -151,6 +266,7 @@ fn add_crate_to_source_distribution(
     known_path_deps: &HashMap<String, PathDependency>,
     root_crate: bool,
     skip_cargo_toml: bool,
+    version: Option<&semver::Version>,
 ) -> Result<()> {
     let manifest_path = manifest_path.as_ref();
     let output = Command::new("cargo")
@@ -217,7 +333,7 @@ fn add_crate_to_source_distribution(
     let cargo_toml_path = prefix.join(manifest_path.file_name().unwrap());
 
     if root_crate {
-        let rewritten_cargo_toml = rewrite_cargo_toml(manifest_path, known_path_deps)?;
+        let rewritten_cargo_toml = rewrite_cargo_toml(manifest_path, known_path_deps, version)?;
         writer.add_bytes(cargo_toml_path, rewritten_cargo_toml.as_bytes())?;
     } else if !skip_cargo_toml {
         writer.add_file(cargo_toml_path, manifest_path)?;
@@ -231,7 +347,7 @@ fn add_crate_to_source_distribution(
 }
 
 /// Finds all path dependencies of the crate
-fn find_path_deps(cargo_metadata: &Metadata) -> Result<HashMap<String, PathDependency>> {
+pub(crate) fn find_path_deps(cargo_metadata: &Metadata) -> Result<HashMap<String, PathDependency>> {
     let root = cargo_metadata
         .root_package()
         .context("Expected the dependency graph to have a root package")?;
@@ -324,6 +440,92 @@ fn find_path_deps(cargo_metadata: &Metadata) -> Result<HashMap<String, PathDepen
     Ok(path_deps)
 }
 
+/// Adds the rewritten pyproject.toml and, with a version from git, Cargo.toml and Cargo.lock for
+/// the git sdist generator, before the unchanged files from git are added
+fn add_git_rewritten_files_to_sdist(
+    build_context: &BuildContext,
+    pyproject_toml_path: &Path,
+    writer: &mut SDistWriter,
+    prefix: &Path,
+    overrides: &SdistOverrides,
+) -> Result<()> {
+    let pyproject_dir = pyproject_toml_path.parent().unwrap();
+    let pyproject_toml = rewrite_pyproject_toml(pyproject_toml_path, None, overrides)?;
+    writer.add_bytes(prefix.join("pyproject.toml"), pyproject_toml.as_bytes())?;
+    let Some(vcs_version) = &overrides.vcs_version else {
+        return Ok(());
+    };
+
+    let manifest_path = build_context
+        .manifest_path
+        .normalize()
+        .with_context(|| {
+            format!(
+                "failed to normalize manifest path `{}`",
+                build_context.manifest_path.display()
+            )
+        })?
+        .into_path_buf();
+    if let Ok(relative_manifest_path) = manifest_path.strip_prefix(pyproject_dir) {
+        let mut document = parse_toml_file(&manifest_path, "Cargo.toml")?;
+        set_cargo_toml_version(&mut document, &manifest_path, &vcs_version.semver)?;
+        writer.add_bytes(
+            prefix.join(relative_manifest_path),
+            document.to_string().as_bytes(),
+        )?;
+    }
+
+    let main_crate = build_context.cargo_metadata.root_package().unwrap();
+    let workspace_root = build_context.cargo_metadata.workspace_root.as_std_path();
+    for cargo_lock_path in [
+        manifest_path.with_file_name("Cargo.lock"),
+        workspace_root.join("Cargo.lock"),
+    ] {
+        if let Ok(relative_cargo_lock) = cargo_lock_path.strip_prefix(pyproject_dir) {
+            if cargo_lock_path.is_file() {
+                let cargo_lock =
+                    rewrite_cargo_lock(&cargo_lock_path, main_crate, &vcs_version.semver)?;
+                writer.add_bytes(prefix.join(relative_cargo_lock), cargo_lock.as_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Collects the settings that must be written into the pyproject.toml of the source distribution
+fn sdist_overrides(
+    build_context: &BuildContext,
+    pyproject: &PyProjectToml,
+) -> Result<SdistOverrides> {
+    let vcs_version = match pyproject.version_source() {
+        VersionSource::Cargo => None,
+        VersionSource::Vcs => {
+            // The local version label is applied again when building from the source distribution
+            let mut version = build_context.metadata23.version.clone();
+            if let Some(local_version) = &build_context.local_version {
+                let mut local = version.local().to_vec();
+                local.truncate(local.len() - parse_local_version(local_version)?.len());
+                version = version.with_local(local);
+            }
+            Some(VcsVersion {
+                semver: pep440_to_semver(&version)?,
+                pep440: version,
+            })
+        }
+    };
+    Ok(SdistOverrides {
+        vcs_version,
+        build_tag: build_context
+            .build_tag
+            .clone()
+            .filter(|build_tag| Some(build_tag.as_str()) != pyproject.build_tag()),
+        local_version: build_context
+            .local_version
+            .clone()
+            .filter(|local_version| Some(local_version.as_str()) != pyproject.local_version()),
+    })
+}
+
 /// Copies the files of git to a source distribution
 ///
 /// Runs `git ls-files -z` to obtain a list of files to package.
@@ -369,6 +571,7 @@ fn add_cargo_package_files_to_sdist(
     pyproject_toml_path: &Path,
     writer: &mut SDistWriter,
     root_dir: &Path,
+    overrides: &SdistOverrides,
 ) -> Result<()> {
     let manifest_path = &build_context.manifest_path;
     let workspace_root = &build_context.cargo_metadata.workspace_root;
@@ -412,6 +615,7 @@ fn add_cargo_package_files_to_sdist(
             &known_path_deps,
             false,
             skip_cargo_toml,
+            None,
         )
         .with_context(|| {
             format!(
@@ -467,6 +671,7 @@ fn add_cargo_package_files_to_sdist(
         &known_path_deps,
         true,
         false,
+        overrides.semver(),
     )?;
     // Handle possible relative readme field in Cargo.toml
     if let Some(readme) = main_crate.readme.as_ref() {
@@ -501,7 +706,12 @@ fn add_cargo_package_files_to_sdist(
                 pyproject_root
             };
         let relative_cargo_lock = cargo_lock_path.strip_prefix(project_root).unwrap();
-        writer.add_file(root_dir.join(relative_cargo_lock), &cargo_lock_path)?;
+        if let Some(version) = overrides.semver() {
+            let cargo_lock = rewrite_cargo_lock(&cargo_lock_path, main_crate, version)?;
+            writer.add_bytes(root_dir.join(relative_cargo_lock), cargo_lock.as_bytes())?;
+        } else {
+            writer.add_file(root_dir.join(relative_cargo_lock), &cargo_lock_path)?;
+        }
         if use_workspace_cargo_lock {
             let relative_workspace_cargo_toml = relative_cargo_lock.with_file_name("Cargo.toml");
             let mut deps_to_keep = known_path_deps.clone();
@@ -520,7 +730,8 @@ fn add_cargo_package_files_to_sdist(
                     readme: None,
                 },
             );
-            let workspace_cargo_toml = rewrite_cargo_toml(workspace_manifest_path, &deps_to_keep)?;
+            let workspace_cargo_toml =
+                rewrite_cargo_toml(workspace_manifest_path, &deps_to_keep, None)?;
             writer.add_bytes(
                 root_dir.join(relative_workspace_cargo_toml),
                 workspace_cargo_toml.as_bytes(),
@@ -537,11 +748,14 @@ fn add_cargo_package_files_to_sdist(
 
     // Add pyproject.toml
     let pyproject_dir = pyproject_toml_path.parent().unwrap();
-    if pyproject_dir != sdist_root {
+    if pyproject_dir != sdist_root || !overrides.is_empty() {
         // rewrite `tool.maturin.manifest-path` in pyproject.toml
+        let relative_manifest_path = (pyproject_dir != sdist_root)
+            .then(|| relative_main_crate_manifest_dir.join("Cargo.toml"));
         let rewritten_pyproject_toml = rewrite_pyproject_toml(
             pyproject_toml_path,
-            &relative_main_crate_manifest_dir.join("Cargo.toml"),
+            relative_manifest_path.as_deref(),
+            overrides,
         )?;
         writer.add_bytes(
             root_dir.join("pyproject.toml"),
@@ -613,21 +827,37 @@ pub fn source_distribution(
         })?
         .into_path_buf();
     let metadata23 = &build_context.metadata23;
-    let mut writer = SDistWriter::new(&build_context.out, metadata23, excludes)?;
+    let mut writer = SDistWriter::new(
+        &build_context.out,
+        metadata23,
+        excludes,
+        build_context.reproducible,
+    )?;
     let root_dir = PathBuf::from(format!(
         "{}-{}",
         &metadata23.get_distribution_escaped(),
         &metadata23.get_version_escaped()
     ));
 
+    let overrides = sdist_overrides(build_context, pyproject)?;
     match pyproject.sdist_generator() {
         SdistGenerator::Cargo => add_cargo_package_files_to_sdist(
             build_context,
             &pyproject_toml_path,
             &mut writer,
             &root_dir,
+            &overrides,
         )?,
         SdistGenerator::Git => {
+            if !overrides.is_empty() {
+                add_git_rewritten_files_to_sdist(
+                    build_context,
+                    &pyproject_toml_path,
+                    &mut writer,
+                    &root_dir,
+                    &overrides,
+                )?;
+            }
             add_git_tracked_files_to_sdist(&pyproject_toml_path, &mut writer, &root_dir)?
         }
     }
@@ -656,6 +886,14 @@ pub fn source_distribution(
             writer.add_file(root_dir.join(license), pyproject_dir.join(license))?;
         }
     }
+    // The policies are needed to build a wheel from the source distribution
+    if let Some(policy_file) = pyproject.policy_file() {
+        writer.add_file(root_dir.join(policy_file), pyproject_dir.join(policy_file))?;
+    }
+    // The `License-File` entries of PKG-INFO are relative to the root of the source distribution
+    for license_file in &metadata23.license_files {
+        writer.add_file(root_dir.join(&license_file.path), &license_file.source)?;
+    }
 
     let mut include = |pattern| -> Result<()> {
         eprintln!("📦 Including files matching \"{pattern}\"");
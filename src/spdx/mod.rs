@@ -0,0 +1,303 @@
+//! Parsing and validation of SPDX license expressions for the PEP 639 `License-Expression`
+use anyhow::{bail, format_err, Result};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fmt;
+
+/// The version of the SPDX license list that `licenses.txt` and `exceptions.txt` were taken from
+const SPDX_LICENSE_LIST_VERSION: &str = "3.27.0";
+
+/// The license identifiers of the SPDX license list, keyed by their lowercase form since
+/// identifiers are matched case-insensitively
+static LICENSES: Lazy<HashMap<String, &'static str>> = Lazy::new(|| {
+    include_str!("licenses.txt")
+        .lines()
+        .map(|id| (id.to_lowercase(), id))
+        .collect()
+});
+
+/// The license exception identifiers of the SPDX license list, keyed by their lowercase form
+static EXCEPTIONS: Lazy<HashMap<String, &'static str>> = Lazy::new(|| {
+    include_str!("exceptions.txt")
+        .lines()
+        .map(|id| (id.to_lowercase(), id))
+        .collect()
+});
+
+/// A parsed SPDX license expression, see
+/// <https://spdx.github.io/spdx-spec/v2.3/SPDX-license-expressions/>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LicenseExpression {
+    /// A single license, optionally with an exception
+    License {
+        /// The license identifier, either from the SPDX license list or a `LicenseRef-`
+        id: String,
+        /// A trailing `+`, meaning this version or any later version
+        or_later: bool,
+        /// The exception after `WITH`
+        exception: Option<String>,
+    },
+    /// All of the licenses apply
+    And(Vec<LicenseExpression>),
+    /// Any of the licenses can be chosen
+    Or(Vec<LicenseExpression>),
+}
+
+impl LicenseExpression {
+    /// Parses an expression and checks that all identifiers are on the SPDX license list,
+    /// normalizing their case
+    pub fn parse(expression: &str) -> Result<Self> {
+        let spaced = expression.replace('(', " ( ").replace(')', " ) ");
+        let mut parser = Parser {
+            tokens: spaced.split_whitespace().collect(),
+            position: 0,
+        };
+        if parser.tokens.is_empty() {
+            bail!("The license expression is empty");
+        }
+        let parsed = parser
+            .parse_or()
+            .map_err(|err| format_err!("Invalid SPDX license expression `{expression}`: {err}"))?;
+        if let Some(token) = parser.peek() {
+            bail!("Invalid SPDX license expression `{expression}`: unexpected `{token}`");
+        }
+        Ok(parsed)
+    }
+}
+
+impl fmt::Display for LicenseExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LicenseExpression::License {
+                id,
+                or_later,
+                exception,
+            } => {
+                write!(f, "{id}")?;
+                if *or_later {
+                    write!(f, "+")?;
+                }
+                if let Some(exception) = exception {
+                    write!(f, " WITH {exception}")?;
+                }
+                Ok(())
+            }
+            LicenseExpression::And(operands) => {
+                for (idx, operand) in operands.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, " AND ")?;
+                    }
+                    // AND binds stronger than OR
+                    if matches!(operand, LicenseExpression::Or(_)) {
+                        write!(f, "({operand})")?;
+                    } else {
+                        write!(f, "{operand}")?;
+                    }
+                }
+                Ok(())
+            }
+            LicenseExpression::Or(operands) => {
+                for (idx, operand) in operands.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, " OR ")?;
+                    }
+                    write!(f, "{operand}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    tokens: Vec<&'a str>,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.peek();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<&'a str> {
+        self.next()
+            .ok_or_else(|| format_err!("expected {expected}, found the end of the expression"))
+    }
+
+    /// Operators are case-insensitive in PEP 639
+    fn next_if_operator(&mut self, operator: &str) -> bool {
+        if self
+            .peek()
+            .is_some_and(|token| token.eq_ignore_ascii_case(operator))
+        {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<LicenseExpression> {
+        let mut operands = vec![self.parse_and()?];
+        while self.next_if_operator("OR") {
+            operands.push(self.parse_and()?);
+        }
+        Ok(if operands.len() == 1 {
+            operands.remove(0)
+        } else {
+            LicenseExpression::Or(operands)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<LicenseExpression> {
+        let mut operands = vec![self.parse_license()?];
+        while self.next_if_operator("AND") {
+            operands.push(self.parse_license()?);
+        }
+        Ok(if operands.len() == 1 {
+            operands.remove(0)
+        } else {
+            LicenseExpression::And(operands)
+        })
+    }
+
+    fn parse_license(&mut self) -> Result<LicenseExpression> {
+        let token = self.expect("a license")?;
+        if token == "(" {
+            let expression = self.parse_or()?;
+            if self.next() != Some(")") {
+                bail!("missing closing parenthesis");
+            }
+            return Ok(expression);
+        }
+        if token == ")"
+            || ["AND", "OR", "WITH"]
+                .iter()
+                .any(|op| token.eq_ignore_ascii_case(op))
+        {
+            bail!("expected a license, found `{token}`");
+        }
+        let (token, or_later) = match token.strip_suffix('+') {
+            Some(token) => (token, true),
+            None => (token, false),
+        };
+        let id = self.license_id(token)?;
+        let exception = if self.next_if_operator("WITH") {
+            let token = self.expect("an exception")?;
+            Some(self.exception_id(token)?)
+        } else {
+            None
+        };
+        Ok(LicenseExpression::License {
+            id,
+            or_later,
+            exception,
+        })
+    }
+
+    fn license_id(&self, token: &str) -> Result<String> {
+        check_idstring(token)?;
+        if let Some(reference) = strip_prefix_ignore_case(token, "LicenseRef-") {
+            return Ok(format!("LicenseRef-{reference}"));
+        }
+        match LICENSES.get(&token.to_lowercase()) {
+            Some(id) => Ok(id.to_string()),
+            None => bail!(
+                "unknown license `{token}`, see https://spdx.org/licenses/ for the SPDX license \
+                list {SPDX_LICENSE_LIST_VERSION}"
+            ),
+        }
+    }
+
+    fn exception_id(&self, token: &str) -> Result<String> {
+        check_idstring(token)?;
+        if let Some(reference) = strip_prefix_ignore_case(token, "AdditionRef-") {
+            return Ok(format!("AdditionRef-{reference}"));
+        }
+        match EXCEPTIONS.get(&token.to_lowercase()) {
+            Some(id) => Ok(id.to_string()),
+            None => bail!(
+                "unknown license exception `{token}`, see https://spdx.org/licenses/exceptions-index.html"
+            ),
+        }
+    }
+}
+
+/// Identifiers may only contain letters, numbers, `.` and `-`
+fn check_idstring(token: &str) -> Result<()> {
+    if token.is_empty()
+        || !token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+    {
+        bail!("invalid identifier `{token}`");
+    }
+    Ok(())
+}
+
+fn strip_prefix_ignore_case<'a>(token: &'a str, prefix: &str) -> Option<&'a str> {
+    if token.len() > prefix.len()
+        && token.is_char_boundary(prefix.len())
+        && token[..prefix.len()].eq_ignore_ascii_case(prefix)
+    {
+        Some(&token[prefix.len()..])
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_expressions() {
+        for (expression, expected) in [
+            ("MIT", "MIT"),
+            ("mit", "MIT"),
+            ("MIT or apache-2.0", "MIT OR Apache-2.0"),
+            (
+                "MIT AND (Apache-2.0 OR BSD-2-Clause)",
+                "MIT AND (Apache-2.0 OR BSD-2-Clause)",
+            ),
+            ("(MIT OR Apache-2.0)", "MIT OR Apache-2.0"),
+            ("GPL-2.0-only+", "GPL-2.0-only+"),
+            (
+                "gpl-2.0-or-later with classpath-exception-2.0",
+                "GPL-2.0-or-later WITH Classpath-exception-2.0",
+            ),
+            ("licenseref-Proprietary", "LicenseRef-Proprietary"),
+        ] {
+            assert_eq!(
+                LicenseExpression::parse(expression).unwrap().to_string(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for expression in [
+            "",
+            "MIT/Apache-2.0",
+            "Not-A-License",
+            "MIT OR",
+            "MIT AND (Apache-2.0",
+            "MIT Apache-2.0",
+            "MIT WITH Not-An-Exception",
+            "Apache-2.0 WITH",
+            "LicenseRef-",
+        ] {
+            assert!(
+                LicenseExpression::parse(expression).is_err(),
+                "{expression}"
+            );
+        }
+    }
+}
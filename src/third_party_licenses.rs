@@ -0,0 +1,136 @@
+//! Bundles the licenses of the third-party crates that are compiled into a wheel
+use crate::metadata::LicenseFile;
+use crate::pyproject_toml::{LicenseViolation, ThirdPartyLicenses};
+use crate::sbom::compiled_crates;
+use crate::BuildContext;
+use anyhow::{bail, Result};
+use cargo_metadata::Package;
+use fs_err as fs;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+/// File name prefixes that mark a license file in the root of a crate, compared case-insensitively
+const LICENSE_FILE_PREFIXES: [&str; 4] = ["license", "licence", "copying", "notice"];
+
+/// Finds the license files in the root of the crate source, including the `license-file`
+/// from its Cargo.toml
+fn find_license_files(package: &Package) -> Result<Vec<PathBuf>> {
+    let Some(package_dir) = package.manifest_path.parent() else {
+        return Ok(Vec::new());
+    };
+    let mut license_files = BTreeSet::new();
+    if let Some(license_file) = &package.license_file {
+        let license_file = package_dir.join(license_file);
+        if license_file.is_file() {
+            license_files.insert(license_file.into_std_path_buf());
+        }
+    }
+    for entry in fs::read_dir(package_dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_lowercase();
+        if LICENSE_FILE_PREFIXES
+            .iter()
+            .any(|prefix| file_name.starts_with(prefix))
+            && entry.file_type()?.is_file()
+        {
+            license_files.insert(entry.path());
+        }
+    }
+    Ok(license_files.into_iter().collect())
+}
+
+/// Checks the license expression of a crate against the deny list
+///
+/// A crate is denied if its expression mentions any denied identifier, even as one of several
+/// alternatives.
+fn is_denied(expression: &str, deny: &[String]) -> bool {
+    expression
+        .split(|c: char| c.is_whitespace() || "()/".contains(c))
+        .any(|license| {
+            deny.iter()
+                .any(|denied| denied.eq_ignore_ascii_case(license))
+        })
+}
+
+/// Collects the license files of the third-party crates that are compiled into the wheels, to
+/// be added to `.dist-info/licenses/<crate>-<version>/`
+///
+/// Crates without a `source`, i.e. path dependencies and workspace members, are considered part
+/// of the project itself and skipped. Crates without any license file or with a license on the
+/// deny list fail the build or print a warning, depending on the configuration.
+pub fn third_party_license_files(
+    context: &BuildContext,
+    config: &ThirdPartyLicenses,
+) -> Result<Vec<LicenseFile>> {
+    let mut license_files = Vec::new();
+    let mut violations = Vec::new();
+    for compiled in compiled_crates(context)?.iter().skip(1) {
+        let package = &compiled.package;
+        if package.source.is_none() {
+            continue;
+        }
+        let crate_dir = PathBuf::from(format!("{}-{}", package.name, package.version));
+        if let Some(license) = &package.license {
+            if is_denied(license, &config.deny) {
+                violations.push(format!(
+                    "{} {} is licensed under `{}`, which is on the deny list",
+                    package.name, package.version, license
+                ));
+            }
+        }
+        let found = find_license_files(package)?;
+        if found.is_empty() {
+            violations.push(format!(
+                "{} {} doesn't contain a license file",
+                package.name, package.version
+            ));
+        }
+        for source in found {
+            let path = crate_dir.join(source.file_name().unwrap());
+            license_files.push(LicenseFile { source, path });
+        }
+    }
+
+    if !violations.is_empty() {
+        match config.on_violation {
+            LicenseViolation::Fail => bail!(
+                "The licenses of the compiled crates don't pass the check:\n  - {}",
+                violations.join("\n  - ")
+            ),
+            LicenseViolation::Warn => {
+                for violation in violations {
+                    eprintln!("⚠️  Warning: {violation}");
+                }
+            }
+        }
+    }
+    eprintln!(
+        "📦 Including {} license files of third-party crates",
+        license_files.len()
+    );
+    Ok(license_files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deny_list() {
+        let deny = vec!["GPL-3.0-only".to_string(), "AGPL-3.0-only".to_string()];
+        assert!(!is_denied("MIT", &deny));
+        assert!(!is_denied("MIT OR Apache-2.0", &deny));
+        assert!(!is_denied("MIT/Apache-2.0", &deny));
+        assert!(is_denied("GPL-3.0-only", &deny));
+        assert!(is_denied("gpl-3.0-only", &deny));
+        assert!(is_denied("MIT OR GPL-3.0-only", &deny));
+        assert!(is_denied("(MIT OR Apache-2.0) AND GPL-3.0-only", &deny));
+        assert!(!is_denied("LGPL-3.0-only OR Apache-2.0", &deny));
+        assert!(is_denied(
+            "GPL-3.0-only WITH Classpath-exception-2.0 AND MIT",
+            &deny
+        ));
+        assert!(is_denied("MIT OR (GPL-3.0-only", &deny));
+        assert!(!is_denied("MIT OR (Apache-2.0", &deny));
+    }
+}
@@ -0,0 +1,199 @@
+//! Implementation of `maturin uninstall`, which removes what `maturin develop` installed into a
+//! virtualenv and optionally the extension modules it built in place
+use crate::conda::{conda_records, remove_conda_records};
+use crate::install::{InstallScheme, InstalledDistribution};
+use crate::module_writer::cffi_init_file;
+use crate::project_layout::{ProjectLayout, ProjectResolver};
+use crate::{BridgeModel, CargoOptions, PythonInterpreter, Target};
+use anyhow::{Context, Result};
+use fs_err as fs;
+use std::path::{Path, PathBuf};
+
+/// Remove a package installed with `maturin develop` from the current virtualenv
+#[derive(Debug, Clone, clap::Parser)]
+pub struct UninstallOptions {
+    /// The name of the distribution to uninstall. Defaults to the project in the current
+    /// directory or the one given with `--manifest-path`
+    #[arg(value_name = "NAME")]
+    pub name: Option<String>,
+    /// The path to the Cargo.toml of the project
+    #[arg(short = 'm', long = "manifest-path", value_name = "PATH")]
+    pub manifest_path: Option<PathBuf>,
+    /// Also delete the extension modules that `maturin develop --skip-install` and editable
+    /// installs copied next to the python sources
+    #[arg(long)]
+    pub clean: bool,
+    /// Only list the files that would be removed
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// The extension modules and generated cffi and uniffi packages that editable builds copy into
+/// the python source tree of a mixed Rust/Python project
+fn inplace_artifacts(project_layout: &ProjectLayout) -> Result<Vec<PathBuf>> {
+    let Some(python_module) = &project_layout.python_module else {
+        return Ok(Vec::new());
+    };
+    let ext_name = &project_layout.extension_name;
+    let mut artifacts = Vec::new();
+    // pyo3 and rust-cpython, e.g. `ext.cpython-312-x86_64-linux-gnu.so`, `ext.abi3.so` or
+    // `ext_d.pyd`
+    if project_layout.rust_module.is_dir() {
+        for entry in fs::read_dir(&project_layout.rust_module)? {
+            let path = entry?.path();
+            let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let is_extension = file_name.ends_with(".so") || file_name.ends_with(".pyd");
+            let is_ours = file_name.starts_with(&format!("{ext_name}."))
+                || file_name == format!("{ext_name}_d.pyd");
+            if is_extension && is_ours && path.is_file() {
+                artifacts.push(path);
+            }
+        }
+    }
+    // cffi and uniffi generate a whole package, which we recognize by its `__init__.py`
+    let package = python_module.join(ext_name);
+    if let Ok(init) = fs::read_to_string(package.join("__init__.py")) {
+        let is_cffi = init == cffi_init_file(ext_name);
+        let is_uniffi = init.starts_with("from .") && init.ends_with(" import *  # NOQA\n");
+        if is_cffi || is_uniffi {
+            artifacts.push(package);
+        }
+    }
+    artifacts.sort();
+    Ok(artifacts)
+}
+
+/// Uninstalls a distribution from the virtualenv, including the `.pth` file or other editable
+/// hooks listed in its RECORD and its record in `conda-meta`, and with `--clean` deletes the
+/// in-place build artifacts
+pub fn uninstall(options: UninstallOptions, venv_dir: &Path) -> Result<()> {
+    let project = if options.name.is_none() || options.clean {
+        Some(
+            ProjectResolver::resolve(options.manifest_path.clone(), CargoOptions::default())
+                .context("Failed to resolve the project to uninstall")?,
+        )
+    } else {
+        None
+    };
+    let name = match (&options.name, &project) {
+        (Some(name), _) => name.clone(),
+        (None, Some(project)) => project.metadata23.name.clone(),
+        (None, None) => unreachable!(),
+    };
+
+    let target = Target::from_target_triple(None)?;
+    let python = target.get_venv_python(venv_dir);
+    // The bridge only matters for checking whether the interpreter is supported for building
+    let interpreter = PythonInterpreter::check_executable(&python, &target, &BridgeModel::Cffi)?
+        .with_context(|| format!("Expected {} to be a python interpreter", python.display()))?;
+    let scheme = InstallScheme::venv(venv_dir, &interpreter, &target)?;
+
+    let installed = InstalledDistribution::find(&scheme.purelib, &name)?;
+    if installed.is_empty() {
+        eprintln!(
+            "⚠️  Warning: {name} is not installed in {}",
+            scheme.root.display()
+        );
+    }
+    for distribution in &installed {
+        if options.dry_run {
+            eprintln!(
+                "🗑️  Would uninstall {} {}:",
+                distribution.name, distribution.version
+            );
+            for file in distribution.existing_files(&scheme) {
+                println!("{}", file.display());
+            }
+            println!("{}", distribution.dist_info.display());
+            for record in conda_records(&scheme.root, &distribution.name)? {
+                println!("{}", record.display());
+            }
+        } else {
+            distribution.uninstall(&scheme)?;
+            remove_conda_records(&scheme.root, &distribution.name)?;
+            eprintln!(
+                "🗑️  Uninstalled {} {} from {}",
+                distribution.name,
+                distribution.version,
+                scheme.root.display()
+            );
+        }
+    }
+
+    if let Some(project) = project.filter(|_| options.clean) {
+        let artifacts = inplace_artifacts(&project.project_layout)?;
+        if artifacts.is_empty() {
+            eprintln!("🧹 No in-place build artifacts found");
+        }
+        for artifact in artifacts {
+            if options.dry_run {
+                println!("{}", artifact.display());
+                continue;
+            }
+            if artifact.is_dir() {
+                fs::remove_dir_all(&artifact)?;
+            } else {
+                fs::remove_file(&artifact)?;
+            }
+            eprintln!("🧹 Removed {}", artifact.display());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn finds_inplace_artifacts() -> Result<()> {
+        let dir = TempDir::new()?;
+        let python_module = dir.path().join("my_project");
+        fs::create_dir_all(python_module.join("_ffi"))?;
+        fs::create_dir_all(python_module.join("helpers"))?;
+        for file in [
+            "__init__.py",
+            "my_project.cpython-312-x86_64-linux-gnu.so",
+            "my_project.abi3.so",
+            "my_project_d.pyd",
+            "my_project.py",
+            "other.so",
+        ] {
+            fs::write(python_module.join(file), "")?;
+        }
+        fs::write(
+            python_module.join("_ffi/__init__.py"),
+            cffi_init_file("_ffi"),
+        )?;
+        fs::write(python_module.join("helpers/__init__.py"), "")?;
+        let project_layout = ProjectLayout {
+            python_dir: dir.path().to_path_buf(),
+            python_packages: vec!["my_project".to_string()],
+            python_module: Some(python_module.clone()),
+            rust_module: python_module.clone(),
+            extension_name: "my_project".to_string(),
+            data: None,
+        };
+        assert_eq!(
+            inplace_artifacts(&project_layout)?,
+            vec![
+                python_module.join("my_project.abi3.so"),
+                python_module.join("my_project.cpython-312-x86_64-linux-gnu.so"),
+                python_module.join("my_project_d.pyd"),
+            ]
+        );
+
+        let project_layout = ProjectLayout {
+            extension_name: "_ffi".to_string(),
+            ..project_layout
+        };
+        assert_eq!(
+            inplace_artifacts(&project_layout)?,
+            vec![python_module.join("_ffi")]
+        );
+        Ok(())
+    }
+}
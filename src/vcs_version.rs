@@ -0,0 +1,360 @@
+//! Derives the package version from git tags, for `[tool.maturin.version] source = "vcs"`
+//!
+//! The version scheme follows setuptools_scm's default `guess-next-dev`: an exactly tagged and
+//! clean commit gets the version of the tag, otherwise the tag version is bumped and gets a
+//! `.devN` suffix with the number of commits since the tag and a local version with the commit
+//! hash, e.g. `1.2.4.dev3+g1a2b3c4`. Unlike setuptools_scm, a dirty working tree is marked with
+//! `.dirty` in the local version instead of the current date.
+use anyhow::{bail, format_err, Context, Result};
+use once_cell::sync::Lazy;
+use pep440_rs::{LocalSegment, PreRelease, PreReleaseKind, Version};
+use regex::Regex;
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+
+/// The output of `git describe`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Describe {
+    /// The nearest tag, if there is any
+    tag: Option<String>,
+    /// The number of commits since the tag, or since the root commit without a tag
+    distance: u64,
+    /// The abbreviated hash of `HEAD`
+    hash: String,
+    /// Whether tracked files have uncommitted changes
+    dirty: bool,
+}
+
+fn git(project_dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(project_dir)
+        .output()
+        .with_context(|| format!("Failed to run `git {}`", args.join(" ")))?;
+    if !output.status.success() {
+        bail!(
+            "`git {}` failed in {}: {}",
+            args.join(" "),
+            project_dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8(output.stdout)
+        .context("git printed invalid utf-8 ಠ_ಠ")?
+        .trim()
+        .to_string())
+}
+
+fn describe(project_dir: &Path) -> Result<Describe> {
+    let output = git(
+        project_dir,
+        &["describe", "--tags", "--long", "--dirty", "--always"],
+    )?;
+    parse_describe(&output, || {
+        git(project_dir, &["rev-list", "--count", "HEAD"])?
+            .parse()
+            .context("git printed an invalid commit count")
+    })
+}
+
+/// Parses `<tag>-<distance>-g<hash>[-dirty]`, or only `<hash>[-dirty]` if there is no tag
+fn parse_describe(output: &str, commit_count: impl FnOnce() -> Result<u64>) -> Result<Describe> {
+    static DESCRIBE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^(?P<tag>.+)-(?P<distance>\d+)-g(?P<hash>[0-9a-f]+)$").unwrap());
+
+    let (output, dirty) = match output.strip_suffix("-dirty") {
+        Some(output) => (output, true),
+        None => (output, false),
+    };
+    match DESCRIBE.captures(output) {
+        Some(captures) => Ok(Describe {
+            tag: Some(captures["tag"].to_string()),
+            distance: captures["distance"].parse()?,
+            hash: captures["hash"].to_string(),
+            dirty,
+        }),
+        None => Ok(Describe {
+            tag: None,
+            distance: commit_count()?,
+            hash: output.to_string(),
+            dirty,
+        }),
+    }
+}
+
+/// Converts a tag such as `v1.2.3`, `my-project-v1.2.3` or `1.0.0-rc.1` into a PEP 440 version,
+/// converting semver pre-releases into their PEP 440 counterpart
+fn tag_to_version(tag: &str) -> Result<Version> {
+    static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:[\w-]+?-)?[vV]?(\d.*)$").unwrap());
+
+    let version = TAG
+        .captures(tag)
+        .map(|captures| captures.get(1).unwrap().as_str())
+        .with_context(|| format!("The git tag `{tag}` doesn't contain a version"))?;
+    if let Ok(version) = Version::from_str(version) {
+        return Ok(version.without_local());
+    }
+    let semver = semver::Version::parse(version).map_err(|err| {
+        format_err!("The git tag `{tag}` is neither a PEP 440 nor a semver version: {err}")
+    })?;
+    let release = Version::new([semver.major, semver.minor, semver.patch]);
+    if semver.pre.is_empty() {
+        return Ok(release);
+    }
+    let identifiers: Vec<&str> = semver.pre.split('.').collect();
+    let (kind, number) = match identifiers.as_slice() {
+        [kind] => (*kind, 0),
+        [kind, number] => (
+            *kind,
+            number.parse().with_context(|| {
+                format!("Unsupported semver pre-release `{}` in `{tag}`", semver.pre)
+            })?,
+        ),
+        _ => bail!("Unsupported semver pre-release `{}` in `{tag}`", semver.pre),
+    };
+    let pre = |kind| Some(PreRelease { kind, number });
+    Ok(match kind.to_lowercase().as_str() {
+        "alpha" | "a" => release.with_pre(pre(PreReleaseKind::Alpha)),
+        "beta" | "b" => release.with_pre(pre(PreReleaseKind::Beta)),
+        "rc" | "c" | "pre" | "preview" => release.with_pre(pre(PreReleaseKind::Rc)),
+        "dev" => release.with_dev(Some(number)),
+        _ => bail!("Unsupported semver pre-release `{}` in `{tag}`", semver.pre),
+    })
+}
+
+/// Computes the version from the nearest tag, the distance and the dirty state
+fn describe_to_version(describe: &Describe) -> Result<Version> {
+    let tagged = match &describe.tag {
+        Some(tag) => tag_to_version(tag)?,
+        None => Version::new([0, 0]),
+    };
+    if describe.tag.is_some() && describe.distance == 0 && !describe.dirty {
+        return Ok(tagged);
+    }
+
+    // Guess the next version: the next pre-release or the next patch release
+    let next = match tagged.pre() {
+        Some(pre) => tagged.clone().with_pre(Some(PreRelease {
+            kind: pre.kind,
+            number: pre.number + 1,
+        })),
+        None => {
+            let mut release = tagged.release().to_vec();
+            *release.last_mut().unwrap() += 1;
+            tagged.clone().with_release(release)
+        }
+    };
+    let mut local = vec![LocalSegment::String(format!("g{}", describe.hash))];
+    if describe.dirty {
+        local.push(LocalSegment::String("dirty".to_string()));
+    }
+    Ok(next
+        .with_post(None)
+        .with_dev(Some(describe.distance))
+        .with_local(local))
+}
+
+/// Determines the PEP 440 version of the project from the git repository it is in
+pub fn version_from_vcs(project_dir: &Path) -> Result<Version> {
+    let describe = describe(project_dir).context(
+        "Failed to determine the version from git, which is required by \
+        `tool.maturin.version.source = \"vcs\"`",
+    )?;
+    describe_to_version(&describe)
+}
+
+/// Converts a PEP 440 version into a semver version for Cargo.toml, e.g. `1.2.4.dev3+g1a2b3c4`
+/// becomes `1.2.4-dev.3+g1a2b3c4` and `1.0.0.post1` becomes `1.0.0+post.1`
+pub fn pep440_to_semver(version: &Version) -> Result<semver::Version> {
+    if version.epoch() != 0 {
+        bail!("Version {version} with an epoch can't be converted to semver for Cargo.toml");
+    }
+    let release = version.release();
+    if release.len() > 3 {
+        bail!("Version {version} has more than three components and can't be converted to semver for Cargo.toml");
+    }
+    let component = |idx: usize| release.get(idx).copied().unwrap_or_default();
+    let mut semver = format!("{}.{}.{}", component(0), component(1), component(2));
+
+    let mut pre = Vec::new();
+    if let Some(PreRelease { kind, number }) = version.pre() {
+        let kind = match kind {
+            PreReleaseKind::Alpha => "alpha",
+            PreReleaseKind::Beta => "beta",
+            PreReleaseKind::Rc => "rc",
+        };
+        pre.push(format!("{kind}.{number}"));
+    }
+    if let Some(dev) = version.dev() {
+        if version.post().is_some() {
+            bail!("Version {version} is a development release of a post-release, which can't be converted to semver for Cargo.toml");
+        }
+        pre.push(format!("dev.{dev}"));
+    }
+    if !pre.is_empty() {
+        semver.push('-');
+        semver.push_str(&pre.join("."));
+    }
+    // A semver pre-release would sort before the release, so the post-release number goes into
+    // the build metadata, which is ignored for ordering
+    let mut build: Vec<String> = version
+        .post()
+        .map(|post| format!("post.{post}"))
+        .into_iter()
+        .collect();
+    build.extend(version.local().iter().map(ToString::to_string));
+    if !build.is_empty() {
+        semver.push('+');
+        semver.push_str(&build.join("."));
+    }
+    semver::Version::parse(&semver)
+        .with_context(|| format!("Failed to convert version {version} to semver for Cargo.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn version(tag: Option<&str>, distance: u64, dirty: bool) -> String {
+        describe_to_version(&Describe {
+            tag: tag.map(ToString::to_string),
+            distance,
+            hash: "1a2b3c4".to_string(),
+            dirty,
+        })
+        .unwrap()
+        .to_string()
+    }
+
+    #[test]
+    fn test_parse_describe() {
+        let no_count = || -> Result<u64> { unreachable!() };
+        assert_eq!(
+            parse_describe("v1.2.3-4-g1a2b3c4-dirty", no_count).unwrap(),
+            Describe {
+                tag: Some("v1.2.3".to_string()),
+                distance: 4,
+                hash: "1a2b3c4".to_string(),
+                dirty: true
+            }
+        );
+        assert_eq!(
+            parse_describe("release-1.0.0-rc.1-0-g1a2b3c4", no_count)
+                .unwrap()
+                .tag
+                .as_deref(),
+            Some("release-1.0.0-rc.1")
+        );
+        assert_eq!(
+            parse_describe("1a2b3c4", || Ok(7)).unwrap(),
+            Describe {
+                tag: None,
+                distance: 7,
+                hash: "1a2b3c4".to_string(),
+                dirty: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_tag_to_version() {
+        for (tag, expected) in [
+            ("1.2.3", "1.2.3"),
+            ("v1.2.3", "1.2.3"),
+            ("my-project-v1.2", "1.2"),
+            ("v1.0.0a1", "1.0.0a1"),
+            ("v1.0.0-alpha", "1.0.0a0"),
+            ("v1.0.0-alpha.1", "1.0.0a1"),
+            ("v1.0.0-beta.2", "1.0.0b2"),
+            ("1.0.0-rc.3", "1.0.0rc3"),
+            ("v1.0.0-dev.4", "1.0.0.dev4"),
+            ("v1.0.0+build.5", "1.0.0"),
+        ] {
+            assert_eq!(tag_to_version(tag).unwrap().to_string(), expected, "{tag}");
+        }
+        assert!(tag_to_version("latest").is_err());
+        assert!(tag_to_version("v1.0.0-nightly.1").is_err());
+    }
+
+    #[test]
+    fn test_describe_to_version() {
+        assert_eq!(version(Some("v1.2.3"), 0, false), "1.2.3");
+        assert_eq!(version(Some("v1.2.3"), 3, false), "1.2.4.dev3+g1a2b3c4");
+        assert_eq!(
+            version(Some("v1.2.3"), 0, true),
+            "1.2.4.dev0+g1a2b3c4.dirty"
+        );
+        assert_eq!(version(Some("v1.2"), 1, false), "1.3.dev1+g1a2b3c4");
+        assert_eq!(
+            version(Some("v1.0.0-rc.1"), 2, false),
+            "1.0.0rc2.dev2+g1a2b3c4"
+        );
+        assert_eq!(
+            version(Some("v1.0.0.post1"), 2, false),
+            "1.0.1.dev2+g1a2b3c4"
+        );
+        assert_eq!(version(None, 5, false), "0.1.dev5+g1a2b3c4");
+    }
+
+    #[test]
+    fn test_pep440_to_semver() {
+        for (version, expected) in [
+            ("1.2.3", "1.2.3"),
+            ("1.2", "1.2.0"),
+            ("1.2.4.dev3+g1a2b3c4", "1.2.4-dev.3+g1a2b3c4"),
+            (
+                "1.0.0rc2.dev2+g1a2b3c4.dirty",
+                "1.0.0-rc.2.dev.2+g1a2b3c4.dirty",
+            ),
+            ("1.0.0.post1", "1.0.0+post.1"),
+            ("1.0.0rc1.post2+abc", "1.0.0-rc.1+post.2.abc"),
+        ] {
+            assert_eq!(
+                pep440_to_semver(&Version::from_str(version).unwrap())
+                    .unwrap()
+                    .to_string(),
+                expected
+            );
+        }
+        assert!(pep440_to_semver(&Version::from_str("1.2.3.4").unwrap()).is_err());
+        assert!(pep440_to_semver(&Version::from_str("1!1.0").unwrap()).is_err());
+        assert!(pep440_to_semver(&Version::from_str("1.0.post1.dev2").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_version_from_vcs() {
+        let repo = tempfile::tempdir().unwrap();
+        let repo = repo.path();
+        let git = |args: &[&str]| {
+            let mut full_args = vec![
+                "-c",
+                "user.name=maturin",
+                "-c",
+                "user.email=maturin@localhost",
+            ];
+            full_args.extend(args);
+            git(repo, &full_args).unwrap()
+        };
+        git(&["init", "-q"]);
+        fs_err::write(repo.join("file"), "1").unwrap();
+        git(&["add", "file"]);
+        git(&["commit", "-q", "-m", "first"]);
+        git(&["tag", "v0.3.0"]);
+        assert_eq!(version_from_vcs(repo).unwrap().to_string(), "0.3.0");
+
+        git(&["commit", "-q", "--allow-empty", "-m", "second"]);
+        let hash = git(&["rev-parse", "--short", "HEAD"]);
+        assert_eq!(
+            version_from_vcs(repo).unwrap().to_string(),
+            format!("0.3.1.dev1+g{hash}")
+        );
+
+        fs_err::write(repo.join("file"), "2").unwrap();
+        assert_eq!(
+            version_from_vcs(repo).unwrap().to_string(),
+            format!("0.3.1.dev1+g{hash}.dirty")
+        );
+    }
+}
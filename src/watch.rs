@@ -0,0 +1,209 @@
+//! Polls the inputs of a build for changes, which is how `maturin develop --watch` knows when
+//! to rebuild
+use crate::source_distribution::find_path_deps;
+use crate::BuildContext;
+use anyhow::{Context, Result};
+use fs_err as fs;
+use ignore::WalkBuilder;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::{Duration, SystemTime};
+
+/// How often the files are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// A change is only picked up once the files stopped changing for this long, so that saving
+/// many files at once or switching branches triggers a single rebuild
+const DEBOUNCE: Duration = Duration::from_millis(300);
+/// Extensions of the files that a build or python itself writes into the source tree
+const GENERATED_EXTENSIONS: [&str; 6] = ["so", "pyd", "dll", "dylib", "pyc", "pyo"];
+
+/// The modification time and the size of each watched file
+pub type Snapshot = BTreeMap<PathBuf, (Option<SystemTime>, u64)>;
+
+/// The files that a develop build depends on: the crate, its path dependencies, the python
+/// sources and pyproject.toml
+///
+/// Files ignored by git, hidden files, the cargo target directories and compiled python and
+/// extension modules are skipped.
+#[derive(Debug, Clone)]
+pub struct WatchedInputs {
+    /// Directories that are watched recursively
+    roots: Vec<PathBuf>,
+    /// Single files that are watched
+    files: Vec<PathBuf>,
+    /// Directories below the roots that are skipped
+    excluded: Vec<PathBuf>,
+}
+
+impl WatchedInputs {
+    /// Watches the `roots` recursively and the single `files`, skipping the `excluded`
+    /// directories
+    pub fn new(roots: Vec<PathBuf>, files: Vec<PathBuf>, excluded: Vec<PathBuf>) -> Self {
+        Self {
+            roots,
+            files,
+            excluded,
+        }
+    }
+
+    /// Collects the inputs of the build
+    pub fn from_build_context(build_context: &BuildContext) -> Result<Self> {
+        let mut roots = Vec::new();
+        if let Some(crate_dir) = build_context.manifest_path.parent() {
+            roots.push(crate_dir.to_path_buf());
+        }
+        for path_dep in find_path_deps(&build_context.cargo_metadata)?.into_values() {
+            if let Some(dep_dir) = path_dep.manifest_path.parent() {
+                roots.push(dep_dir.to_path_buf());
+            }
+        }
+        roots.push(build_context.project_layout.python_dir.clone());
+        Ok(Self::new(
+            roots,
+            vec![build_context.pyproject_toml_path.clone()],
+            target_dirs(build_context),
+        ))
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        if self
+            .excluded
+            .iter()
+            .any(|excluded| path.starts_with(excluded))
+        {
+            return true;
+        }
+        if path.file_name().is_some_and(|name| name == "__pycache__") {
+            return true;
+        }
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| GENERATED_EXTENSIONS.contains(&extension))
+    }
+
+    /// Records the modification time and size of all watched files
+    pub fn snapshot(&self) -> Result<Snapshot> {
+        let mut snapshot = Snapshot::new();
+        let mut add = |path: &Path| {
+            // Files can be deleted while we walk the directory
+            if let Ok(metadata) = fs::metadata(path) {
+                snapshot.insert(
+                    path.to_path_buf(),
+                    (metadata.modified().ok(), metadata.len()),
+                );
+            }
+        };
+        for file in &self.files {
+            add(file);
+        }
+        for root in &self.roots {
+            let excluded = self.clone();
+            for entry in WalkBuilder::new(root)
+                .filter_entry(move |entry| !excluded.is_excluded(entry.path()))
+                .build()
+            {
+                let entry = entry.with_context(|| format!("Failed to watch {}", root.display()))?;
+                if entry
+                    .file_type()
+                    .is_some_and(|file_type| file_type.is_file())
+                {
+                    add(entry.path());
+                }
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// Blocks until the watched files differ from the snapshot and then stopped changing,
+    /// returning the new snapshot
+    pub fn wait_for_change(&self, snapshot: &Snapshot) -> Result<Snapshot> {
+        loop {
+            sleep(POLL_INTERVAL);
+            let mut current = self.snapshot()?;
+            if &current == snapshot {
+                continue;
+            }
+            loop {
+                sleep(DEBOUNCE);
+                let next = self.snapshot()?;
+                if next == current {
+                    return Ok(current);
+                }
+                current = next;
+            }
+        }
+    }
+}
+
+/// The cargo target directories, which are never watched
+pub fn target_dirs(build_context: &BuildContext) -> Vec<PathBuf> {
+    vec![
+        build_context.target_dir.clone(),
+        build_context
+            .cargo_metadata
+            .target_directory
+            .clone()
+            .into_std_path_buf(),
+    ]
+}
+
+/// A hash of the paths and the contents of all files in the snapshot, so that a file that was
+/// saved without changes doesn't cause a rebuild
+pub fn fingerprint(snapshot: &Snapshot) -> String {
+    let mut hasher = Sha256::new();
+    for path in snapshot.keys() {
+        hasher.update(path.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        match fs::read(path) {
+            Ok(contents) => hasher.update(Sha256::digest(contents)),
+            // Deleted since the snapshot was taken
+            Err(_) => hasher.update([0]),
+        }
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use tempfile::TempDir;
+
+    #[test]
+    fn detects_changes() -> Result<()> {
+        let dir = TempDir::new()?;
+        let src = dir.path().join("src");
+        let target = dir.path().join("target");
+        fs::create_dir_all(&src)?;
+        fs::create_dir_all(&target)?;
+        fs::write(src.join("lib.rs"), "fn main() {}")?;
+        fs::write(dir.path().join("module.so"), "")?;
+        fs::write(target.join("build.log"), "")?;
+        let inputs = WatchedInputs::new(
+            vec![dir.path().to_path_buf()],
+            Vec::new(),
+            vec![target.clone()],
+        );
+
+        let snapshot = inputs.snapshot()?;
+        assert_eq!(
+            snapshot.keys().collect::<Vec<_>>(),
+            vec![&src.join("lib.rs")]
+        );
+        let built = fingerprint(&snapshot);
+        fs::write(src.join("lib.rs"), "fn main() {}")?;
+        assert_eq!(fingerprint(&inputs.snapshot()?), built);
+
+        let lib_rs = src.join("lib.rs");
+        let writer = thread::spawn(move || {
+            sleep(Duration::from_millis(100));
+            fs::write(lib_rs, "fn main() { println!(); }").unwrap();
+        });
+        let changed = inputs.wait_for_change(&snapshot)?;
+        writer.join().unwrap();
+        assert_ne!(fingerprint(&changed), built);
+        Ok(())
+    }
+}
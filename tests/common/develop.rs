@@ -62,7 +62,10 @@ pub fn test_develop(
         release: false,
         strip: false,
         extras: Vec::new(),
+        groups: Vec::new(),
+        lockfile: None,
         skip_install: false,
+        inplace_dir: None,
         pip_path: None,
         cargo_options: CargoOptions {
             manifest_path: Some(manifest_file),
@@ -71,8 +74,14 @@ pub fn test_develop(
             ..Default::default()
         },
         uv,
+        install_deps: false,
+        watch: false,
+        exec: None,
+        venvs: Vec::new(),
+        all_venvs: false,
+        message_format: Default::default(),
     };
-    develop(develop_options, &venv_dir)?;
+    develop(develop_options, std::slice::from_ref(&venv_dir))?;
 
     check_installed(package, &python)?;
     Ok(())
@@ -92,7 +92,8 @@ pub fn test_integration(
     };
     // We can do this since we know that wheels are built and returned in the
     // order they are in the build context
-    for ((filename, supported_version), python_interpreter) in wheels.iter().zip(interpreter) {
+    for (wheel, python_interpreter) in wheels.iter().zip(interpreter) {
+        let filename = &wheel.path;
         if test_zig
             && build_context.target.is_linux()
             && !build_context.target.is_musl_libc()
@@ -107,7 +108,7 @@ pub fn test_integration(
             };
             assert!(filename.to_string_lossy().ends_with(&file_suffix))
         }
-        let mut venv_name = if supported_version == "py3" {
+        let mut venv_name = if wheel.python_tag == "py3" {
             format!("{unique_name}-py3")
         } else {
             format!(
@@ -199,10 +200,10 @@ pub fn test_integration_conda(package: impl AsRef<Path>, bindings: Option<String
     let wheels = build_context.build_wheels()?;
 
     let mut conda_wheels: Vec<(PathBuf, PathBuf)> = vec![];
-    for ((filename, _), python_interpreter) in wheels.iter().zip(build_context.interpreter) {
+    for (wheel, python_interpreter) in wheels.iter().zip(build_context.interpreter) {
         let executable = python_interpreter.executable;
         if executable.to_str().unwrap().contains("maturin-env-") {
-            conda_wheels.push((filename.clone(), executable))
+            conda_wheels.push((wheel.path.clone(), executable))
         }
     }
 
@@ -2,14 +2,23 @@ use anyhow::{Context, Result};
 use clap::Parser;
 use expect_test::Expect;
 use flate2::read::GzDecoder;
+use indoc::indoc;
 use maturin::pyproject_toml::{SdistGenerator, ToolMaturin};
-use maturin::{BuildOptions, CargoOptions, PlatformTag};
+use maturin::{
+    check_wheel, retag, BuildOptions, BuildReport, CargoOptions, DistributionKind, PlatformTag,
+    RetagOptions, SbomFormat,
+};
 use pretty_assertions::assert_eq;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
 use std::collections::BTreeSet;
+use std::ffi::OsStr;
 use std::fs::File;
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use tar::Archive;
+use time::macros::datetime;
 use time::OffsetDateTime;
 use zip::ZipArchive;
 
@@ -153,11 +162,11 @@ pub fn test_source_distribution(
     pyproject_toml.tool = Some(tool);
     build_context.pyproject_toml = Some(pyproject_toml);
 
-    let (path, _) = build_context
+    let sdist = build_context
         .build_source_distribution()?
         .context("Failed to build source distribution")?;
 
-    let tar_gz = fs_err::File::open(path)?;
+    let tar_gz = fs_err::File::open(sdist.path)?;
     let tar = GzDecoder::new(tar_gz);
     let mut archive = Archive::new(tar);
     let mut files = BTreeSet::new();
@@ -198,7 +207,7 @@ fn build_wheel_files(package: impl AsRef<Path>, unique_name: &str) -> Result<Zip
             target_dir: Some(PathBuf::from(format!("test-crates/targets/{unique_name}"))),
             ..Default::default()
         },
-        platform_tag: vec![PlatformTag::Linux],
+        platform_tag: vec![PlatformTag::Linux.to_string()],
         ..Default::default()
     };
 
@@ -207,9 +216,7 @@ fn build_wheel_files(package: impl AsRef<Path>, unique_name: &str) -> Result<Zip
         .build_wheels()
         .context("Failed to build wheels")?;
     assert!(!wheels.is_empty());
-    let (wheel_path, _) = &wheels[0];
-
-    let wheel = ZipArchive::new(File::open(wheel_path)?)?;
+    let wheel = ZipArchive::new(File::open(&wheels[0].path)?)?;
     Ok(wheel)
 }
 
@@ -231,6 +238,616 @@ pub fn check_wheel_mtimes(
     Ok(())
 }
 
+/// Copies a test crate without its target directory
+fn copy_crate(source: &Path, target: &Path) -> Result<()> {
+    fs_err::create_dir_all(target)?;
+    for entry in fs_err::read_dir(source)? {
+        let entry = entry?;
+        if entry.file_name() == "target" {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            copy_crate(&entry.path(), &target.join(entry.file_name()))?;
+        } else {
+            fs_err::copy(entry.path(), target.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Builds the wheels and the source distribution twice in reproducible mode and checks that
+/// both builds are byte-identical, even though a source file was touched in between
+///
+/// maturin runs in a subprocess without `SOURCE_DATE_EPOCH`, which other tests set for the whole
+/// process, so that the timestamps fall back to 1980-01-01.
+pub fn check_reproducible(package: impl AsRef<Path>, unique_name: &str) -> Result<()> {
+    use anyhow::bail;
+
+    let project_dir = tempfile::tempdir()?;
+    let project_dir = project_dir.path();
+    copy_crate(package.as_ref(), project_dir)?;
+    let zip_epoch = datetime!(1980-01-01 0:00 UTC);
+
+    let mut hashes = Vec::new();
+    for run in ["first", "second"] {
+        if run == "second" {
+            // Rewriting the file only changes its timestamp, which must not end up in the archives
+            let lib_rs = project_dir.join("src").join("lib.rs");
+            fs_err::write(&lib_rs, fs_err::read(&lib_rs)?)?;
+        }
+        let wheel_directory = Path::new("test-crates")
+            .join("wheels")
+            .join(unique_name)
+            .join(run);
+        if wheel_directory.is_dir() {
+            fs_err::remove_dir_all(&wheel_directory)?;
+        }
+        let output = Command::new(env!("CARGO_BIN_EXE_maturin"))
+            .args([
+                "build",
+                "--reproducible",
+                "--sdist",
+                "--compatibility",
+                "linux",
+            ])
+            .arg("--manifest-path")
+            .arg(project_dir.join("Cargo.toml"))
+            .arg("--out")
+            .arg(&wheel_directory)
+            .arg("--target-dir")
+            .arg(format!("test-crates/targets/{unique_name}"))
+            .env_remove("SOURCE_DATE_EPOCH")
+            .output()?;
+        if !output.status.success() {
+            bail!(
+                "Failed to run maturin build: {}\n---stdout:\n{}---stderr:\n{}",
+                output.status,
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+
+        let mut paths: Vec<PathBuf> = fs_err::read_dir(&wheel_directory)?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<Result<_>>()?;
+        paths.sort();
+        let sdist_path = paths
+            .iter()
+            .find(|path| path.to_string_lossy().ends_with(".tar.gz"))
+            .context("Missing the source distribution")?;
+        assert!(paths
+            .iter()
+            .any(|path| path.extension().is_some_and(|ext| ext == "whl")));
+
+        let mut archive = Archive::new(GzDecoder::new(File::open(sdist_path)?));
+        let mut entries = Vec::new();
+        for entry in archive.entries()? {
+            let entry = entry?;
+            let header = entry.header();
+            assert_eq!(header.uid()?, 0);
+            assert_eq!(header.gid()?, 0);
+            assert_eq!(header.mtime()?, zip_epoch.unix_timestamp() as u64);
+            assert!(matches!(header.mode()?, 0o644 | 0o755));
+            entries.push(entry.path()?.into_owned());
+        }
+        assert!(entries.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let mut run_hashes = Vec::new();
+        for path in &paths {
+            if path.extension().is_some_and(|ext| ext == "whl") {
+                let mut wheel = ZipArchive::new(File::open(path)?)?;
+                for idx in 0..wheel.len() {
+                    assert_eq!(wheel.by_index(idx)?.last_modified().to_time()?, zip_epoch);
+                }
+            }
+            let hash = format!("{:x}", Sha256::digest(fs_err::read(path)?));
+            run_hashes.push((path.file_name().unwrap().to_owned(), hash));
+        }
+        hashes.push(run_hashes);
+    }
+    assert_eq!(hashes[0], hashes[1]);
+    Ok(())
+}
+
+/// Checks that the build report matches the built wheel and serializes to the documented JSON
+pub fn check_build_report(package: impl AsRef<Path>, unique_name: &str) -> Result<()> {
+    let build_options = BuildOptions {
+        out: Some(Path::new("test-crates").join("wheels").join(unique_name)),
+        cargo: CargoOptions {
+            manifest_path: Some(package.as_ref().join("Cargo.toml")),
+            quiet: true,
+            target_dir: Some(PathBuf::from(format!("test-crates/targets/{unique_name}"))),
+            ..Default::default()
+        },
+        platform_tag: vec![PlatformTag::Linux.to_string()],
+        ..Default::default()
+    };
+    let build_context = build_options.into_build_context(false, false, false)?;
+    let wheels = build_context.build_wheels()?;
+    let wheel = &wheels[0];
+
+    assert_eq!(wheel.kind, DistributionKind::Wheel);
+    let contents = fs_err::read(&wheel.path)?;
+    assert_eq!(wheel.sha256, format!("{:x}", Sha256::digest(&contents)));
+    assert_eq!(wheel.size, contents.len() as u64);
+    let filename = wheel.path.file_name().unwrap().to_str().unwrap();
+    assert!(wheel
+        .tags
+        .iter()
+        .any(|tag| filename.ends_with(&format!("-{tag}.whl"))));
+    assert_eq!(wheel.bridge, "pyo3");
+    assert!(wheel.interpreter.is_some());
+    assert!(wheel.grafted_libraries.is_empty());
+
+    let report = serde_json::to_value(BuildReport {
+        artifacts: wheels.clone(),
+    })?;
+    assert_eq!(report["artifacts"][0]["sha256"], wheel.sha256.as_str());
+    assert_eq!(report["artifacts"][0]["kind"], "wheel");
+    assert_eq!(
+        report["artifacts"][0]["auditwheel-platform-tag"],
+        Value::Null
+    );
+    Ok(())
+}
+
+/// Checks that the SBOM lists the crates compiled into the wheel, but not proc macros or
+/// build dependencies
+pub fn check_sbom(package: impl AsRef<Path>, unique_name: &str) -> Result<()> {
+    let build_options = BuildOptions {
+        out: Some(Path::new("test-crates").join("wheels").join(unique_name)),
+        sbom: Some(SbomFormat::CycloneDx),
+        cargo: CargoOptions {
+            manifest_path: Some(package.as_ref().join("Cargo.toml")),
+            quiet: true,
+            target_dir: Some(PathBuf::from(format!("test-crates/targets/{unique_name}"))),
+            ..Default::default()
+        },
+        platform_tag: vec![PlatformTag::Linux.to_string()],
+        ..Default::default()
+    };
+    let build_context = build_options.into_build_context(false, false, false)?;
+    let wheels = build_context.build_wheels()?;
+    let mut wheel = ZipArchive::new(File::open(&wheels[0].path)?)?;
+    let sbom_path = format!(
+        "{}/sboms/{}.cdx.json",
+        build_context.metadata23.get_dist_info_dir().display(),
+        build_context.crate_name
+    );
+    let mut sbom = String::new();
+    wheel.by_name(&sbom_path)?.read_to_string(&mut sbom)?;
+    let sbom: Value = serde_json::from_str(&sbom)?;
+
+    assert_eq!(sbom["bomFormat"], "CycloneDX");
+    assert_eq!(
+        sbom["metadata"]["component"]["name"],
+        build_context.crate_name
+    );
+    let components: BTreeSet<&str> = sbom["components"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|component| component["name"].as_str().unwrap())
+        .collect();
+    assert!(components.contains("pyo3"));
+    assert!(components.contains("pyo3-ffi"));
+    assert!(!components.contains("pyo3-macros"));
+    assert!(!components.contains("pyo3-build-config"));
+    Ok(())
+}
+
+pub fn check_third_party_licenses(package: impl AsRef<Path>, unique_name: &str) -> Result<()> {
+    let build_options = BuildOptions {
+        out: Some(Path::new("test-crates").join("wheels").join(unique_name)),
+        third_party_licenses: true,
+        cargo: CargoOptions {
+            manifest_path: Some(package.as_ref().join("Cargo.toml")),
+            quiet: true,
+            target_dir: Some(PathBuf::from(format!("test-crates/targets/{unique_name}"))),
+            ..Default::default()
+        },
+        platform_tag: vec![PlatformTag::Linux.to_string()],
+        ..Default::default()
+    };
+    let build_context = build_options.into_build_context(false, false, false)?;
+    let wheels = build_context.build_wheels()?;
+    let mut wheel = ZipArchive::new(File::open(&wheels[0].path)?)?;
+    let dist_info_dir = build_context.metadata23.get_dist_info_dir();
+    let mut metadata = String::new();
+    wheel
+        .by_name(&format!("{}/METADATA", dist_info_dir.display()))?
+        .read_to_string(&mut metadata)?;
+    let license_files: Vec<&str> = metadata
+        .lines()
+        .filter_map(|line| line.strip_prefix("License-File: "))
+        .collect();
+    let pyo3_license = license_files
+        .iter()
+        .find(|license_file| license_file.starts_with("pyo3-0."))
+        .context("pyo3 is missing from License-File")?;
+    assert!(!license_files
+        .iter()
+        .any(|license_file| license_file.starts_with("pyo3-macros")));
+    // Every License-File must be in the wheel
+    for license_file in &license_files {
+        wheel.by_name(&format!(
+            "{}/licenses/{}",
+            dist_info_dir.display(),
+            license_file
+        ))?;
+    }
+    assert!(pyo3_license.contains('/'));
+
+    // The source distribution doesn't contain the third-party licenses
+    assert!(!build_context
+        .metadata23
+        .to_file_contents()?
+        .contains(pyo3_license));
+    Ok(())
+}
+
+/// Builds a source distribution of a fresh git repository with a version from git tags, then
+/// checks that building from the unpacked source distribution gives the same version
+pub fn check_vcs_version(unique_name: &str) -> Result<()> {
+    let project_dir = tempfile::tempdir()?;
+    let project_dir = project_dir.path();
+    fs_err::create_dir(project_dir.join("src"))?;
+    fs_err::write(project_dir.join("src").join("main.rs"), "fn main() {}\n")?;
+    fs_err::write(
+        project_dir.join("Cargo.toml"),
+        indoc!(
+            r#"
+            [package]
+            name = "vcs-version"
+            version = "0.0.0"
+            edition = "2021"
+            "#
+        ),
+    )?;
+    fs_err::write(
+        project_dir.join("pyproject.toml"),
+        indoc!(
+            r#"
+            [build-system]
+            requires = ["maturin>=1.0,<2.0"]
+            build-backend = "maturin"
+
+            [project]
+            name = "vcs-version"
+            dynamic = ["version"]
+
+            [tool.maturin]
+            bindings = "bin"
+            version = { source = "vcs" }
+            "#
+        ),
+    )?;
+    let git = |args: &[&str]| -> Result<String> {
+        let output = Command::new("git")
+            .args([
+                "-c",
+                "user.name=maturin",
+                "-c",
+                "user.email=maturin@localhost",
+            ])
+            .args(args)
+            .current_dir(project_dir)
+            .output()?;
+        assert!(output.status.success(), "git {args:?} failed");
+        Ok(String::from_utf8(output.stdout)?.trim().to_string())
+    };
+    git(&["init", "-q"])?;
+    git(&["add", "."])?;
+    git(&["commit", "-q", "-m", "first"])?;
+    git(&["tag", "v1.2.0-rc.1"])?;
+    git(&["commit", "-q", "--allow-empty", "-m", "second"])?;
+    let hash = git(&["rev-parse", "--short", "HEAD"])?;
+
+    let build = |manifest_path: PathBuf| {
+        BuildOptions {
+            out: Some(Path::new("test-crates").join("wheels").join(unique_name)),
+            cargo: CargoOptions {
+                manifest_path: Some(manifest_path),
+                quiet: true,
+                target_dir: Some(PathBuf::from(format!("test-crates/targets/{unique_name}"))),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .into_build_context(false, false, false)
+    };
+    let build_context = build(project_dir.join("Cargo.toml"))?;
+    let version = format!("1.2.0rc2.dev1+g{hash}");
+    assert_eq!(build_context.metadata23.version.to_string(), version);
+    let sdist = build_context
+        .build_source_distribution()?
+        .context("Failed to build source distribution")?;
+
+    let unpacked = tempfile::tempdir()?;
+    Archive::new(GzDecoder::new(File::open(sdist.path)?)).unpack(unpacked.path())?;
+    let sdist_root = unpacked.path().join(format!("vcs_version-{version}"));
+    let cargo_toml = fs_err::read_to_string(sdist_root.join("Cargo.toml"))?;
+    assert!(cargo_toml.contains(&format!("version = \"1.2.0-rc.2.dev.1+g{hash}\"")));
+    let pyproject_toml = fs_err::read_to_string(sdist_root.join("pyproject.toml"))?;
+    assert!(pyproject_toml.contains(&format!("version = \"{version}\"")));
+    assert!(!pyproject_toml.contains("dynamic"));
+    assert!(!pyproject_toml.contains("source = \"vcs\""));
+
+    // There is no git repository in the unpacked source distribution
+    let from_sdist = build(sdist_root.join("Cargo.toml"))?;
+    assert_eq!(from_sdist.metadata23.version.to_string(), version);
+    Ok(())
+}
+
+/// Uses an alias from the `policy-file` in pyproject.toml for `compatibility` and `--compatibility`
+pub fn check_policy_file(unique_name: &str) -> Result<()> {
+    let project_dir = tempfile::tempdir()?;
+    let project_dir = project_dir.path();
+    fs_err::create_dir(project_dir.join("src"))?;
+    fs_err::write(project_dir.join("src").join("main.rs"), "fn main() {}\n")?;
+    fs_err::write(
+        project_dir.join("Cargo.toml"),
+        indoc!(
+            r#"
+            [package]
+            name = "policy-file"
+            version = "0.1.0"
+            edition = "2021"
+            "#
+        ),
+    )?;
+    fs_err::write(
+        project_dir.join("pyproject.toml"),
+        indoc!(
+            r#"
+            [build-system]
+            requires = ["maturin>=1.0,<2.0"]
+            build-backend = "maturin"
+
+            [project]
+            name = "policy-file"
+
+            [tool.maturin]
+            bindings = "bin"
+            policy-file = "policies.json"
+            compatibility = "manylinux2039"
+            auditwheel = "skip"
+            "#
+        ),
+    )?;
+    fs_err::write(
+        project_dir.join("policies.json"),
+        indoc!(
+            r#"
+            [
+                {
+                    "name": "manylinux_2_39",
+                    "aliases": ["manylinux2039"],
+                    "priority": 50,
+                    "symbol_versions": {},
+                    "lib_whitelist": ["libc.so.6"],
+                    "blacklist": {}
+                }
+            ]
+            "#
+        ),
+    )?;
+
+    let build = |platform_tag: Vec<String>| {
+        BuildOptions {
+            out: Some(Path::new("test-crates").join("wheels").join(unique_name)),
+            cargo: CargoOptions {
+                manifest_path: Some(project_dir.join("Cargo.toml")),
+                quiet: true,
+                target_dir: Some(PathBuf::from(format!("test-crates/targets/{unique_name}"))),
+                ..Default::default()
+            },
+            platform_tag,
+            ..Default::default()
+        }
+        .into_build_context(false, false, false)
+    };
+    let manylinux_2_39 = PlatformTag::Manylinux { x: 2, y: 39 };
+
+    let build_context = build(Vec::new())?;
+    assert_eq!(build_context.platform_tag, vec![manylinux_2_39]);
+    assert_eq!(
+        build_context.policies.aliases(&manylinux_2_39),
+        vec!["manylinux2039".to_string()]
+    );
+    let wheels = build_context.build_wheels()?;
+    let filename = wheels[0].path.file_name().unwrap().to_str().unwrap();
+    let arch = build_context.target.get_platform_arch()?;
+    assert!(
+        filename.ends_with(&format!("-manylinux_2_39_{arch}.manylinux2039_{arch}.whl")),
+        "{filename}"
+    );
+
+    let build_context = build(vec!["manylinux2039".to_string()])?;
+    assert_eq!(build_context.platform_tag, vec![manylinux_2_39]);
+    Ok(())
+}
+
+/// Retags a wheel with a binary, which must be rejected for a platform tag it doesn't comply with
+pub fn check_retag(package: impl AsRef<Path>, unique_name: &str) -> Result<()> {
+    let build_options = BuildOptions {
+        out: Some(Path::new("test-crates").join("wheels").join(unique_name)),
+        cargo: CargoOptions {
+            manifest_path: Some(package.as_ref().join("Cargo.toml")),
+            quiet: true,
+            target_dir: Some(PathBuf::from(format!("test-crates/targets/{unique_name}"))),
+            ..Default::default()
+        },
+        platform_tag: vec![PlatformTag::Linux.to_string()],
+        ..Default::default()
+    };
+    let build_context = build_options.into_build_context(false, false, false)?;
+    let wheels = build_context.build_wheels()?;
+    let wheel = &wheels[0].path;
+    let arch = build_context.target.get_platform_arch()?;
+    let retag_out = Path::new("test-crates")
+        .join("wheels")
+        .join(format!("{unique_name}_retagged"));
+
+    // The extension module links glibc, which musllinux doesn't allow
+    let options = RetagOptions::try_parse_from([
+        OsStr::new("retag"),
+        wheel.as_os_str(),
+        OsStr::new("--platform-tag"),
+        OsStr::new(&format!("musllinux_1_2_{arch}")),
+        OsStr::new("--out"),
+        retag_out.as_os_str(),
+    ])?;
+    let err = retag(&options).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("libc.so.6"),
+        "unexpected error: {err:#}"
+    );
+
+    let options = RetagOptions::try_parse_from([
+        OsStr::new("retag"),
+        wheel.as_os_str(),
+        OsStr::new("--build-tag"),
+        OsStr::new("1"),
+        OsStr::new("--out"),
+        retag_out.as_os_str(),
+    ])?;
+    let retagged = retag(&options)?;
+    let filename = retagged[0].file_name().unwrap().to_str().unwrap();
+    assert!(
+        filename.contains("-1-"),
+        "{filename} is missing the build tag"
+    );
+    check_wheel(&retagged[0])?;
+    Ok(())
+}
+
+/// Builds the wheels for two interpreters concurrently and checks that they match a sequential
+/// build, in the same order
+pub fn check_interpreter_jobs(package: impl AsRef<Path>, unique_name: &str) -> Result<()> {
+    let cargo = CargoOptions {
+        manifest_path: Some(package.as_ref().join("Cargo.toml")),
+        quiet: true,
+        target_dir: Some(PathBuf::from(format!("test-crates/targets/{unique_name}"))),
+        ..Default::default()
+    };
+    let found = BuildOptions {
+        find_interpreter: true,
+        cargo: cargo.clone(),
+        ..Default::default()
+    }
+    .into_build_context(false, false, false)?
+    .interpreter;
+    if found.len() < 2 {
+        eprintln!("⚠️ Skipping {unique_name}, it needs two interpreters");
+        return Ok(());
+    }
+    let interpreters: Vec<PathBuf> = found[..2]
+        .iter()
+        .map(|interpreter| interpreter.executable.clone())
+        .collect();
+
+    let mut results = Vec::new();
+    for interpreter_jobs in [1, 2] {
+        let build_options = BuildOptions {
+            out: Some(
+                Path::new("test-crates")
+                    .join("wheels")
+                    .join(format!("{unique_name}_{interpreter_jobs}")),
+            ),
+            cargo: cargo.clone(),
+            interpreter: interpreters.clone(),
+            interpreter_jobs: Some(interpreter_jobs),
+            platform_tag: vec![PlatformTag::Linux.to_string()],
+            ..Default::default()
+        };
+        let wheels = build_options
+            .into_build_context(false, false, false)?
+            .build_wheels()?;
+        let mut result = Vec::new();
+        for wheel in wheels {
+            let mut archive = ZipArchive::new(File::open(&wheel.path)?)?;
+            let record_name = archive
+                .file_names()
+                .find(|name| name.ends_with(".dist-info/RECORD"))
+                .context("RECORD is missing")?
+                .to_string();
+            let mut record = String::new();
+            archive.by_name(&record_name)?.read_to_string(&mut record)?;
+            let record_paths: Vec<String> = record
+                .lines()
+                .filter_map(|line| line.split(',').next())
+                .map(ToString::to_string)
+                .collect();
+            result.push((
+                wheel.path.file_name().unwrap().to_os_string(),
+                serde_json::to_value(&wheel.interpreter)?,
+                record_paths,
+            ));
+        }
+        results.push(result);
+    }
+    assert_eq!(results[0].len(), 2);
+    assert_eq!(results[0], results[1]);
+    Ok(())
+}
+
+/// Builds a wheel and a source distribution with a build tag and a local version label
+pub fn check_build_tag_and_local_version(
+    package: impl AsRef<Path>,
+    unique_name: &str,
+) -> Result<()> {
+    let build_options = BuildOptions {
+        out: Some(Path::new("test-crates").join("wheels").join(unique_name)),
+        build_tag: Some("1".to_string()),
+        local_version: Some("cpu".to_string()),
+        cargo: CargoOptions {
+            manifest_path: Some(package.as_ref().join("Cargo.toml")),
+            quiet: true,
+            target_dir: Some(PathBuf::from(format!("test-crates/targets/{unique_name}"))),
+            ..Default::default()
+        },
+        platform_tag: vec![PlatformTag::Linux.to_string()],
+        ..Default::default()
+    };
+    let build_context = build_options.into_build_context(false, false, false)?;
+    let version = build_context.metadata23.version.to_string();
+    // The label is appended to the existing local version
+    assert_eq!(version, "0.1.0+abc123de.cpu");
+
+    let wheels = build_context.build_wheels()?;
+    let wheel = &wheels[0].path;
+    let filename = wheel.file_name().unwrap().to_str().unwrap();
+    assert!(
+        filename.starts_with(&format!("pyo3_pure-{version}-1-")),
+        "{filename}"
+    );
+    // The build tag and the dist-info directory must match the filename and METADATA
+    check_wheel(wheel)?;
+
+    let sdist = build_context
+        .build_source_distribution()?
+        .context("Failed to build source distribution")?;
+    assert_eq!(
+        sdist.path.file_name().unwrap().to_str().unwrap(),
+        format!("pyo3_pure-{version}.tar.gz")
+    );
+    let mut archive = Archive::new(GzDecoder::new(File::open(&sdist.path)?));
+    let mut pyproject_toml = None;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()? == Path::new(&format!("pyo3_pure-{version}/pyproject.toml")) {
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents)?;
+            pyproject_toml = Some(contents);
+        }
+    }
+    let pyproject_toml = pyproject_toml.context("pyproject.toml is missing from the sdist")?;
+    assert!(pyproject_toml.contains("build-tag = \"1\""));
+    assert!(pyproject_toml.contains("local-version = \"cpu\""));
+    Ok(())
+}
+
 pub fn check_wheel_files(
     package: impl AsRef<Path>,
     expected_files: Vec<&str>,
@@ -890,6 +890,69 @@ fn abi3_python_interpreter_args() {
     handle_result(other::abi3_python_interpreter_args());
 }
 
+#[test]
+fn pyo3_reproducible() {
+    handle_result(other::check_reproducible(
+        "test-crates/pyo3-mixed-include-exclude",
+        "pyo3_reproducible",
+    ))
+}
+
+#[test]
+fn pyo3_build_report() {
+    handle_result(other::check_build_report(
+        "test-crates/pyo3-mixed-include-exclude",
+        "pyo3_build_report",
+    ))
+}
+
+#[test]
+fn pyo3_sbom() {
+    handle_result(other::check_sbom("test-crates/pyo3-pure", "pyo3_sbom"))
+}
+
+#[test]
+fn pyo3_third_party_licenses() {
+    handle_result(other::check_third_party_licenses(
+        "test-crates/pyo3-pure",
+        "pyo3_third_party_licenses",
+    ))
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn pyo3_retag() {
+    handle_result(other::check_retag("test-crates/pyo3-pure", "pyo3_retag"))
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn pyo3_interpreter_jobs() {
+    handle_result(other::check_interpreter_jobs(
+        "test-crates/pyo3-mixed",
+        "pyo3_interpreter_jobs",
+    ))
+}
+
+#[test]
+fn pyo3_build_tag_and_local_version() {
+    handle_result(other::check_build_tag_and_local_version(
+        "test-crates/pyo3-pure",
+        "pyo3_build_tag_and_local_version",
+    ))
+}
+
+#[test]
+fn vcs_version() {
+    handle_result(other::check_vcs_version("vcs_version"))
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn policy_file() {
+    handle_result(other::check_policy_file("policy_file"))
+}
+
 #[test]
 fn pyo3_source_date_epoch() {
     env::set_var("SOURCE_DATE_EPOCH", "0");